@@ -0,0 +1,8 @@
+/// Side length of the square tiles in pixels.
+pub const TILE_PX: f32 = 100.;
+/// Side length of the square board in pixels.
+pub const BOARD_PX: f32 = TILE_PX * 8.;
+/// Hitcircle radius for a piece.
+pub const HITCIRCLE_RADIUS: f32 = TILE_PX * 0.4;
+/// Size of a turn in bytes.
+pub const TURN_SIZE: usize = 4;
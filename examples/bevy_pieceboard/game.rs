@@ -0,0 +1,132 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    constants::{HITCIRCLE_RADIUS, TILE_PX},
+    logic::{Board, Tile, Turn},
+    netcode::{NetcodeResource, SendTurn, TurnReceived},
+};
+
+/// The board and its pieces, moved by clicking on them.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ClearColor(Color::srgb_u8(240, 217, 181)))
+            .init_resource::<Board>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    (click_system, receive_turns).before(draw_pieces),
+                    draw_pieces.run_if(resource_changed::<Board>),
+                    draw_hitcircles,
+                ),
+            );
+    }
+}
+
+/// Marks the entities drawing pieces, which are redrawn whenever the board
+/// changes.
+#[derive(Component)]
+struct PieceSprite;
+
+/// The mesh and materials every piece is drawn with.
+#[derive(Resource)]
+struct PieceAssets {
+    circle: Handle<Mesh>,
+    materials: Vec<(Color, Handle<ColorMaterial>)>,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn(Camera2d);
+    for tile in Tile::all().filter(Tile::is_dark) {
+        commands.spawn((
+            Sprite::from_color(Color::srgb_u8(181, 136, 99), Vec2::splat(TILE_PX)),
+            Transform::from_translation(tile.center().extend(0.)),
+        ));
+    }
+    commands.insert_resource(PieceAssets {
+        circle: meshes.add(Circle::new(HITCIRCLE_RADIUS)),
+        materials: [Color::WHITE, Color::BLACK]
+            .into_iter()
+            .map(|color| (color, materials.add(color)))
+            .collect(),
+    });
+}
+
+/// Select or move a piece where the user clicked, if it is their turn.
+fn click_system(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut netcode: ResMut<NetcodeResource>,
+    mut board: ResMut<Board>,
+    mut send: EventWriter<SendTurn>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) || !netcode.get_mut().my_turn() {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.single(), cameras.single()) else {
+        return;
+    };
+    let Some(point) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+
+    if let Some(turn) = board.handle_click(point) {
+        send.write(SendTurn(turn.0));
+    }
+}
+
+/// Play the other player's turns on the board.
+fn receive_turns(mut received: EventReader<TurnReceived>, mut board: ResMut<Board>) {
+    for TurnReceived(turn) in received.read() {
+        board.do_turn_unchecked(Turn(*turn));
+    }
+}
+
+fn draw_pieces(
+    mut commands: Commands,
+    sprites: Query<Entity, With<PieceSprite>>,
+    board: Res<Board>,
+    assets: Res<PieceAssets>,
+) {
+    for entity in &sprites {
+        commands.entity(entity).despawn();
+    }
+    for piece in &board.pieces {
+        let material = assets
+            .materials
+            .iter()
+            .find(|(color, _)| *color == piece.color)
+            .map(|(_, material)| material.clone())
+            .expect("every piece is white or black");
+        commands.spawn((
+            PieceSprite,
+            Mesh2d(assets.circle.clone()),
+            MeshMaterial2d(material),
+            Transform::from_translation(piece.tile.center().extend(1.)),
+        ));
+    }
+}
+
+/// Show where the selected piece can move.
+fn draw_hitcircles(board: Res<Board>, mut gizmos: Gizmos) {
+    if board.selected_idx.is_none() {
+        return;
+    }
+    for tile in Tile::all() {
+        gizmos.circle_2d(
+            tile.center(),
+            HITCIRCLE_RADIUS,
+            Color::srgba_u8(250, 250, 200, 80),
+        );
+    }
+}
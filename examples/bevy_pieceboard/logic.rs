@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+
+use crate::constants::{BOARD_PX, HITCIRCLE_RADIUS, TILE_PX, TURN_SIZE};
+
+/// A turn, encoded the same way as the `pieceboard` example's, so the two
+/// can play each other.
+pub struct Turn(pub [u8; TURN_SIZE]);
+
+impl From<(Tile, Tile)> for Turn {
+    /// A turn that encodes (src, dest).
+    fn from((src, dest): (Tile, Tile)) -> Self {
+        Self([src.rank, src.file, dest.rank, dest.file])
+    }
+}
+
+impl From<Turn> for (Tile, Tile) {
+    fn from(turn: Turn) -> Self {
+        let [src_rank, src_file, dest_rank, dest_file] = turn.0;
+        (
+            Tile {
+                rank: src_rank,
+                file: src_file,
+            },
+            Tile {
+                rank: dest_rank,
+                file: dest_file,
+            },
+        )
+    }
+}
+
+/// A tile of the board.
+///
+/// The rank must be within `1..=8` and the file within `b'a'..=b'h'`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    rank: u8,
+    file: u8,
+}
+
+impl Tile {
+    /// Every tile of the board.
+    pub fn all() -> impl Iterator<Item = Tile> {
+        (1..=8).flat_map(|rank| (b'a'..=b'h').map(move |file| Tile { rank, file }))
+    }
+
+    /// The center of this tile, in world coordinates. The board is centered
+    /// on the origin.
+    pub fn center(&self) -> Vec2 {
+        let x_offset = f32::from(self.file - b'a');
+        let y_offset = f32::from(self.rank - 1);
+        Vec2::new(
+            TILE_PX / 2. + x_offset * TILE_PX - BOARD_PX / 2.,
+            TILE_PX / 2. + y_offset * TILE_PX - BOARD_PX / 2.,
+        )
+    }
+
+    /// Whether the tile is dark, like a1.
+    pub fn is_dark(&self) -> bool {
+        (self.rank + self.file - b'a') % 2 == 1
+    }
+
+    /// Whether the world coordinates are within this tile's hitcircle.
+    fn collidepoint(&self, point: Vec2) -> bool {
+        self.center().distance_squared(point) < HITCIRCLE_RADIUS.powi(2)
+    }
+
+    /// The tile whose hitcircle contains the world coordinates, if any.
+    fn under(point: Vec2) -> Option<Tile> {
+        Self::all().find(|tile| tile.collidepoint(point))
+    }
+}
+
+/// A piece on the board.
+pub struct Piece {
+    pub color: Color,
+    pub tile: Tile,
+}
+
+/// Every piece on the board, and the one the user selected, if any.
+#[derive(Resource)]
+pub struct Board {
+    pub pieces: Vec<Piece>,
+    pub selected_idx: Option<usize>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        let mut pieces = vec![];
+        for (color, rank) in [
+            (Color::WHITE, 1),
+            (Color::WHITE, 2),
+            (Color::BLACK, 7),
+            (Color::BLACK, 8),
+        ] {
+            for file in b'a'..=b'h' {
+                pieces.push(Piece {
+                    color,
+                    tile: Tile { rank, file },
+                })
+            }
+        }
+
+        Self {
+            pieces,
+            selected_idx: None,
+        }
+    }
+}
+
+impl Board {
+    pub fn do_turn_unchecked(&mut self, turn: Turn) {
+        let (src, dest): (Tile, Tile) = turn.into();
+        assert!(self.selected_idx.is_none());
+        let piece_idx = self
+            .pieces
+            .iter()
+            .position(|piece| piece.tile == src)
+            .expect("unchecked invariant is this");
+        self.move_piece(piece_idx, dest);
+    }
+
+    /// Select the piece under a click at the world coordinates, or move the
+    /// selected piece there, returning the turn if it moved.
+    pub fn handle_click(&mut self, point: Vec2) -> Option<Turn> {
+        match self.selected_idx.take() {
+            Some(src_piece_idx) => {
+                let src = self.pieces[src_piece_idx].tile;
+                let dest = Tile::under(point).filter(|&dest| dest != src)?;
+                self.move_piece(src_piece_idx, dest);
+                Some(Turn::from((src, dest)))
+            }
+            None => {
+                self.selected_idx = self
+                    .pieces
+                    .iter()
+                    .position(|piece| piece.tile.collidepoint(point));
+                None
+            }
+        }
+    }
+
+    /// Move a piece to `dest`, capturing anything already there.
+    fn move_piece(&mut self, piece_idx: usize, dest: Tile) {
+        let mut piece = self.pieces.swap_remove(piece_idx);
+        piece.tile = dest;
+        self.pieces.retain(|piece| piece.tile != dest);
+        self.pieces.push(piece);
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::{prelude::*, window::WindowResolution};
+use sfn_tpn::{Config, NetcodeInterface};
+use tokio::sync::oneshot;
+
+pub mod constants;
+pub mod game;
+mod logic;
+pub mod netcode;
+
+use constants::{BOARD_PX, TURN_SIZE};
+use game::GamePlugin;
+use netcode::{NetcodePlugin, NetcodeResource};
+
+async fn get_netcode_interface() -> Result<NetcodeInterface<TURN_SIZE>, String> {
+    /// Return whether our process is a client.
+    ///
+    /// If not, we must be the server.
+    ///
+    /// Decides based on command line arguments. If no arguments
+    /// are supplied, we assume the user wants the process to be
+    /// a server.
+    fn is_client() -> Result<bool, String> {
+        let mut is_client = false;
+        let mut is_server = false;
+        for arg in std::env::args() {
+            if arg == "client" {
+                is_client = true;
+            }
+            if arg == "server" {
+                is_server = true;
+            }
+        }
+        if is_client && is_server {
+            Err("This process cannot be both the client and the server.".to_string())
+        } else {
+            Ok(is_client)
+        }
+    }
+
+    /// Gets the first ticket string from the command line arguments.
+    fn ticket() -> Result<String, String> {
+        for arg in std::env::args() {
+            if let Some(("--ticket", t)) = arg.split_once("=") {
+                return Ok(t.to_string());
+            }
+        }
+
+        Err("No ticket provided. Clients must provide a ticket to find a server.".to_string())
+    }
+
+    if is_client()? {
+        Ok(NetcodeInterface::new(Config::Ticket(ticket()?)))
+    } else {
+        let (send, recv) = oneshot::channel();
+        let net = NetcodeInterface::<TURN_SIZE>::new(Config::TicketSender(send));
+        println!(
+            "hosting game. another player may join with \n\n\
+            cargo run --example bevy_pieceboard client --ticket={}",
+            recv.await.unwrap()
+        );
+        Ok(net)
+    }
+}
+
+// the interface must be created within the tokio runtime, which keeps running
+// its protocol in the background while bevy runs on this thread.
+#[tokio::main]
+pub async fn main() -> Result<AppExit, String> {
+    let netcode = get_netcode_interface().await?;
+
+    Ok(App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "movable pieces on board".to_string(),
+                resolution: WindowResolution::new(BOARD_PX, BOARD_PX),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .insert_resource(NetcodeResource::new(netcode))
+        .add_plugins((NetcodePlugin, GamePlugin))
+        .run())
+}
@@ -0,0 +1,73 @@
+//! A plugin connecting the game to the other player.
+//!
+//! The game never touches the [`NetcodeInterface`] itself. It writes a
+//! [`SendTurn`] event when the user moves, and reads a [`TurnReceived`] event
+//! when the other player does.
+
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use sfn_tpn::{NetcodeInterface, TurnPoll};
+
+use crate::constants::TURN_SIZE;
+
+/// The interface to the other player, as a resource.
+///
+/// Resources must be [`Sync`], and the interface is only [`Send`], so it is
+/// kept behind a mutex. Systems get it with [`ResMut`], which never has to
+/// lock.
+#[derive(Resource)]
+pub struct NetcodeResource(Mutex<NetcodeInterface<TURN_SIZE>>);
+
+impl NetcodeResource {
+    pub fn new(netcode: NetcodeInterface<TURN_SIZE>) -> Self {
+        Self(Mutex::new(netcode))
+    }
+
+    pub fn get_mut(&mut self) -> &mut NetcodeInterface<TURN_SIZE> {
+        self.0
+            .get_mut()
+            .expect("nothing panics while holding the lock")
+    }
+}
+
+/// Send this turn to the other player.
+#[derive(Event)]
+pub struct SendTurn(pub [u8; TURN_SIZE]);
+
+/// The other player sent this turn.
+#[derive(Event)]
+pub struct TurnReceived(pub [u8; TURN_SIZE]);
+
+/// Polls for the other player's turns every frame, and sends the user's.
+///
+/// Insert a [`NetcodeResource`] before running the app.
+pub struct NetcodePlugin;
+
+impl Plugin for NetcodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SendTurn>()
+            .add_event::<TurnReceived>()
+            .add_systems(Update, (receive_turn_system, send_turn_system));
+    }
+}
+
+/// Pass the other player's turn on to the game, once it arrives.
+fn receive_turn_system(
+    mut netcode: ResMut<NetcodeResource>,
+    mut received: EventWriter<TurnReceived>,
+) {
+    let netcode = netcode.get_mut();
+    if !netcode.my_turn()
+        && let TurnPoll::Ready(turn) = netcode.try_recv_turn()
+    {
+        received.write(TurnReceived(turn));
+    }
+}
+
+/// Send every turn the game made this frame.
+fn send_turn_system(mut netcode: ResMut<NetcodeResource>, mut to_send: EventReader<SendTurn>) {
+    for SendTurn(turn) in to_send.read() {
+        netcode.get_mut().send_turn(turn);
+    }
+}
@@ -4,7 +4,7 @@ use ggez::{
     graphics::{Canvas, Color, DrawMode, Mesh, MeshBuilder, Rect},
     input::mouse::MouseButton,
 };
-use sfn_tpn::{Config, NetcodeInterface};
+use sfn_tpn::{Config, NetcodeInterface, TurnPoll};
 use tokio::sync::oneshot;
 
 use crate::{
@@ -129,7 +129,7 @@ impl GameState {
 impl event::EventHandler for GameState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
         if !self.netcode.my_turn()
-            && let Ok(turn) = self.netcode.try_recv_turn()
+            && let TurnPoll::Ready(turn) = self.netcode.try_recv_turn()
         {
             self.pieces.do_turn_unchecked(Turn(turn));
             self.pieces_mesh = self.pieces.get_mesh(ctx)?;
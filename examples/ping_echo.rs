@@ -43,13 +43,15 @@ fn ticket() -> Result<String, String> {
     Err("No ticket provided. Clients must provide a ticket to find a server.".to_string())
 }
 
-/// Naively poll `f` with an argument `a: &mut A` until it returns `Ok`.
-async fn wait_for<A, T>(f: fn(&mut A) -> Result<T, ()>, a: &mut A) -> T {
-    loop {
-        if let Ok(t) = f(a) {
-            return t;
-        }
-        sleep(Duration::from_secs(1)).await;
+/// Wait for the other player's turn. Neither player ever passes, so there
+/// always is one, unless they disconnected.
+async fn recv_turn<const SIZE: usize>(
+    netcode: &mut NetcodeInterface<SIZE>,
+) -> Result<[u8; SIZE], String> {
+    match netcode.recv_turn().await {
+        Ok(Some(turn)) => Ok(turn),
+        Ok(None) => Err("the other player passed".to_string()),
+        Err(e) => Err(e.to_string()),
     }
 }
 
@@ -61,10 +63,7 @@ async fn main() -> Result<(), String> {
         netcode.send_turn(b"ping");
         println!("Client sent ping");
 
-        assert_eq!(
-            b"pong",
-            &wait_for(NetcodeInterface::try_recv_turn, &mut netcode).await
-        );
+        assert_eq!(b"pong", &recv_turn(&mut netcode).await?);
         println!("Client recieved pong");
 
         let mut counter = 0;
@@ -74,11 +73,11 @@ async fn main() -> Result<(), String> {
             netcode.send_turn(&bytes);
             println!("Client sent {bytes:?}");
 
-            assert_eq!(
-                &bytes,
-                &wait_for(NetcodeInterface::try_recv_turn, &mut netcode).await
+            assert_eq!(&bytes, &recv_turn(&mut netcode).await?);
+            println!(
+                "Client got {bytes:?} back ({:.2} turns/s)",
+                netcode.turn_rate()
             );
-            println!("Client got {bytes:?} back");
 
             counter += 1;
         }
@@ -93,17 +92,14 @@ async fn main() -> Result<(), String> {
             recv.await.unwrap()
         );
 
-        assert_eq!(
-            b"ping",
-            &wait_for(NetcodeInterface::try_recv_turn, &mut netcode).await
-        );
+        assert_eq!(b"ping", &recv_turn(&mut netcode).await?);
         println!("Server received ping");
 
         netcode.send_turn(b"pong");
         println!("Server sent pong");
 
         loop {
-            let bytes = wait_for(NetcodeInterface::try_recv_turn, &mut netcode).await;
+            let bytes = recv_turn(&mut netcode).await?;
             println!("Server received: {:?}", &bytes);
 
             netcode.send_turn(&bytes);
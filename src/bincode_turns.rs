@@ -0,0 +1,56 @@
+//! Sending game-specific structs as turns, encoded with bincode, so games do
+//! not have to pack them into bytes themselves.
+
+use bincode::{
+    config::{self, Configuration},
+    error::EncodeError,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{NetcodeError, NetcodeInterface, TurnPoll};
+
+/// How turns are encoded. Both players must use the same, so it is fixed.
+const CONFIG: Configuration = config::standard();
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Encode `turn` with bincode, then send it like
+    /// [`send_turn_checked`](NetcodeInterface::send_turn_checked).
+    ///
+    /// The encoded turn is padded with zeros up to `SIZE` bytes. Returns
+    /// [`NetcodeError::TurnTooLarge`] without sending anything if it does not
+    /// fit, or the same errors as `send_turn_checked`.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub async fn send_turn_bincode<T: Serialize>(&mut self, turn: &T) -> Result<(), NetcodeError> {
+        let mut bytes = [0; SIZE];
+        match bincode::serde::encode_into_slice(turn, &mut bytes, CONFIG) {
+            Ok(_) => {}
+            Err(EncodeError::UnexpectedEnd) => return Err(NetcodeError::TurnTooLarge),
+            Err(e) => return Err(NetcodeError::MalformedTurn(e.to_string())),
+        }
+        self.send_turn_checked(&bytes).await
+    }
+
+    /// Check if the other player has sent a turn, like
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn), and decode it with
+    /// bincode.
+    ///
+    /// Returns `Ok(None)` if there is no turn yet, or the other player passed,
+    /// in which case it is the user's turn. Returns
+    /// [`NetcodeError::MalformedTurn`] if the turn does not decode as a `T`.
+    /// The turn was still received, so the game may want to
+    /// [reject](NetcodeInterface::reject_turn) it.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub fn try_recv_turn_bincode<T: DeserializeOwned>(
+        &mut self,
+    ) -> Result<Option<T>, NetcodeError> {
+        let TurnPoll::Ready(bytes) = self.try_recv_turn() else {
+            return Ok(None);
+        };
+        // the padding after the encoded turn is ignored.
+        bincode::serde::decode_from_slice(&bytes, CONFIG)
+            .map(|(turn, _)| Some(turn))
+            .map_err(|e| NetcodeError::MalformedTurn(e.to_string()))
+    }
+}
@@ -0,0 +1,397 @@
+//! A builder for configuring a [`NetcodeInterface`].
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use iroh::RelayUrl;
+
+use crate::{
+    ChatLog, Config, DiffEncoder, LatencyThresholds, MAX_SETUP_LEN, NetcodeInterface, Reconnect,
+    Role, TimeControl, TurnTransformer, compression::Compression, metadata::Metadata,
+    protocol::Transport,
+};
+
+/// Builder for a [`NetcodeInterface`] with non-default options.
+///
+/// Create one with [`NetcodeInterface::builder`].
+pub struct NetcodeInterfaceBuilder<const SIZE: usize> {
+    pub(crate) transport: Transport,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) reconnect: Reconnect,
+    pub(crate) check_internet: bool,
+    /// `None` to check the relay we would use.
+    pub(crate) internet_check_target: Option<String>,
+    /// `None` to use n0's public relays.
+    pub(crate) relay_url: Option<RelayUrl>,
+    pub(crate) turn_recv_timeout: Option<Duration>,
+    pub(crate) turn_deadline: Option<Duration>,
+    pub(crate) latency_thresholds: LatencyThresholds,
+    pub(crate) turn_rate_window: usize,
+    pub(crate) time_control: Option<TimeControl>,
+    /// `None` if the first move is random.
+    pub(crate) first_move: Option<Role>,
+    pub(crate) max_spectators: Option<u8>,
+    pub(crate) encoder: Option<Box<dyn DiffEncoder<SIZE>>>,
+    pub(crate) compression: Option<Compression>,
+    /// The turn size both players agree on in the handshake, only smaller
+    /// than `SIZE` for a [`DynNetcodeInterface`](crate::DynNetcodeInterface).
+    pub(crate) size: usize,
+    /// How many bytes of each turn the user sends, then receives.
+    pub(crate) turn_sizes: (usize, usize),
+    pub(crate) transformer: Option<Box<dyn TurnTransformer<SIZE>>>,
+    pub(crate) setup: Option<Vec<u8>>,
+    pub(crate) auto_end_on_double_pass: bool,
+    pub(crate) metadata: Metadata,
+    pub(crate) state_hash: Option<Box<dyn Fn() -> u64 + Send>>,
+    pub(crate) chat_log: Option<Arc<Mutex<ChatLog>>>,
+    pub(crate) record_history: bool,
+    pub(crate) replay: Option<Box<dyn Write + Send>>,
+}
+
+impl<const SIZE: usize> NetcodeInterfaceBuilder<SIZE> {
+    /// Create a builder with the default options.
+    pub fn new(config: Config) -> Self {
+        Self::with_transport(Transport::Config(config))
+    }
+
+    /// Create a builder with the default options, for a game that reaches
+    /// the other player over `transport`.
+    pub(crate) fn with_transport(transport: Transport) -> Self {
+        Self {
+            transport,
+            connect_timeout: None,
+            check_internet: false,
+            internet_check_target: None,
+            relay_url: None,
+            reconnect: Reconnect::Disabled,
+            turn_recv_timeout: None,
+            turn_deadline: None,
+            latency_thresholds: LatencyThresholds::default(),
+            turn_rate_window: 10,
+            time_control: None,
+            first_move: Some(Role::Client),
+            max_spectators: None,
+            encoder: None,
+            compression: None,
+            size: SIZE,
+            turn_sizes: (SIZE, SIZE),
+            transformer: None,
+            setup: None,
+            auto_end_on_double_pass: false,
+            metadata: Metadata::default(),
+            state_hash: None,
+            chat_log: None,
+            record_history: false,
+            replay: None,
+        }
+    }
+
+    /// How long [`wait_for_connection`](NetcodeInterface::wait_for_connection)
+    /// waits for the other player before giving up.
+    ///
+    /// By default, it waits forever.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// What to do if the connection to the other player drops mid-game,
+    /// like when a laptop goes to sleep.
+    ///
+    /// Once reconnected, any moves that were lost are sent again, so the
+    /// game picks up where it left off. Games played over a
+    /// [`Session`](crate::Session) share its connection, so they are never
+    /// reconnected. By default, the game stays
+    /// [`Disconnected`](crate::ConnectionState::Disconnected).
+    pub fn reconnect(mut self, strategy: Reconnect) -> Self {
+        self.reconnect = strategy;
+        self
+    }
+
+    /// Check that we can reach the internet before looking for the other
+    /// player, rather than waiting for a connection that can never come.
+    ///
+    /// We try to open a TCP connection to the relay server we would use for
+    /// a few seconds: the one set with `relay_url`, with the `iroh-relay`
+    /// feature, or else n0's North American relay. iroh contacts it anyway,
+    /// so the check reaches no one new. If we cannot, the connection state becomes
+    /// [`Disconnected`](crate::ConnectionState::Disconnected) with
+    /// [`NetcodeError::NoInternet`](crate::NetcodeError::NoInternet). Use
+    /// [`check_internet_at`](Self::check_internet_at) to try somewhere else,
+    /// say on a network that only lets some hosts through. Games played over
+    /// a [`Session`](crate::Session) share its connection, so they are never
+    /// checked. By default, nothing is checked.
+    pub fn check_internet(mut self) -> Self {
+        self.check_internet = true;
+        self
+    }
+
+    /// Like [`check_internet`](Self::check_internet), but try to reach
+    /// `target` instead of the relay server.
+    ///
+    /// `target` is a `host:port`, like `"example.com:443"` or
+    /// `"[2001:db8::1]:443"`, and the host is looked up with the system's
+    /// resolver first. Any TCP connection that opens counts, whatever the
+    /// port speaks.
+    pub fn check_internet_at(mut self, target: impl Into<String>) -> Self {
+        self.check_internet = true;
+        self.internet_check_target = Some(target.into());
+        self
+    }
+
+    /// How long to wait for the other player's turn before
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn) returns
+    /// [`TurnPoll::WaitingTooLong`](crate::TurnPoll::WaitingTooLong) instead of
+    /// [`TurnPoll::Pending`](crate::TurnPoll::Pending).
+    ///
+    /// This never disconnects. By default, we never report waiting too long.
+    pub fn turn_recv_timeout(mut self, timeout: Duration) -> Self {
+        self.turn_recv_timeout = Some(timeout);
+        self
+    }
+
+    /// How long each player has to move once it is their turn, after which
+    /// [`try_recv_timeout`](NetcodeInterface::try_recv_timeout) reports a
+    /// [`TurnTimedOut`](crate::TurnTimedOut) on both machines.
+    ///
+    /// The clock starts when the turn becomes available to the player, and
+    /// stops when they send a turn or pass. Each player's clock runs on their
+    /// own machine, which tells the other player when it runs out. The game
+    /// decides what a timeout means, like forfeiting or passing. By default,
+    /// there is no deadline.
+    pub fn turn_deadline(mut self, deadline: Duration) -> Self {
+        self.turn_deadline = Some(deadline);
+        self
+    }
+
+    /// The round-trip times that
+    /// [`peer_latency_class`](NetcodeInterface::peer_latency_class) bins the
+    /// connection by. See [`LatencyThresholds`] for the defaults.
+    pub fn latency_thresholds(mut self, thresholds: LatencyThresholds) -> Self {
+        self.latency_thresholds = thresholds;
+        self
+    }
+
+    /// How many of the latest moves and passes
+    /// [`turn_rate`](NetcodeInterface::turn_rate) averages over. Defaults
+    /// to 10.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `plies` is less than 2, since a rate needs at least two
+    /// plies to measure the time between.
+    pub fn turn_rate_window(mut self, plies: usize) -> Self {
+        assert!(plies >= 2);
+        self.turn_rate_window = plies;
+        self
+    }
+
+    /// Play each game with chess clocks.
+    ///
+    /// Each player's clock runs while it is their turn, and
+    /// [`clocks`](NetcodeInterface::clocks) shows how much time both have
+    /// left. A player's own machine keeps their clock, and sends the time
+    /// they have left along with each move, so both players agree on it. If
+    /// it runs out, their flag falls, which both players are told about by
+    /// [`try_recv_flag_fall`](NetcodeInterface::try_recv_flag_fall).
+    ///
+    /// Both players must configure the same time control, or the handshake
+    /// fails with [`NetcodeError::HandshakeFailed`](crate::NetcodeError::HandshakeFailed).
+    /// By default, there are no clocks.
+    pub fn time_control(mut self, time_control: TimeControl) -> Self {
+        self.time_control = Some(time_control);
+        self
+    }
+
+    /// Which player moves first.
+    ///
+    /// Both players must configure the same value, or the handshake fails with
+    /// [`NetcodeError::HandshakeFailed`](crate::NetcodeError::HandshakeFailed).
+    /// By default, the client moves first.
+    pub fn first_move(mut self, role: Role) -> Self {
+        self.first_move = Some(role);
+        self
+    }
+
+    /// Let a coin flip decide which player moves first.
+    ///
+    /// The coin is flipped with the
+    /// [`shared_seed`](NetcodeInterface::shared_seed), so neither player can
+    /// bias the result. Each player commits to their half of the seed before
+    /// seeing the other's, and the other player checks it against the
+    /// commitment, so the flip is fair without trusting a third party. Use
+    /// [`moves_first`](NetcodeInterface::moves_first) to find out the result.
+    ///
+    /// Both players must use this option, or the handshake fails.
+    pub fn random_first_move(mut self) -> Self {
+        self.first_move = None;
+        self
+    }
+
+    /// Let up to `max` spectators watch the game at once.
+    ///
+    /// Only has an effect for the host, which will generate a
+    /// [`spectator_ticket`](NetcodeInterface::spectator_ticket). By default,
+    /// spectators are not allowed.
+    pub fn allow_spectators(mut self, max: u8) -> Self {
+        self.max_spectators = Some(max);
+        self
+    }
+
+    /// Encode turns relative to the previous turn before sending them, for
+    /// example with a [`TurnDiff`](crate::TurnDiff).
+    ///
+    /// Both players must use the same encoder, or the handshake fails. By
+    /// default, turns are sent as-is.
+    pub fn diff_encoder(mut self, encoder: impl DiffEncoder<SIZE>) -> Self {
+        self.encoder = Some(Box::new(encoder));
+        self
+    }
+
+    /// Only send the first `send` bytes of each of the user's turns, and
+    /// receive `recv` bytes of each of the other player's, for games where
+    /// the players' moves differ in size.
+    ///
+    /// The rest of each turn the user sends is dropped, and the rest of each
+    /// turn they receive is zeroed. The other player must
+    /// configure the same sizes the other way around, or the handshake fails
+    /// with [`NetcodeError::HandshakeFailed`](crate::NetcodeError::HandshakeFailed).
+    /// By default, whole turns are sent both ways.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either size is larger than `SIZE`.
+    pub fn turn_sizes(mut self, send: usize, recv: usize) -> Self {
+        assert!(send <= SIZE && recv <= SIZE);
+        self.turn_sizes = (send, recv);
+        self
+    }
+
+    /// Pass each turn through `transformer` after the user sends it, and
+    /// before the user receives it.
+    ///
+    /// The [`history`](NetcodeInterface::history) and
+    /// [`replay`](NetcodeInterfaceBuilder::replay) keep turns as the game sent
+    /// or received them, while spectators see them as they were sent over
+    /// the connection. By default, turns are passed along unchanged.
+    pub fn turn_transformer(mut self, transformer: impl TurnTransformer<SIZE>) -> Self {
+        self.transformer = Some(Box::new(transformer));
+        self
+    }
+
+    /// End the game in a [`GameOutcome::Draw`](crate::GameOutcome::Draw) when
+    /// both players [pass](NetcodeInterface::pass_turn) in a row.
+    ///
+    /// Both players should use the same value. By default, passing never ends
+    /// the game.
+    pub fn auto_end_on_double_pass(mut self) -> Self {
+        self.auto_end_on_double_pass = true;
+        self
+    }
+
+    /// Hash the game's state, to catch the players' states drifting apart.
+    ///
+    /// `state_hash` is called after every move the user sends, and the hash
+    /// is sent along with it. Once the user receives the other player's move,
+    /// it is called again to compare. A mismatch is reported by
+    /// [`try_recv_divergence`](NetcodeInterface::try_recv_divergence). By
+    /// default, nothing is hashed or sent.
+    pub fn state_hash(mut self, state_hash: impl Fn() -> u64 + Send + 'static) -> Self {
+        self.state_hash = Some(Box::new(state_hash));
+        self
+    }
+
+    /// Record every message sent or received with
+    /// [`send_message`](NetcodeInterface::send_message) and
+    /// [`try_recv_message`](NetcodeInterface::try_recv_message) in `log`.
+    ///
+    /// Messages are recorded as they arrive, even if the game has yet to
+    /// receive them. By default, messages are not recorded.
+    pub fn chat_log(mut self, log: Arc<Mutex<ChatLog>>) -> Self {
+        self.chat_log = Some(log);
+        self
+    }
+
+    /// Record every turn sent or received in the
+    /// [`history`](NetcodeInterface::history).
+    ///
+    /// The history is kept for as long as the interface, across games, so it
+    /// grows by a [`TurnRecord`](crate::TurnRecord) of a little more than
+    /// `SIZE` bytes with every turn. By default, nothing is recorded.
+    pub fn record_history(mut self, record: bool) -> Self {
+        self.record_history = record;
+        self
+    }
+
+    /// Write a replay of every turn sent or received to `writer`, such as a
+    /// [`File`](std::fs::File).
+    ///
+    /// The format is described in [`Replay`](crate::Replay), which reads it
+    /// back. If writing fails, the replay stops, but the game goes on, and
+    /// the error is kept in
+    /// [`replay_error`](NetcodeInterface::replay_error). By default, no
+    /// replay is written.
+    pub fn replay(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.replay = Some(Box::new(writer));
+        self
+    }
+
+    /// Metadata, like the game's name or rules version, that both players must
+    /// agree on.
+    ///
+    /// During the handshake, every key set here by either player must have the
+    /// same value for both, whether the other player set it with `metadata`
+    /// or [`soft_metadata`](NetcodeInterfaceBuilder::soft_metadata).
+    /// Otherwise, including if the other player did not set the key at all,
+    /// the handshake fails with
+    /// [`NetcodeError::MetadataMismatch`](crate::NetcodeError::MetadataMismatch).
+    pub fn metadata(mut self, entries: &[(&str, &str)]) -> Self {
+        for (key, value) in entries {
+            self.metadata.soft.remove(*key);
+            self.metadata
+                .hard
+                .insert(key.to_string(), value.to_string());
+        }
+        self
+    }
+
+    /// Metadata, like the user's name, avatar, or locale, that is shared with
+    /// the other player but never compared.
+    ///
+    /// It is sent during the handshake, before the first turn, and the other
+    /// player can read it with
+    /// [`peer_metadata`](NetcodeInterface::peer_metadata) once connected.
+    pub fn soft_metadata(mut self, entries: &[(&str, &str)]) -> Self {
+        for (key, value) in entries {
+            self.metadata.hard.remove(*key);
+            self.metadata
+                .soft
+                .insert(key.to_string(), value.to_string());
+        }
+        self
+    }
+
+    /// Send initial settings for the game, like the board size or variant
+    /// rules, to the client before the first turn.
+    ///
+    /// Only has an effect for the host. The setup can be any length up to
+    /// [`MAX_SETUP_LEN`], regardless of `SIZE`. The client receives it from
+    /// [`await_setup`](NetcodeInterface::await_setup).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the setup is longer than [`MAX_SETUP_LEN`].
+    pub fn setup(mut self, setup: Vec<u8>) -> Self {
+        assert!(setup.len() <= MAX_SETUP_LEN);
+        self.setup = Some(setup);
+        self
+    }
+
+    /// Create the interface.
+    pub fn build(self) -> NetcodeInterface<SIZE> {
+        NetcodeInterface::from_builder(self)
+    }
+}
@@ -0,0 +1,45 @@
+//! A record of the chat messages sent during a session, for post-game review.
+
+use std::time::SystemTime;
+
+use crate::Role;
+
+/// Every chat message sent or received by a
+/// [`NetcodeInterface`](crate::NetcodeInterface) it was given to with
+/// [`chat_log`](crate::NetcodeInterfaceBuilder::chat_log).
+///
+/// With the `serde` feature, the log can be serialized, so games can include
+/// it in saved game files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChatLog {
+    /// The messages, in the order they were sent or received.
+    pub messages: Vec<ChatMessage>,
+}
+
+/// A single message in a [`ChatLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChatMessage {
+    pub sender: Role,
+    /// The message, with any invalid UTF-8 replaced.
+    pub text: String,
+    /// The [`ply`](crate::NetcodeInterface::ply) when the message was sent
+    /// or received.
+    pub turn_number: u64,
+    /// When the message was sent or received. This is wall-clock time, unlike
+    /// an `Instant`, so it still means something once saved.
+    pub timestamp: SystemTime,
+}
+
+impl ChatLog {
+    /// Record a message.
+    pub(crate) fn record(&mut self, sender: Role, message: &[u8], turn_number: u64) {
+        self.messages.push(ChatMessage {
+            sender,
+            text: String::from_utf8_lossy(message).into_owned(),
+            turn_number,
+            timestamp: SystemTime::now(),
+        });
+    }
+}
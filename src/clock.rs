@@ -0,0 +1,293 @@
+//! Chess clocks, for games played with a time control.
+
+use std::time::{Duration, Instant};
+
+use crate::Role;
+
+/// How much time each player has for a whole game, set with
+/// [`time_control`](crate::NetcodeInterfaceBuilder::time_control).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeControl {
+    /// Each player starts with `initial`, and gains `increment` after each of
+    /// their moves.
+    Increment {
+        initial: Duration,
+        increment: Duration,
+    },
+    /// Each player starts with `initial`, and their clock only starts running
+    /// `delay` into each of their turns.
+    Delay { initial: Duration, delay: Duration },
+}
+
+impl TimeControl {
+    fn initial(self) -> Duration {
+        match self {
+            TimeControl::Increment { initial, .. } | TimeControl::Delay { initial, .. } => initial,
+        }
+    }
+}
+
+/// A player ran out of time, so their flag fell.
+///
+/// See [`try_recv_flag_fall`](crate::NetcodeInterface::try_recv_flag_fall).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagFell(pub Role);
+
+/// Both players' clocks in the current game.
+///
+/// Only the player whose clock it is decides how much time they have left.
+/// They send it along with each of their moves, and we take their word for it.
+#[derive(Debug)]
+pub(crate) struct Clocks {
+    time_control: TimeControl,
+    mine: Duration,
+    theirs: Duration,
+    /// When the clock of the player to move started running, if it has since
+    /// the first game started.
+    running_since: Option<Instant>,
+    /// When the game paused, if it is paused.
+    paused_at: Option<Instant>,
+    /// Whether a flag fell in the current game.
+    flag_fell: bool,
+}
+
+impl Clocks {
+    pub fn new(time_control: TimeControl) -> Self {
+        Self {
+            time_control,
+            mine: time_control.initial(),
+            theirs: time_control.initial(),
+            running_since: None,
+            paused_at: None,
+            flag_fell: false,
+        }
+    }
+
+    /// Reset both clocks for a new game.
+    pub fn reset(&mut self) {
+        *self = Self {
+            running_since: self.running_since,
+            paused_at: self.paused_at,
+            ..Self::new(self.time_control)
+        };
+    }
+
+    /// Start the clock of the player to move.
+    pub fn start(&mut self) {
+        // while paused, the clock starts once the game resumes.
+        self.running_since = Some(self.paused_at.unwrap_or_else(Instant::now));
+    }
+
+    /// Stop the running clock until the game resumes.
+    pub fn pause(&mut self, connected_at: Option<Instant>) {
+        self.running_since = self.running_since.or(connected_at);
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
+
+    /// Restart the running clock where it stopped.
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.running_since = self.running_since.map(|since| since + paused_at.elapsed());
+        }
+    }
+
+    /// How much of `clock` is left, if it is the one running. The first turn
+    /// of the first game starts at `connected_at`.
+    fn remaining(&self, clock: Duration, connected_at: Option<Instant>) -> Duration {
+        let Some(since) = self.running_since.or(connected_at) else {
+            return clock;
+        };
+        let elapsed = self
+            .paused_at
+            .unwrap_or_else(Instant::now)
+            .saturating_duration_since(since);
+        let charged = match self.time_control {
+            TimeControl::Increment { .. } => elapsed,
+            TimeControl::Delay { delay, .. } => elapsed.saturating_sub(delay),
+        };
+        clock.saturating_sub(charged)
+    }
+
+    /// The user's time left, then the other player's.
+    pub fn clocks(&self, my_turn: bool, connected_at: Option<Instant>) -> (Duration, Duration) {
+        if self.flag_fell {
+            (self.mine, self.theirs)
+        } else if my_turn {
+            (self.remaining(self.mine, connected_at), self.theirs)
+        } else {
+            (self.mine, self.remaining(self.theirs, connected_at))
+        }
+    }
+
+    /// Stop the user's clock as they move, returning their time left to send
+    /// to the other player, and start theirs.
+    pub fn stop_mine(&mut self, connected_at: Option<Instant>) -> Duration {
+        if !self.flag_fell {
+            self.mine = self.remaining(self.mine, connected_at);
+            if let TimeControl::Increment { increment, .. } = self.time_control {
+                self.mine += increment;
+            }
+        }
+        self.start();
+        self.mine
+    }
+
+    /// Take the other player's word for how much time they have left after
+    /// their move.
+    pub fn set_theirs(&mut self, remaining: Duration) {
+        if !self.flag_fell {
+            self.theirs = remaining;
+        }
+        self.start();
+    }
+
+    /// Return whether the user's flag just fell. It can only fall once a
+    /// game.
+    pub fn check_my_flag(&mut self, my_turn: bool, connected_at: Option<Instant>) -> bool {
+        if self.flag_fell || !my_turn || !self.remaining(self.mine, connected_at).is_zero() {
+            return false;
+        }
+        self.mine = Duration::ZERO;
+        self.flag_fell = true;
+        true
+    }
+
+    /// The other player told us their flag fell.
+    pub fn their_flag_fell(&mut self) {
+        self.theirs = Duration::ZERO;
+        self.flag_fell = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    /// Clocks frozen `elapsed` into the running player's turn, so that how
+    /// much time is left does not depend on how fast the test runs.
+    fn frozen_after(time_control: TimeControl, elapsed: Duration) -> Clocks {
+        let since = Instant::now();
+        Clocks {
+            running_since: Some(since),
+            paused_at: Some(since + elapsed),
+            ..Clocks::new(time_control)
+        }
+    }
+
+    #[test]
+    fn increment_is_added_after_each_move() {
+        let increment = TimeControl::Increment {
+            initial: MINUTE,
+            increment: Duration::from_secs(2),
+        };
+        let mut clocks = frozen_after(increment, Duration::from_secs(10));
+        assert_eq!(clocks.clocks(true, None), (Duration::from_secs(50), MINUTE));
+        assert_eq!(clocks.stop_mine(None), Duration::from_secs(52));
+        // now it is their clock that runs, from where ours stopped.
+        assert_eq!(
+            clocks.clocks(false, None),
+            (Duration::from_secs(52), MINUTE)
+        );
+    }
+
+    #[test]
+    fn delay_is_not_charged() {
+        let delay = TimeControl::Delay {
+            initial: MINUTE,
+            delay: Duration::from_secs(5),
+        };
+        let clocks = frozen_after(delay, Duration::from_secs(3));
+        assert_eq!(clocks.clocks(true, None).0, MINUTE);
+        let clocks = frozen_after(delay, Duration::from_secs(8));
+        assert_eq!(clocks.clocks(true, None).0, Duration::from_secs(57));
+        assert_eq!(clocks.clocks(false, None).1, Duration::from_secs(57));
+    }
+
+    #[test]
+    fn flag_falls_once_time_runs_out() {
+        let blitz = TimeControl::Increment {
+            initial: Duration::from_secs(10),
+            increment: Duration::from_secs(1),
+        };
+        let mut clocks = frozen_after(blitz, Duration::from_secs(9));
+        assert!(!clocks.check_my_flag(true, None));
+        let mut clocks = frozen_after(blitz, Duration::from_secs(10));
+        assert!(!clocks.check_my_flag(false, None));
+        assert!(clocks.check_my_flag(true, None));
+        assert!(!clocks.check_my_flag(true, None));
+        assert_eq!(
+            clocks.clocks(true, None),
+            (Duration::ZERO, Duration::from_secs(10))
+        );
+        // no increment for a move made after the flag fell.
+        assert_eq!(clocks.stop_mine(None), Duration::ZERO);
+        clocks.set_theirs(Duration::from_secs(3));
+        assert_eq!(clocks.clocks(false, None).1, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn their_flag_falling_stops_both_clocks() {
+        let mut clocks = frozen_after(
+            TimeControl::Increment {
+                initial: MINUTE,
+                increment: Duration::ZERO,
+            },
+            Duration::from_secs(20),
+        );
+        clocks.their_flag_fell();
+        assert_eq!(clocks.clocks(false, None), (MINUTE, Duration::ZERO));
+        assert!(!clocks.check_my_flag(true, None));
+    }
+
+    #[test]
+    fn pausing_stops_the_running_clock() {
+        let mut clocks = Clocks::new(TimeControl::Increment {
+            initial: MINUTE,
+            increment: Duration::ZERO,
+        });
+        clocks.running_since = Some(Instant::now() - Duration::from_secs(5));
+        clocks.pause(None);
+        let paused = clocks.clocks(true, None);
+        assert!(paused.0 <= Duration::from_secs(55));
+        assert_eq!(clocks.clocks(true, None), paused);
+
+        // say the game stayed paused for half a minute, which is not charged.
+        let ago = Duration::from_secs(30);
+        clocks.running_since = clocks.running_since.map(|since| since - ago);
+        clocks.paused_at = clocks.paused_at.map(|paused_at| paused_at - ago);
+        clocks.resume();
+        assert_eq!(clocks.paused_at, None);
+        let resumed = clocks.clocks(true, None).0;
+        assert!(resumed > Duration::from_secs(50) && resumed <= Duration::from_secs(55));
+    }
+
+    #[test]
+    fn the_first_turn_runs_from_the_connection_while_paused() {
+        let mut clocks = Clocks::new(TimeControl::Increment {
+            initial: MINUTE,
+            increment: Duration::ZERO,
+        });
+        let connected_at = Instant::now() - Duration::from_secs(10);
+        clocks.pause(Some(connected_at));
+        assert_eq!(clocks.running_since, Some(connected_at));
+        assert!(clocks.clocks(true, None).0 <= Duration::from_secs(50));
+    }
+
+    #[test]
+    fn moves_and_resets_while_paused_charge_nothing() {
+        let mut clocks = frozen_after(
+            TimeControl::Increment {
+                initial: MINUTE,
+                increment: Duration::ZERO,
+            },
+            Duration::from_secs(20),
+        );
+        clocks.set_theirs(Duration::from_secs(30));
+        assert_eq!(clocks.clocks(true, None), (MINUTE, Duration::from_secs(30)));
+        clocks.reset();
+        assert!(clocks.paused_at.is_some());
+        assert_eq!(clocks.clocks(true, None), (MINUTE, MINUTE));
+    }
+}
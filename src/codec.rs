@@ -0,0 +1,95 @@
+//! Serialization formats for a [`TypedInterface`](crate::TypedInterface)'s
+//! turns.
+
+use std::fmt;
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::NetcodeError;
+
+/// Turns a game's own turn type into bytes and back, for a
+/// [`TypedInterface`](crate::TypedInterface).
+///
+/// Both players must use codecs with the same [`name`](TurnCodec::name), or
+/// the handshake fails with
+/// [`NetcodeError::MetadataMismatch`](crate::NetcodeError::MetadataMismatch).
+pub trait TurnCodec: Send + Sync + 'static {
+    /// A name identifying this codec and its configuration.
+    fn name(&self) -> String;
+
+    /// Append the encoded `turn` to `buf`.
+    fn encode<T: Serialize>(&self, turn: &T, buf: &mut Vec<u8>) -> Result<(), CodecError>;
+
+    /// Decode a turn from the start of `bytes`. Turns are padded with zeros
+    /// up to the turn size, so anything after the turn must be ignored.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// A turn could not be encoded or decoded by a [`TurnCodec`].
+///
+/// Holds a human-readable reason. The interface reports it as a
+/// [`NetcodeError::MalformedTurn`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecError(pub String);
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<CodecError> for NetcodeError {
+    fn from(e: CodecError) -> Self {
+        NetcodeError::MalformedTurn(e.0)
+    }
+}
+
+/// A [`TurnCodec`] encoding turns with bincode's standard configuration.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl TurnCodec for BincodeCodec {
+    fn name(&self) -> String {
+        "bincode-standard".to_string()
+    }
+
+    fn encode<T: Serialize>(&self, turn: &T, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        bincode::serde::encode_into_std_write(turn, buf, bincode::config::standard())
+            .map(|_| ())
+            .map_err(|e| CodecError(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map(|(turn, _)| turn)
+            .map_err(|e| CodecError(e.to_string()))
+    }
+}
+
+/// A [`TurnCodec`] encoding turns with postcard, whose compact encoding suits
+/// small turn sizes.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostcardCodec;
+
+#[cfg(feature = "postcard")]
+impl TurnCodec for PostcardCodec {
+    fn name(&self) -> String {
+        "postcard".to_string()
+    }
+
+    fn encode<T: Serialize>(&self, turn: &T, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        // postcard takes the buffer by value, and hands it back once written.
+        *buf = postcard::to_extend(turn, std::mem::take(buf))
+            .map_err(|e| CodecError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        postcard::from_bytes(bytes).map_err(|e| CodecError(e.to_string()))
+    }
+}
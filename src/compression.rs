@@ -0,0 +1,167 @@
+//! Compressing large moves, like serialized decks or map states, before
+//! sending them.
+//!
+//! A move's whole frame is compressed, then sent inside a
+//! [`Frame::Compressed`], which may in turn be sent in chunks. Small frames
+//! gain little, so they are sent as-is.
+
+use crate::{frame::Frame, protocol::BoxError};
+
+/// The [`metadata`](crate::NetcodeInterfaceBuilder::metadata) key holding
+/// the compression's name, so that both players must compress the same way.
+#[cfg(feature = "zstd")]
+const COMPRESSION_METADATA_KEY: &str = "sfn-tpn/compression";
+
+/// Frames up to this many bytes are never compressed.
+const COMPRESSION_THRESHOLD: usize = 256;
+
+/// How to compress moves, set with
+/// [`compression`](crate::NetcodeInterfaceBuilder::compression).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Compress with zstd at `level`, from 1 to 22. Higher levels compress
+    /// better, but slower. zstd's own default is 3.
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+#[cfg(feature = "zstd")]
+impl<const SIZE: usize> crate::NetcodeInterfaceBuilder<SIZE> {
+    /// Compress large moves, like serialized decks or map states, before
+    /// sending them.
+    ///
+    /// Moves are only compressed if they are larger than a few hundred
+    /// bytes, and get smaller for it, so small turns are sent as-is. Moves
+    /// that decompress to more than 16 MiB are rejected, so the other player
+    /// cannot make us decompress without end. See
+    /// [`compressed_bytes_total`](crate::NetcodeInterface::compressed_bytes_total)
+    /// for how much it saves.
+    ///
+    /// The compression's name is added to the
+    /// [`metadata`](crate::NetcodeInterfaceBuilder::metadata), so both
+    /// players must use the same compression, or the handshake fails with
+    /// [`NetcodeError::MetadataMismatch`](crate::NetcodeError::MetadataMismatch).
+    /// By default, nothing is compressed.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self.metadata(&[(COMPRESSION_METADATA_KEY, &compression.name())])
+    }
+}
+
+impl Compression {
+    /// A name identifying this compression and its configuration.
+    #[cfg(feature = "zstd")]
+    fn name(&self) -> String {
+        match *self {
+            Compression::Zstd { .. } => "zstd".to_string(),
+        }
+    }
+
+    /// Compress an encoded frame, returning the bytes to send in its place.
+    ///
+    /// Returns `None` if the frame is too small to be worth compressing, or
+    /// did not get any smaller.
+    pub fn compress(&self, frame: &[u8]) -> Result<Option<Vec<u8>>, BoxError> {
+        if frame.len() <= COMPRESSION_THRESHOLD {
+            return Ok(None);
+        }
+        match *self {
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { level } => {
+                let compressed = Frame::Compressed(zstd::bulk::compress(frame, level)?).encode()?;
+                Ok((compressed.len() < frame.len()).then_some(compressed))
+            }
+        }
+    }
+}
+
+/// Decompress the frame inside a [`Frame::Compressed`].
+///
+/// Decompresses at most [`MAX_TRANSFER_LEN`](crate::transfer::MAX_TRANSFER_LEN)
+/// bytes, the most we would take in chunks, so a small frame cannot make us
+/// decompress without end.
+pub fn decompress(bytes: &[u8]) -> Result<Frame, BoxError> {
+    match Frame::decode(&decompress_bytes(bytes)?)? {
+        Frame::Compressed(_) | Frame::Chunk { .. } => {
+            Err("the other player sent a frame nested in a compressed frame".into())
+        }
+        frame => Ok(frame),
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_bytes(bytes: &[u8]) -> Result<Vec<u8>, BoxError> {
+    use std::io::Read;
+
+    use crate::transfer::MAX_TRANSFER_LEN;
+
+    let mut frame = Vec::new();
+    zstd::stream::read::Decoder::new(bytes)?
+        .take(MAX_TRANSFER_LEN as u64 + 1)
+        .read_to_end(&mut frame)?;
+    if frame.len() > MAX_TRANSFER_LEN {
+        return Err(format!(
+            "the other player sent a frame that decompresses to more than {MAX_TRANSFER_LEN} bytes"
+        )
+        .into());
+    }
+    Ok(frame)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_bytes(_: &[u8]) -> Result<Vec<u8>, BoxError> {
+    Err("the other player sent a compressed frame, but compression is not enabled".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn large_moves_round_trip() {
+        let frame = Frame::Turns {
+            seq: 1,
+            game: 0,
+            clock: None,
+            state_hash: None,
+            turns: vec![vec![7; 4096]],
+        };
+        let bytes = frame.encode().unwrap();
+        let compressed = Compression::Zstd { level: 3 }
+            .compress(&bytes)
+            .unwrap()
+            .unwrap();
+        assert!(compressed.len() < bytes.len());
+        let Frame::Compressed(inner) = Frame::decode(&compressed).unwrap() else {
+            panic!("a compressed move should be sent in a compressed frame");
+        };
+        assert_eq!(decompress(&inner).unwrap(), frame);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn small_moves_are_sent_as_is() {
+        let zstd = Compression::Zstd { level: 3 };
+        assert_eq!(zstd.compress(&[0; COMPRESSION_THRESHOLD]).unwrap(), None);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn decompressing_past_the_cap_is_rejected() {
+        use crate::transfer::MAX_TRANSFER_LEN;
+
+        // a few kilobytes that inflate to just past what we would take.
+        let bomb = zstd::bulk::compress(&vec![0; MAX_TRANSFER_LEN + 1], 19).unwrap();
+        assert!(bomb.len() < 4096);
+        let e = decompress(&bomb).unwrap_err();
+        assert!(e.to_string().contains("decompresses to more than"), "{e}");
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn compressed_frames_need_the_feature() {
+        assert!(decompress(&[0; 16]).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! A log of every step the connection to the other player went through, for
+//! debugging why one failed.
+
+use std::time::Instant;
+
+use crate::NetcodeError;
+
+/// A step in the connection's lifecycle, in the
+/// [`connection_log`](crate::NetcodeInterface::connection_log).
+///
+/// A connection that goes well is logged as `EndpointCreated`,
+/// `DiscoveryStarted`, `PeerFound`, `StreamOpened`, then `Connected`. When
+/// it drops and [reconnects](crate::Reconnect), it is logged as
+/// `Disconnected`, then `PeerFound`, `StreamOpened` and `Connected` again.
+/// Games played over a [`Session`](crate::Session) share its connection,
+/// so only log from `StreamOpened` on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// Our iroh endpoint was bound.
+    EndpointCreated { at: Instant },
+    /// We started looking for the other player: dialing the host, as the
+    /// client, or waiting to be dialed once the ticket is ready, as the
+    /// host.
+    DiscoveryStarted { at: Instant },
+    /// A connection was made to the other player's node, whose node ID is
+    /// `node_id`.
+    PeerFound { at: Instant, node_id: [u8; 32] },
+    /// The stream the game is played over was opened.
+    StreamOpened { at: Instant },
+    /// The handshake is done, so the game can be played.
+    Connected { at: Instant },
+    /// The connection was lost, or could not be established, because of
+    /// `reason`.
+    Disconnected { at: Instant, reason: NetcodeError },
+}
+
+impl ConnectionEvent {
+    /// Return when it happened.
+    pub fn at(&self) -> Instant {
+        match self {
+            ConnectionEvent::EndpointCreated { at }
+            | ConnectionEvent::DiscoveryStarted { at }
+            | ConnectionEvent::PeerFound { at, .. }
+            | ConnectionEvent::StreamOpened { at }
+            | ConnectionEvent::Connected { at }
+            | ConnectionEvent::Disconnected { at, .. } => *at,
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Encoding turns relative to the previous turn to save bandwidth.
+
+/// Shrinks turns before they are sent by encoding them relative to the
+/// previous turn exchanged, by either player.
+///
+/// Set one with
+/// [`diff_encoder`](crate::NetcodeInterfaceBuilder::diff_encoder). Both
+/// players must use encoders with the same [`name`](DiffEncoder::name), or the
+/// handshake fails.
+pub trait DiffEncoder<const SIZE: usize>: Send + Sync + 'static {
+    /// A name identifying this encoder and its configuration.
+    fn name(&self) -> String;
+
+    /// Encode `curr` relative to `prev`.
+    fn encode(&self, prev: &[u8; SIZE], curr: &[u8; SIZE]) -> Vec<u8>;
+
+    /// Reconstruct the turn that `diff` was encoded from, or `None` if `diff`
+    /// is malformed.
+    fn decode(&self, prev: &[u8; SIZE], diff: &[u8]) -> Option<[u8; SIZE]>;
+}
+
+/// A [`DiffEncoder`] that only sends the runs of bytes that changed.
+///
+/// Turns of `FROM` bytes are encoded as diffs of `TO` bytes: a big-endian
+/// `u16` count of runs, then each run as a big-endian `u16` offset, a `u8`
+/// length, and the new bytes. Unused bytes at the end are zero.
+///
+/// Good for games that send their whole board every turn, where only a few
+/// bytes change. If a diff does not fit in `TO` bytes, the encoder sends the
+/// whole turn instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TurnDiff<const FROM: usize, const TO: usize>;
+
+impl<const FROM: usize, const TO: usize> TurnDiff<FROM, TO> {
+    /// Encode `curr` relative to `prev`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the diff does not fit in `TO` bytes. See
+    /// [`try_encode`](TurnDiff::try_encode).
+    pub fn encode(prev: &[u8; FROM], curr: &[u8; FROM]) -> [u8; TO] {
+        Self::try_encode(prev, curr).expect("the diff should fit in TO bytes")
+    }
+
+    /// Encode `curr` relative to `prev`, or return `None` if the diff does not
+    /// fit in `TO` bytes.
+    pub fn try_encode(prev: &[u8; FROM], curr: &[u8; FROM]) -> Option<[u8; TO]> {
+        let mut diff = [0; TO];
+        let mut len = 2;
+        let mut runs: u16 = 0;
+        let mut i = 0;
+        while i < FROM {
+            if prev[i] == curr[i] {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < FROM && prev[i] != curr[i] && i - start < usize::from(u8::MAX) {
+                i += 1;
+            }
+
+            let run = &curr[start..i];
+            let end = len + 3 + run.len();
+            let header = diff.get_mut(len..len + 3)?;
+            header[..2].copy_from_slice(&u16::try_from(start).ok()?.to_be_bytes());
+            header[2] = run.len() as u8;
+            diff.get_mut(len + 3..end)?.copy_from_slice(run);
+            len = end;
+            runs += 1;
+        }
+        diff.get_mut(..2)?.copy_from_slice(&runs.to_be_bytes());
+        Some(diff)
+    }
+
+    /// Reconstruct the turn that `diff` was encoded from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `diff` is malformed. See [`try_decode`](TurnDiff::try_decode).
+    pub fn decode(prev: &[u8; FROM], diff: &[u8; TO]) -> [u8; FROM] {
+        Self::try_decode(prev, diff).expect("the diff should be well-formed")
+    }
+
+    /// Reconstruct the turn that `diff` was encoded from, or return `None` if
+    /// `diff` is malformed.
+    pub fn try_decode(prev: &[u8; FROM], diff: &[u8]) -> Option<[u8; FROM]> {
+        let (runs, mut rest) = diff.split_first_chunk::<2>()?;
+        let mut curr = *prev;
+        for _ in 0..u16::from_be_bytes(*runs) {
+            let (&[o0, o1, len], after) = rest.split_first_chunk::<3>()?;
+            let (run, after) = after.split_at_checked(usize::from(len))?;
+            let offset = usize::from(u16::from_be_bytes([o0, o1]));
+            curr.get_mut(offset..offset + run.len())?
+                .copy_from_slice(run);
+            rest = after;
+        }
+        Some(curr)
+    }
+}
+
+/// Prefixes every diff with whether it holds a diff or the whole turn.
+const ENCODED_DIFF: u8 = 0;
+const ENCODED_WHOLE: u8 = 1;
+
+impl<const FROM: usize, const TO: usize> DiffEncoder<FROM> for TurnDiff<FROM, TO> {
+    fn name(&self) -> String {
+        format!("turn-diff/{FROM}/{TO}")
+    }
+
+    fn encode(&self, prev: &[u8; FROM], curr: &[u8; FROM]) -> Vec<u8> {
+        match Self::try_encode(prev, curr) {
+            Some(diff) => [&[ENCODED_DIFF], &diff[..]].concat(),
+            None => [&[ENCODED_WHOLE], &curr[..]].concat(),
+        }
+    }
+
+    fn decode(&self, prev: &[u8; FROM], diff: &[u8]) -> Option<[u8; FROM]> {
+        match diff.split_first()? {
+            (&ENCODED_DIFF, diff) if diff.len() == TO => Self::try_decode(prev, diff),
+            (&ENCODED_WHOLE, whole) => whole.try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<const FROM: usize, const TO: usize>(prev: &[u8; FROM], curr: &[u8; FROM]) {
+        let diff = TurnDiff::<FROM, TO>::encode(prev, curr);
+        assert_eq!(TurnDiff::<FROM, TO>::decode(prev, &diff), *curr);
+    }
+
+    #[test]
+    fn identical_turns_round_trip_as_no_runs() {
+        let turn = [5; 8];
+        round_trip::<8, 2>(&turn, &turn);
+        assert_eq!(TurnDiff::<8, 4>::encode(&turn, &turn), [0; 4]);
+    }
+
+    #[test]
+    fn sparse_changes_round_trip() {
+        let prev = [0; 16];
+        let mut curr = prev;
+        curr[1] = 1;
+        curr[9..12].copy_from_slice(&[2, 3, 4]);
+        curr[15] = 5;
+        round_trip::<16, 16>(&prev, &curr);
+        let diff = TurnDiff::<16, 16>::encode(&prev, &curr);
+        assert_eq!(diff[..6], [0, 3, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn all_different_turns_round_trip() {
+        let prev = [0; 8];
+        let curr = [1, 2, 3, 4, 5, 6, 7, 8];
+        round_trip::<8, 13>(&prev, &curr);
+        round_trip::<8, 13>(&curr, &prev);
+    }
+
+    #[test]
+    fn long_runs_are_split() {
+        let prev = [0; 600];
+        let curr = [1; 600];
+        // runs of 255, 255 and 90 bytes, each with a 3-byte header.
+        round_trip::<600, 611>(&prev, &curr);
+        assert_eq!(TurnDiff::<600, 610>::try_encode(&prev, &curr), None);
+    }
+
+    #[test]
+    fn encoder_sends_the_whole_turn_when_the_diff_does_not_fit() {
+        let encoder = TurnDiff::<8, 6>;
+        let prev = [0; 8];
+        let curr = [9; 8];
+        let encoded = DiffEncoder::encode(&encoder, &prev, &curr);
+        assert_eq!(encoded[0], ENCODED_WHOLE);
+        assert_eq!(DiffEncoder::decode(&encoder, &prev, &encoded), Some(curr));
+
+        let mut small = prev;
+        small[3] = 1;
+        let encoded = DiffEncoder::encode(&encoder, &prev, &small);
+        assert_eq!(encoded[0], ENCODED_DIFF);
+        assert_eq!(DiffEncoder::decode(&encoder, &prev, &encoded), Some(small));
+    }
+
+    #[test]
+    fn malformed_diffs_are_rejected() {
+        let prev = [0; 4];
+        // a run past the end of the turn.
+        assert_eq!(
+            TurnDiff::<4, 8>::try_decode(&prev, &[0, 1, 0, 3, 2, 1, 1]),
+            None
+        );
+        // more runs than there are bytes.
+        assert_eq!(
+            TurnDiff::<4, 8>::try_decode(&prev, &[0, 2, 0, 0, 1, 1]),
+            None
+        );
+        assert_eq!(TurnDiff::<4, 8>::try_decode(&prev, &[0]), None);
+        let encoder = TurnDiff::<4, 8>;
+        assert_eq!(
+            DiffEncoder::decode(&encoder, &prev, &[ENCODED_DIFF, 0, 0]),
+            None
+        );
+        assert_eq!(
+            DiffEncoder::decode(&encoder, &prev, &[ENCODED_WHOLE, 1]),
+            None
+        );
+        assert_eq!(DiffEncoder::decode(&encoder, &prev, &[2; 9]), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! Offering, accepting, and declining draws.
+//!
+//! Draw frames are control frames, so they stay in order with respect to each
+//! other no matter whose turn it is. An offer stays outstanding across turns
+//! until it is answered.
+
+use std::collections::VecDeque;
+
+use crate::frame::ControlFrame;
+
+/// Something that happened to a draw offer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawEvent {
+    /// The other player offered a draw. Answer with
+    /// [`respond_draw`](crate::NetcodeInterface::respond_draw).
+    Offered,
+    /// The other player declined the user's offer.
+    Declined,
+    /// Both players agreed to a draw, so the game is over.
+    Agreed,
+}
+
+/// Who made the draw offer that has yet to be answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Offer {
+    Ours,
+    Theirs,
+}
+
+/// Both players' view of draw offers in the current game.
+#[derive(Debug, Default)]
+pub(crate) struct Draw {
+    outstanding: Option<Offer>,
+    agreed: bool,
+    /// Events the game has yet to receive.
+    events: VecDeque<DrawEvent>,
+}
+
+impl Draw {
+    /// Whether both players agreed to a draw.
+    pub fn agreed(&self) -> bool {
+        self.agreed
+    }
+
+    /// Offer a draw, returning the frame to send, if any.
+    ///
+    /// Offering again while our offer is outstanding does nothing. Offering
+    /// while theirs is outstanding accepts it.
+    pub fn offer(&mut self) -> Option<ControlFrame> {
+        assert!(!self.agreed);
+        match self.outstanding {
+            Some(Offer::Ours) => None,
+            Some(Offer::Theirs) => {
+                self.agree();
+                Some(ControlFrame::DrawAccept)
+            }
+            None => {
+                self.outstanding = Some(Offer::Ours);
+                Some(ControlFrame::DrawOffer)
+            }
+        }
+    }
+
+    /// Answer the other player's offer, returning the frame to send.
+    pub fn respond(&mut self, accept: bool) -> ControlFrame {
+        assert_eq!(self.outstanding, Some(Offer::Theirs));
+        if accept {
+            self.agree();
+            ControlFrame::DrawAccept
+        } else {
+            self.outstanding = None;
+            ControlFrame::DrawDecline
+        }
+    }
+
+    /// Handle a draw frame from the other player.
+    pub fn handle(&mut self, control: ControlFrame) {
+        if self.agreed {
+            return;
+        }
+        match (control, self.outstanding) {
+            // our offers crossed in flight, so both of us see the other's
+            // offer while our own is outstanding, and both agree.
+            (ControlFrame::DrawOffer, Some(Offer::Ours)) => self.agree(),
+            (ControlFrame::DrawOffer, None) => {
+                self.outstanding = Some(Offer::Theirs);
+                self.events.push_back(DrawEvent::Offered);
+            }
+            (ControlFrame::DrawAccept, Some(Offer::Ours)) => self.agree(),
+            (ControlFrame::DrawDecline, Some(Offer::Ours)) => {
+                self.outstanding = None;
+                self.events.push_back(DrawEvent::Declined);
+            }
+            // anything else answers an offer that does not exist, which a
+            // well-behaved player never sends.
+            _ => {}
+        }
+    }
+
+    /// Return the next event the game has yet to receive.
+    pub fn next_event(&mut self) -> Option<DrawEvent> {
+        self.events.pop_front()
+    }
+
+    fn agree(&mut self) {
+        self.outstanding = None;
+        self.agreed = true;
+        self.events.push_back(DrawEvent::Agreed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offer_then_accept() {
+        let (mut ours, mut theirs) = (Draw::default(), Draw::default());
+        let offer = ours.offer().unwrap();
+        assert_eq!(ours.offer(), None);
+        theirs.handle(offer);
+        assert_eq!(theirs.next_event(), Some(DrawEvent::Offered));
+        ours.handle(theirs.respond(true));
+        assert!(ours.agreed() && theirs.agreed());
+        assert_eq!(ours.next_event(), Some(DrawEvent::Agreed));
+        assert_eq!(theirs.next_event(), Some(DrawEvent::Agreed));
+    }
+
+    #[test]
+    fn offer_then_decline() {
+        let (mut ours, mut theirs) = (Draw::default(), Draw::default());
+        theirs.handle(ours.offer().unwrap());
+        ours.handle(theirs.respond(false));
+        assert!(!ours.agreed() && !theirs.agreed());
+        assert_eq!(ours.next_event(), Some(DrawEvent::Declined));
+        // the offer is answered, so we can offer again.
+        assert_eq!(ours.offer(), Some(ControlFrame::DrawOffer));
+    }
+
+    #[test]
+    fn crossed_offers_agree() {
+        let (mut ours, mut theirs) = (Draw::default(), Draw::default());
+        let (a, b) = (ours.offer().unwrap(), theirs.offer().unwrap());
+        ours.handle(b);
+        theirs.handle(a);
+        assert!(ours.agreed() && theirs.agreed());
+        assert_eq!(ours.next_event(), Some(DrawEvent::Agreed));
+        assert_eq!(ours.next_event(), None);
+    }
+
+    #[test]
+    fn offering_while_theirs_is_outstanding_accepts() {
+        let (mut ours, mut theirs) = (Draw::default(), Draw::default());
+        ours.handle(theirs.offer().unwrap());
+        assert_eq!(ours.offer(), Some(ControlFrame::DrawAccept));
+        assert!(ours.agreed());
+    }
+
+    #[test]
+    fn answers_to_no_offer_are_ignored() {
+        let mut draw = Draw::default();
+        draw.handle(ControlFrame::DrawAccept);
+        draw.handle(ControlFrame::DrawDecline);
+        assert!(!draw.agreed());
+        assert_eq!(draw.next_event(), None);
+    }
+}
@@ -0,0 +1,187 @@
+//! An interface whose turn size is picked at runtime, for games that only
+//! know it once the players agree on something like the board size.
+
+use crate::{Config, NetcodeError, NetcodeInterface, NetcodeInterfaceBuilder, TurnPoll};
+
+/// The largest turn a [`DynNetcodeInterface`] can send, in bytes.
+pub const MAX_DYN_SIZE: usize = 1024;
+
+/// The result of polling for the other player's turn, like [`TurnPoll`],
+/// with turns sized at runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DynTurnPoll {
+    /// The other player's turn arrived, like [`TurnPoll::Ready`].
+    Ready(Vec<u8>),
+    /// The other player passed, like [`TurnPoll::OpponentPassed`].
+    OpponentPassed,
+    /// The other player has not sent their turn yet, like
+    /// [`TurnPoll::Pending`].
+    Pending,
+    /// The other player has not sent their turn for a while, like
+    /// [`TurnPoll::WaitingTooLong`].
+    WaitingTooLong,
+}
+
+/// A [`NetcodeInterface`] whose turns are `size` bytes, picked at runtime,
+/// rather than `SIZE`.
+///
+/// Create one with [`DynNetcodeInterface::new`], or
+/// [`NetcodeInterfaceBuilder::build_dyn`]. It speaks the same protocol, so it
+/// plays against a `NetcodeInterface` of the same size. Everything besides
+/// sending and receiving turns is done on the underlying interface, with
+/// [`get`](DynNetcodeInterface::get) and
+/// [`get_mut`](DynNetcodeInterface::get_mut), whose turns are zero-padded up
+/// to [`MAX_DYN_SIZE`] bytes.
+pub struct DynNetcodeInterface {
+    inner: NetcodeInterface<MAX_DYN_SIZE>,
+    size: usize,
+}
+
+impl NetcodeInterfaceBuilder<MAX_DYN_SIZE> {
+    /// Create an interface whose turns are `size` bytes.
+    ///
+    /// Turns are sent whole both ways, so this replaces any
+    /// [`turn_sizes`](NetcodeInterfaceBuilder::turn_sizes). The other player
+    /// must pick the same size, or the handshake fails with
+    /// [`NetcodeError::HandshakeFailed`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is larger than [`MAX_DYN_SIZE`].
+    pub fn build_dyn(mut self, size: usize) -> DynNetcodeInterface {
+        assert!(
+            size <= MAX_DYN_SIZE,
+            "turns are at most {MAX_DYN_SIZE} bytes"
+        );
+        self.size = size;
+        DynNetcodeInterface {
+            inner: self.turn_sizes(size, size).build(),
+            size,
+        }
+    }
+}
+
+impl DynNetcodeInterface {
+    /// Create an interface whose turns are `size` bytes, with the default
+    /// options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is larger than [`MAX_DYN_SIZE`].
+    pub fn new(config: Config, size: usize) -> Self {
+        NetcodeInterface::builder(config).build_dyn(size)
+    }
+
+    /// Return how many bytes each turn is.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Send a turn to the other player, like
+    /// [`NetcodeInterface::send_turn`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `turn` is not [`size`](DynNetcodeInterface::size) bytes, or
+    /// for the same reasons as `send_turn`.
+    pub fn send_turn(&mut self, turn: &[u8]) {
+        assert_eq!(turn.len(), self.size, "turns are {} bytes", self.size);
+        let mut padded = [0; MAX_DYN_SIZE];
+        padded[..self.size].copy_from_slice(turn);
+        self.inner.send_turn(&padded);
+    }
+
+    /// Check if the other player has sent a turn, like
+    /// [`NetcodeInterface::try_recv_turn`].
+    pub fn try_recv_turn(&mut self) -> DynTurnPoll {
+        match self.inner.try_recv_turn() {
+            TurnPoll::Ready(turn) => DynTurnPoll::Ready(turn[..self.size].to_vec()),
+            TurnPoll::OpponentPassed => DynTurnPoll::OpponentPassed,
+            TurnPoll::Pending => DynTurnPoll::Pending,
+            TurnPoll::WaitingTooLong => DynTurnPoll::WaitingTooLong,
+        }
+    }
+
+    /// Wait for the other player's turn, like
+    /// [`NetcodeInterface::recv_turn`].
+    ///
+    /// This is cancellation safe, like `recv_turn`.
+    pub async fn recv_turn(&mut self) -> Result<Option<Vec<u8>>, NetcodeError> {
+        let turn = self.inner.recv_turn().await?;
+        Ok(turn.map(|turn| turn[..self.size].to_vec()))
+    }
+
+    /// Return the underlying interface.
+    pub fn get(&self) -> &NetcodeInterface<MAX_DYN_SIZE> {
+        &self.inner
+    }
+
+    /// Return the underlying interface, to do anything besides sending and
+    /// receiving turns.
+    pub fn get_mut(&mut self) -> &mut NetcodeInterface<MAX_DYN_SIZE> {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying interface.
+    pub fn into_inner(self) -> NetcodeInterface<MAX_DYN_SIZE> {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::tests::recv;
+
+    /// Start a host of `NetcodeInterface<8>` and a client of `size` bytes
+    /// over loopback, without waiting for the handshake.
+    async fn start(size: usize) -> (NetcodeInterface<8>, DynNetcodeInterface) {
+        let (ticket_sender, ticket) = oneshot::channel();
+        let host = NetcodeInterface::new(Config::TicketSender(ticket_sender));
+        let client = DynNetcodeInterface::new(Config::Ticket(ticket.await.unwrap()), size);
+        (host, client)
+    }
+
+    #[tokio::test]
+    async fn plays_against_a_netcode_interface_of_the_same_size() {
+        let (mut host, mut client) = start(8).await;
+        let (host_connected, client_connected) = tokio::join!(
+            host.wait_for_connection(),
+            client.get_mut().wait_for_connection()
+        );
+        host_connected.unwrap();
+        client_connected.unwrap();
+
+        client.send_turn(b"dynamic!");
+        assert_eq!(&recv(&mut host).await, b"dynamic!");
+        host.send_turn(b"generic!");
+        assert_eq!(client.recv_turn().await.unwrap().unwrap(), b"generic!");
+        assert_eq!(client.size(), 8);
+    }
+
+    #[tokio::test]
+    async fn a_different_size_fails_the_handshake() {
+        let (mut host, mut client) = start(6).await;
+        let (host_connected, client_connected) = tokio::join!(
+            host.wait_for_connection(),
+            client.get_mut().wait_for_connection()
+        );
+        assert!(matches!(
+            host_connected,
+            Err(NetcodeError::HandshakeFailed(_))
+        ));
+        assert!(matches!(
+            client_connected,
+            Err(NetcodeError::HandshakeFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "turns are 8 bytes")]
+    async fn sending_a_turn_of_the_wrong_size_panics() {
+        let (_host, mut client) = start(8).await;
+        client.send_turn(b"short");
+    }
+}
@@ -0,0 +1,109 @@
+//! Canned emotes, like "Good game", rate limited so neither player can spam
+//! the other.
+
+use std::time::{Duration, Instant};
+
+/// The other player sent an emote. What the byte means is up to the game.
+///
+/// See [`send_emote`](crate::NetcodeInterface::send_emote).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmoteReceived(pub u8);
+
+/// How many emotes can be sent at once.
+const BURST: u32 = 3;
+
+/// How long it takes to be able to send one more emote, up to the burst.
+const REFILL: Duration = Duration::from_secs(1);
+
+/// A token bucket allowing one emote a second, with bursts of up to three.
+#[derive(Debug)]
+pub(crate) struct EmoteLimit {
+    tokens: u32,
+    /// When the last token was added, or the bucket was last full.
+    refilled_at: Instant,
+}
+
+impl Default for EmoteLimit {
+    fn default() -> Self {
+        Self {
+            tokens: BURST,
+            refilled_at: Instant::now(),
+        }
+    }
+}
+
+impl EmoteLimit {
+    /// Return whether an emote can be sent or received at `now`, using up a
+    /// token if so.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        let refills = u32::try_from(elapsed.as_nanos() / REFILL.as_nanos()).unwrap_or(BURST);
+        if self.tokens.saturating_add(refills) >= BURST {
+            self.tokens = BURST;
+            self.refilled_at = now;
+        } else {
+            self.tokens += refills;
+            self.refilled_at += REFILL * refills;
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_then_refills_one_a_second() {
+        let mut limit = EmoteLimit::default();
+        let start = limit.refilled_at;
+        for _ in 0..BURST {
+            assert!(limit.allow(start));
+        }
+        assert!(!limit.allow(start));
+        assert!(!limit.allow(start + REFILL - Duration::from_nanos(1)));
+        assert!(limit.allow(start + REFILL));
+        assert!(!limit.allow(start + REFILL));
+        assert!(limit.allow(start + REFILL * 2));
+    }
+
+    #[test]
+    fn refills_no_further_than_the_burst() {
+        let mut limit = EmoteLimit::default();
+        let start = limit.refilled_at;
+        assert!(limit.allow(start));
+        let later = start + REFILL * 60;
+        for _ in 0..BURST {
+            assert!(limit.allow(later));
+        }
+        assert!(!limit.allow(later));
+    }
+
+    #[test]
+    fn partial_refills_carry_over() {
+        let mut limit = EmoteLimit::default();
+        let start = limit.refilled_at;
+        for _ in 0..BURST {
+            assert!(limit.allow(start));
+        }
+        // a second and a half in, one token is back, and the half second
+        // counts toward the next.
+        assert!(limit.allow(start + REFILL + REFILL / 2));
+        assert!(!limit.allow(start + REFILL * 2 - Duration::from_nanos(1)));
+        assert!(limit.allow(start + REFILL * 2));
+    }
+
+    #[test]
+    fn time_going_backwards_refills_nothing() {
+        let mut limit = EmoteLimit::default();
+        let start = limit.refilled_at + REFILL;
+        for _ in 0..BURST {
+            assert!(limit.allow(start));
+        }
+        assert!(!limit.allow(start - REFILL));
+    }
+}
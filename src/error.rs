@@ -0,0 +1,172 @@
+//! Errors surfaced by the interface.
+
+use std::fmt;
+
+/// An error from a [`NetcodeInterface`](crate::NetcodeInterface).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NetcodeError {
+    /// The connection was not established within the duration set by
+    /// [`connect_timeout`](crate::NetcodeInterfaceBuilder::connect_timeout).
+    ConnectTimeout,
+    /// The connection could not be established, or was lost.
+    ///
+    /// Holds a human-readable reason.
+    Disconnected(String),
+    /// The two players were configured incompatibly, so the handshake failed.
+    ///
+    /// Holds a human-readable description of the disagreement.
+    HandshakeFailed(String),
+    /// In a [`SimultaneousInterface`](crate::SimultaneousInterface), the other
+    /// player revealed a turn that did not match the one they committed to.
+    RevealMismatch,
+    /// A turn could not be handed to the protocol, because it stopped.
+    SendFailed,
+    /// The two players disagreed on a
+    /// [`metadata`](crate::NetcodeInterfaceBuilder::metadata) entry, so the
+    /// handshake failed.
+    ///
+    /// Holds the key, and each player's value, if they had one.
+    MetadataMismatch {
+        key: String,
+        ours: Option<String>,
+        theirs: Option<String>,
+    },
+    /// The other player sent a move out of order, so it was not delivered.
+    ///
+    /// Holds the sequence number the move should have had, and the one it had.
+    Desync { expected: u64, got: u64 },
+    /// The other player closed the game on purpose, as opposed to losing
+    /// their connection, which is reported as
+    /// [`Disconnected`](NetcodeError::Disconnected).
+    PeerClosed,
+    /// The other player did not acknowledge a turn in time. See
+    /// [`send_turn_with_ack_timeout`](crate::NetcodeInterface::send_turn_with_ack_timeout).
+    AckTimeout,
+    /// The game is paused, so no turns can be sent until it
+    /// [resumes](crate::NetcodeInterface::resume).
+    Paused,
+    /// The players [restored](crate::Config::restore) from snapshots that do
+    /// not match, like snapshots of two different sessions, so the handshake
+    /// failed.
+    SnapshotMismatch,
+    /// A turn did not fit in the turn size once encoded, so it was not sent.
+    TurnTooLarge,
+    /// A turn could not be encoded, or the other player's turn could not be
+    /// decoded.
+    ///
+    /// Holds a human-readable reason.
+    MalformedTurn(String),
+    /// A player [finished](crate::NetcodeInterface::finish_game) the game, so
+    /// no more turns can be sent.
+    GameOver,
+    /// We could not reach the internet, so we did not try to connect. Only
+    /// checked with
+    /// [`check_internet`](crate::NetcodeInterfaceBuilder::check_internet).
+    NoInternet,
+    /// The URI in [`Config::Uri`](crate::Config::Uri) could not be parsed.
+    ///
+    /// Holds a human-readable reason.
+    InvalidUri(String),
+    /// The other player does not speak our version of the sfn-tpn protocol,
+    /// likely because they run a different version of sfn-tpn, so they
+    /// refused the connection.
+    ///
+    /// Holds the protocol we speak, and theirs, if it is an earlier version
+    /// we recognize. They only say they do not speak ours, so we find out
+    /// by dialing them again with each earlier version, which only the
+    /// client can do. Differing versions of the game itself are
+    /// caught by putting its version in the
+    /// [`metadata`](crate::NetcodeInterfaceBuilder::metadata), which reports
+    /// both players' versions in a
+    /// [`MetadataMismatch`](NetcodeError::MetadataMismatch).
+    #[non_exhaustive]
+    ProtocolMismatch {
+        local: String,
+        remote: Option<String>,
+    },
+    /// It is not the user's turn, so the turn was not sent. See
+    /// [`TurnSender::send_turn`](crate::TurnSender::send_turn).
+    NotYourTurn,
+    /// A [restored](crate::Config::restore) snapshot had no secret key, or not
+    /// the one it was taken with. Deserialized snapshots leave it out, so hand
+    /// it back with
+    /// [`with_secret_key`](crate::SessionSnapshot::with_secret_key).
+    MissingSecretKey,
+    /// The host already has a player, so they refused the connection. Only
+    /// the player who first connected can rejoin a game.
+    GameFull,
+}
+
+impl fmt::Display for NetcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetcodeError::ConnectTimeout => write!(f, "timed out waiting for a connection"),
+            NetcodeError::Disconnected(reason) => write!(f, "disconnected: {reason}"),
+            NetcodeError::HandshakeFailed(reason) => write!(f, "handshake failed: {reason}"),
+            NetcodeError::RevealMismatch => {
+                write!(f, "the other player's turn did not match their commitment")
+            }
+            NetcodeError::SendFailed => {
+                write!(f, "the iroh protocol stopped before the turn was sent")
+            }
+            NetcodeError::MetadataMismatch { key, ours, theirs } => {
+                let describe = |value: &Option<String>| match value {
+                    Some(value) => format!("{value:?}"),
+                    None => "missing".to_string(),
+                };
+                write!(
+                    f,
+                    "metadata {key:?} is {} for us, but {} for the other player",
+                    describe(ours),
+                    describe(theirs)
+                )
+            }
+            NetcodeError::Desync { expected, got } => {
+                write!(f, "expected move number {expected}, but got {got}")
+            }
+            NetcodeError::PeerClosed => write!(f, "the other player closed the game"),
+            NetcodeError::AckTimeout => {
+                write!(
+                    f,
+                    "timed out waiting for the other player to acknowledge a turn"
+                )
+            }
+            NetcodeError::Paused => write!(f, "the game is paused"),
+            NetcodeError::SnapshotMismatch => {
+                write!(f, "the other player restored a different snapshot")
+            }
+            NetcodeError::TurnTooLarge => {
+                write!(f, "the encoded turn is larger than the turn size")
+            }
+            NetcodeError::MalformedTurn(reason) => write!(f, "malformed turn: {reason}"),
+            NetcodeError::GameOver => write!(f, "the game is finished"),
+            NetcodeError::NoInternet => write!(f, "no internet connectivity detected"),
+            NetcodeError::InvalidUri(reason) => write!(f, "invalid URI: {reason}"),
+            NetcodeError::ProtocolMismatch {
+                local,
+                remote: Some(remote),
+            } => write!(
+                f,
+                "the other player speaks {remote} rather than our protocol, {local}, so they run a different version"
+            ),
+            NetcodeError::ProtocolMismatch {
+                local,
+                remote: None,
+            } => write!(
+                f,
+                "the other player does not speak our protocol, {local}, so they may run a different version"
+            ),
+            NetcodeError::NotYourTurn => write!(f, "it is not the user's turn"),
+            NetcodeError::MissingSecretKey => {
+                write!(
+                    f,
+                    "the snapshot is missing the secret key it was taken with"
+                )
+            }
+            NetcodeError::GameFull => write!(f, "the game already has two players"),
+        }
+    }
+}
+
+impl std::error::Error for NetcodeError {}
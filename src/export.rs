@@ -0,0 +1,136 @@
+//! Exporting everything the netcode knows about a session as JSON, for
+//! debugging once a game is over.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use serde_json::{Value, json};
+
+use crate::{ConnectionState, Direction, GameOutcome, NetcodeInterface};
+
+/// The version of the format [`export_session_json`](NetcodeInterface::export_session_json)
+/// returns. It goes up whenever a field changes meaning or is removed, but
+/// not when one is added.
+pub const SESSION_EXPORT_VERSION: u32 = 1;
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Export the session's network-layer data as a single JSON object, for
+    /// debugging.
+    ///
+    /// Nothing game-specific, like the board, is included, since the netcode
+    /// never knows it. The object has these fields, any of which may be
+    /// `null` if it is not known yet:
+    ///
+    /// - `version`: [`SESSION_EXPORT_VERSION`].
+    /// - `sfn_tpn_version` and `iroh_version`: as returned by
+    ///   [`sfn_tpn_version`](crate::sfn_tpn_version) and
+    ///   [`iroh_version`](crate::iroh_version).
+    /// - `role`: `"Host"` or `"Client"`.
+    /// - `session_id`: the [`session_id`](NetcodeInterface::session_id), in
+    ///   hex.
+    /// - `established_at`: when the connection was established, in
+    ///   milliseconds since the Unix epoch.
+    /// - `connection`: an object with the connection's `state`
+    ///   (`"connecting"`, `"connected"` or `"disconnected"`), the `error`
+    ///   that disconnected it, the `rtt_ms`, and the totals of
+    ///   `turn_bytes_sent`, `turn_bytes_received`, `uncompressed_bytes` and
+    ///   `compressed_bytes`.
+    /// - `metadata` and `peer_metadata`: both players'
+    ///   [`metadata`](crate::NetcodeInterfaceBuilder::metadata).
+    /// - `game`, `ply` and `outcome`: the current game's number, its
+    ///   [`ply`](NetcodeInterface::ply), and `"draw"` if it ended in one.
+    /// - `turns`: the [`history`](NetcodeInterface::history), each turn an
+    ///   object with its `game`, `ply`, `direction` (`"sent"` or
+    ///   `"received"`), `turn` as an array of bytes, `at_ms` since the
+    ///   connection was established, and `move_time_ms` as in
+    ///   [`wall_clock_time`](NetcodeInterface::wall_clock_time). It is only
+    ///   kept with [`record_history`](crate::NetcodeInterfaceBuilder::record_history).
+    /// - `chat`: the [`chat_log`](NetcodeInterface::chat_log), each message
+    ///   an object with its `sender`, `text`, `turn_number`, and
+    ///   `timestamp` in milliseconds since the Unix epoch.
+    ///
+    /// Requires the `session-export` feature.
+    pub fn export_session_json(&self) -> Value {
+        // no duration here is anywhere near too long for a u64 of
+        // milliseconds, but saturate rather than wrap just in case.
+        let millis = |duration: Duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        let unix_ms = |time: SystemTime| {
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, millis)
+        };
+        let connected_at = *self.connected_at.borrow();
+        let since_connected = |time: Instant| {
+            connected_at.map(|connected_at| millis(time.saturating_duration_since(connected_at)))
+        };
+
+        let (state, error) = match self.connection_state() {
+            ConnectionState::Connecting => ("connecting", None),
+            ConnectionState::Connected => ("connected", None),
+            ConnectionState::Disconnected(e) => ("disconnected", Some(e.to_string())),
+        };
+        let turns: Vec<Value> = self
+            .history()
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                json!({
+                    "game": record.game,
+                    "ply": record.ply,
+                    "direction": match record.direction {
+                        Direction::Sent => "sent",
+                        Direction::Received => "received",
+                    },
+                    "turn": record.turn.as_slice(),
+                    "at_ms": since_connected(record.timestamp),
+                    "move_time_ms": self
+                        .wall_clock_time(index)
+                        .map(millis),
+                })
+            })
+            .collect();
+        let chat: Vec<Value> = self.chat_log.as_ref().map_or_else(Vec::new, |log| {
+            // the log is shared with the game, which may have panicked while
+            // holding it, but the messages recorded so far are still fine.
+            let log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            log.messages
+                .iter()
+                .map(|message| {
+                    json!({
+                        "sender": message.sender,
+                        "text": message.text,
+                        "turn_number": message.turn_number,
+                        "timestamp": unix_ms(message.timestamp),
+                    })
+                })
+                .collect()
+        });
+
+        json!({
+            "version": SESSION_EXPORT_VERSION,
+            "sfn_tpn_version": crate::sfn_tpn_version(),
+            "iroh_version": crate::iroh_version(),
+            "role": self.role(),
+            "session_id": self
+                .session_id()
+                .map(|id| id.iter().map(|byte| format!("{byte:02x}")).collect::<String>()),
+            "established_at": self.connection_established_at().map(unix_ms),
+            "connection": {
+                "state": state,
+                "error": error,
+                "rtt_ms": self.rtt().map(|rtt| rtt.as_secs_f64() * 1000.0),
+                "turn_bytes_sent": self.turn_bytes_sent_total(),
+                "turn_bytes_received": self.turn_bytes_received_total(),
+                "uncompressed_bytes": self.uncompressed_bytes_total(),
+                "compressed_bytes": self.compressed_bytes_total(),
+            },
+            "metadata": self.metadata,
+            "peer_metadata": self.peer_metadata(),
+            "game": self.game,
+            "ply": self.ply(),
+            "outcome": self.outcome().map(|outcome| match outcome {
+                GameOutcome::Draw => "draw",
+            }),
+            "turns": turns,
+            "chat": chat,
+        })
+    }
+}
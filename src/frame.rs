@@ -0,0 +1,755 @@
+//! Framing for everything sent over the bi-stream.
+//!
+//! Every frame is a one-byte tag, a big-endian `u32` payload length, then the
+//! payload itself.
+
+use std::time::Duration;
+
+use iroh::endpoint::{RecvStream, SendStream};
+
+use crate::{MAX_MESSAGE_LEN, Role, TimeControl, metadata::Metadata, protocol::BoxError};
+
+/// The largest payload we are willing to read.
+///
+/// Guards against a misbehaving peer asking us to allocate a huge buffer.
+pub const MAX_PAYLOAD_LEN: u32 = 1 << 16;
+
+/// The length of every frame's header: its tag, then its payload length.
+pub const HEADER_LEN: usize = 5;
+
+const TAG_HELLO: u8 = 0;
+const TAG_TURNS: u8 = 1;
+const TAG_SPECTATED: u8 = 2;
+const TAG_COMMIT: u8 = 3;
+const TAG_REVEAL: u8 = 4;
+const TAG_CONTROL: u8 = 5;
+const TAG_SETUP: u8 = 6;
+const TAG_METADATA: u8 = 7;
+/// Turns with a state hash, so a game without one pays nothing extra.
+const TAG_HASHED_TURNS: u8 = 8;
+const TAG_ACK: u8 = 9;
+/// Turns with the time the sender has left, so a game without a time control
+/// pays nothing extra either.
+const TAG_CLOCKED_TURNS: u8 = 10;
+const TAG_CLOCKED_HASHED_TURNS: u8 = 11;
+const TAG_ESTABLISHED_AT: u8 = 12;
+const TAG_OPEN_GAME: u8 = 13;
+const TAG_CHUNK: u8 = 14;
+const TAG_COMPRESSED: u8 = 15;
+const TAG_RESUME: u8 = 16;
+
+/// A single message between the two players.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    /// The first frame each player sends, describing how they were configured.
+    Hello(Hello),
+    /// The bytes of one or more turns, sent together as a single move, and the
+    /// number of the game they belong to.
+    ///
+    /// Each player numbers the moves they send with `seq`, counting from 1
+    /// over the whole connection, across games. The number never goes back,
+    /// even for a takeback, so a move can be named by it when replaying or
+    /// resuming a connection.
+    ///
+    /// If the sender registered a
+    /// [`state_hash`](crate::NetcodeInterfaceBuilder::state_hash), it is sent
+    /// along with the hash of their state after the move. If the players
+    /// agreed on a [`TimeControl`], it is sent along with the time the sender
+    /// has left after the move, in milliseconds.
+    Turns {
+        seq: u64,
+        game: u32,
+        clock: Option<u64>,
+        state_hash: Option<u64>,
+        turns: Vec<Vec<u8>>,
+    },
+    /// A turn taken by one of the players, shown to a spectator.
+    Spectated { side: Role, turn: Vec<u8> },
+    /// A hash of the nonce this player will reveal to pick a shared seed.
+    Commit([u8; 32]),
+    /// The nonce this player committed to to pick a shared seed.
+    Reveal([u8; 32]),
+    /// Anything that is not a turn, sent at any point after the handshake.
+    Control(ControlFrame),
+    /// The host's setup for the game, if any, sent once during the handshake.
+    Setup(Option<Vec<u8>>),
+    /// This player's metadata, sent once during the handshake.
+    Metadata(Metadata),
+    /// This player handed the move with this sequence number to their game.
+    Ack(u64),
+    /// The client's wall-clock time as the connection is established, in
+    /// milliseconds since the Unix epoch, sent once during the handshake.
+    EstablishedAt(u64),
+    /// The id of the [`Session`](crate::Session) game this stream belongs to,
+    /// sent by the client before anything else on the stream.
+    OpenGame(u32),
+    /// A piece of a frame too large to send at once, so that other frames
+    /// can be sent between its pieces.
+    ///
+    /// The frame is complete once a chunk with `last` set arrives.
+    Chunk { last: bool, bytes: Vec<u8> },
+    /// A whole frame, compressed with the
+    /// [`compression`](crate::NetcodeInterfaceBuilder::compression) both
+    /// players agreed on.
+    Compressed(Vec<u8>),
+    /// The sequence number of the last move the sender received, sent by
+    /// both players once they [reconnect](crate::Reconnect), before anything
+    /// else on the new stream.
+    Resume(u64),
+}
+
+/// A frame that can be sent regardless of whose turn it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlFrame {
+    /// This player wants to start a new game over the same connection.
+    NewGame,
+    /// This player passed instead of taking their turn in the game with this
+    /// number.
+    Pass(u32),
+    /// A hash of the salted turn this player submitted for a simultaneous round.
+    RoundCommit([u8; 32]),
+    /// The salt and turn this player committed to for a simultaneous round.
+    RoundReveal(Vec<u8>),
+    /// A chat message, or anything else the game wants to send out of turn.
+    Message(Vec<u8>),
+    /// This player offered a draw.
+    DrawOffer,
+    /// This player accepted the other player's draw offer.
+    DrawAccept,
+    /// This player declined the other player's draw offer.
+    DrawDecline,
+    /// This player asked to take back their last move, made when this many
+    /// plies had been played.
+    TakebackRequest(u32),
+    /// This player accepted the other player's takeback request.
+    TakebackAccept,
+    /// This player declined the other player's takeback request.
+    TakebackDecline,
+    /// This player offered a rematch, and whether it swaps who moves first.
+    RematchOffer(bool),
+    /// This player accepted the other player's rematch offer.
+    RematchAccept,
+    /// This player declined the other player's rematch offer.
+    RematchDecline,
+    /// This player closed the game on purpose, and the connection is about to
+    /// close.
+    Disconnect,
+    /// This player rejected the other player's last move in the game with
+    /// this number, for this reason.
+    RejectTurn(u32, Vec<u8>),
+    /// This player's turn deadline passed in the game with this number.
+    TimedOut(u32),
+    /// The time this player has left, in milliseconds, after passing in the
+    /// game with this number. Sent just before the pass.
+    Clock(u32, u64),
+    /// This player ran out of time in the game with this number.
+    FlagFell(u32),
+    /// This player asked to pause.
+    PauseRequest,
+    /// This player accepted the other player's pause request.
+    PauseAccept,
+    /// This player declined the other player's pause request.
+    PauseDecline,
+    /// This player resumed, and waits for the other player to acknowledge it.
+    Resume,
+    /// This player acknowledged that the other player resumed.
+    ResumeAck,
+    /// This player sent an emote.
+    Emote(u8),
+    /// This player asked to suspend the session in the game with this
+    /// number, when this many plies had been played.
+    SuspendRequest(u32, u32),
+    /// This player accepted the other player's request to suspend.
+    SuspendAccept,
+    /// This player declined the other player's request to suspend.
+    SuspendDecline,
+    /// This player finished the game with this result, and will close the
+    /// connection once it is acknowledged.
+    Finish(Vec<u8>),
+    /// This player received the other player's result. Sent by the protocol
+    /// as soon as it arrives.
+    FinishAck,
+}
+
+impl ControlFrame {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            ControlFrame::NewGame => vec![0],
+            ControlFrame::Pass(game) => [&[1], &game.to_be_bytes()[..]].concat(),
+            ControlFrame::RoundCommit(hash) => [&[2], hash.as_slice()].concat(),
+            ControlFrame::RoundReveal(reveal) => [&[3], reveal.as_slice()].concat(),
+            ControlFrame::Message(message) => [&[4], message.as_slice()].concat(),
+            ControlFrame::DrawOffer => vec![5],
+            ControlFrame::DrawAccept => vec![6],
+            ControlFrame::DrawDecline => vec![7],
+            ControlFrame::TakebackRequest(plies) => [&[8], &plies.to_be_bytes()[..]].concat(),
+            ControlFrame::TakebackAccept => vec![9],
+            ControlFrame::TakebackDecline => vec![10],
+            ControlFrame::RematchOffer(swap) => vec![11, u8::from(*swap)],
+            ControlFrame::RematchAccept => vec![12],
+            ControlFrame::RematchDecline => vec![13],
+            ControlFrame::Disconnect => vec![14],
+            ControlFrame::RejectTurn(game, reason) => {
+                [&[15], &game.to_be_bytes()[..], reason].concat()
+            }
+            ControlFrame::TimedOut(game) => [&[16], &game.to_be_bytes()[..]].concat(),
+            ControlFrame::Clock(game, millis) => {
+                [&[17], &game.to_be_bytes()[..], &millis.to_be_bytes()[..]].concat()
+            }
+            ControlFrame::FlagFell(game) => [&[18], &game.to_be_bytes()[..]].concat(),
+            ControlFrame::PauseRequest => vec![19],
+            ControlFrame::PauseAccept => vec![20],
+            ControlFrame::PauseDecline => vec![21],
+            ControlFrame::Resume => vec![22],
+            ControlFrame::ResumeAck => vec![23],
+            ControlFrame::Emote(emote) => vec![24, *emote],
+            ControlFrame::SuspendRequest(game, plies) => {
+                [&[25], &game.to_be_bytes()[..], &plies.to_be_bytes()[..]].concat()
+            }
+            ControlFrame::SuspendAccept => vec![26],
+            ControlFrame::SuspendDecline => vec![27],
+            ControlFrame::Finish(result) => [&[28], result.as_slice()].concat(),
+            ControlFrame::FinishAck => vec![29],
+        }
+    }
+
+    fn decode(payload: &[u8]) -> Result<ControlFrame, BoxError> {
+        match payload {
+            [0] => Ok(ControlFrame::NewGame),
+            [1, game @ ..] => Ok(ControlFrame::Pass(u32::from_be_bytes(game.try_into()?))),
+            [2, hash @ ..] => Ok(ControlFrame::RoundCommit(hash.try_into()?)),
+            [3, reveal @ ..] => Ok(ControlFrame::RoundReveal(reveal.to_vec())),
+            [4, message @ ..] if message.len() <= MAX_MESSAGE_LEN => {
+                Ok(ControlFrame::Message(message.to_vec()))
+            }
+            [5] => Ok(ControlFrame::DrawOffer),
+            [6] => Ok(ControlFrame::DrawAccept),
+            [7] => Ok(ControlFrame::DrawDecline),
+            [8, plies @ ..] => Ok(ControlFrame::TakebackRequest(u32::from_be_bytes(
+                plies.try_into()?,
+            ))),
+            [9] => Ok(ControlFrame::TakebackAccept),
+            [10] => Ok(ControlFrame::TakebackDecline),
+            [11, swap @ (0 | 1)] => Ok(ControlFrame::RematchOffer(*swap == 1)),
+            [12] => Ok(ControlFrame::RematchAccept),
+            [13] => Ok(ControlFrame::RematchDecline),
+            [14] => Ok(ControlFrame::Disconnect),
+            [15, g0, g1, g2, g3, reason @ ..] if reason.len() <= MAX_MESSAGE_LEN => Ok(
+                ControlFrame::RejectTurn(u32::from_be_bytes([*g0, *g1, *g2, *g3]), reason.to_vec()),
+            ),
+            [16, game @ ..] => Ok(ControlFrame::TimedOut(u32::from_be_bytes(game.try_into()?))),
+            [17, g0, g1, g2, g3, millis @ ..] => Ok(ControlFrame::Clock(
+                u32::from_be_bytes([*g0, *g1, *g2, *g3]),
+                u64::from_be_bytes(millis.try_into()?),
+            )),
+            [18, game @ ..] => Ok(ControlFrame::FlagFell(u32::from_be_bytes(game.try_into()?))),
+            [19] => Ok(ControlFrame::PauseRequest),
+            [20] => Ok(ControlFrame::PauseAccept),
+            [21] => Ok(ControlFrame::PauseDecline),
+            [22] => Ok(ControlFrame::Resume),
+            [23] => Ok(ControlFrame::ResumeAck),
+            [24, emote] => Ok(ControlFrame::Emote(*emote)),
+            [25, g0, g1, g2, g3, plies @ ..] => Ok(ControlFrame::SuspendRequest(
+                u32::from_be_bytes([*g0, *g1, *g2, *g3]),
+                u32::from_be_bytes(plies.try_into()?),
+            )),
+            [26] => Ok(ControlFrame::SuspendAccept),
+            [27] => Ok(ControlFrame::SuspendDecline),
+            [28, result @ ..] if result.len() <= MAX_MESSAGE_LEN => {
+                Ok(ControlFrame::Finish(result.to_vec()))
+            }
+            [29] => Ok(ControlFrame::FinishAck),
+            _ => Err("the other player sent a malformed control frame".into()),
+        }
+    }
+}
+
+/// What each player tells the other during the handshake.
+///
+/// Both players must agree on everything here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hello {
+    /// The size of a turn in bytes.
+    pub size: u32,
+    /// Which player moves first, or `None` if it is decided by a coin flip.
+    pub first_move: Option<Role>,
+    /// Whether the players submit turns in simultaneous rounds.
+    pub simultaneous: bool,
+    /// The name of the [`DiffEncoder`](crate::DiffEncoder) turns are encoded
+    /// with, if any.
+    pub encoder: Option<String>,
+    /// How many bytes of each turn this player sends, then receives. Unlike
+    /// everything else here, these must be the other player's swapped.
+    pub turn_sizes: (u32, u32),
+    pub time_control: Option<TimeControl>,
+    /// This player's random half of the session ID. Unlike everything else
+    /// here, the players are expected to differ.
+    pub session_half: [u8; 16],
+    /// The digest of the [`SessionSnapshot`](crate::SessionSnapshot) this
+    /// player is restoring, if any.
+    pub restore: Option<[u8; 32]>,
+}
+
+impl Frame {
+    /// Write this frame to the stream.
+    pub async fn write(&self, send: &mut SendStream) -> Result<(), BoxError> {
+        send.write_all(&self.encode()?).await?;
+        Ok(())
+    }
+
+    /// The bytes written to the stream for this frame.
+    pub fn encode(&self) -> Result<Vec<u8>, BoxError> {
+        let (tag, payload) = match self {
+            Frame::Hello(hello) => {
+                let mut payload = hello.size.to_be_bytes().to_vec();
+                payload.push(match hello.first_move {
+                    Some(role) => role_to_byte(role),
+                    None => FIRST_MOVE_RANDOM,
+                });
+                payload.push(u8::from(hello.simultaneous));
+                payload.extend_from_slice(&hello.session_half);
+                match hello.restore {
+                    Some(digest) => {
+                        payload.push(1);
+                        payload.extend_from_slice(&digest);
+                    }
+                    None => payload.push(0),
+                }
+                payload.extend_from_slice(&hello.turn_sizes.0.to_be_bytes());
+                payload.extend_from_slice(&hello.turn_sizes.1.to_be_bytes());
+                encode_time_control(hello.time_control, &mut payload)?;
+                // the name takes up the rest of the payload.
+                payload.extend_from_slice(hello.encoder.as_deref().unwrap_or_default().as_bytes());
+                (TAG_HELLO, payload)
+            }
+            Frame::Turns {
+                seq,
+                game,
+                clock,
+                state_hash,
+                turns,
+            } => {
+                let tag = match (clock, state_hash) {
+                    (None, None) => TAG_TURNS,
+                    (None, Some(_)) => TAG_HASHED_TURNS,
+                    (Some(_), None) => TAG_CLOCKED_TURNS,
+                    (Some(_), Some(_)) => TAG_CLOCKED_HASHED_TURNS,
+                };
+                let mut payload = Vec::new();
+                for prefix in [clock, state_hash].into_iter().flatten() {
+                    payload.extend_from_slice(&prefix.to_be_bytes());
+                }
+                payload.extend_from_slice(&seq.to_be_bytes());
+                payload.extend_from_slice(&game.to_be_bytes());
+                // each turn is prefixed with its length.
+                for turn in turns {
+                    payload.extend_from_slice(&u32::try_from(turn.len())?.to_be_bytes());
+                    payload.extend_from_slice(turn);
+                }
+                (tag, payload)
+            }
+            Frame::Spectated { side, turn } => {
+                let mut payload = vec![role_to_byte(*side)];
+                payload.extend_from_slice(turn);
+                (TAG_SPECTATED, payload)
+            }
+            Frame::Commit(hash) => (TAG_COMMIT, hash.to_vec()),
+            Frame::Reveal(nonce) => (TAG_REVEAL, nonce.to_vec()),
+            Frame::Control(control) => (TAG_CONTROL, control.encode()),
+            Frame::Setup(None) => (TAG_SETUP, vec![0]),
+            Frame::Setup(Some(setup)) => (TAG_SETUP, [&[1], setup.as_slice()].concat()),
+            Frame::Metadata(metadata) => (TAG_METADATA, metadata.encode()?),
+            Frame::Ack(seq) => (TAG_ACK, seq.to_be_bytes().to_vec()),
+            Frame::EstablishedAt(millis) => (TAG_ESTABLISHED_AT, millis.to_be_bytes().to_vec()),
+            Frame::OpenGame(game_id) => (TAG_OPEN_GAME, game_id.to_be_bytes().to_vec()),
+            Frame::Chunk { last, bytes } => {
+                (TAG_CHUNK, [&[u8::from(*last)], bytes.as_slice()].concat())
+            }
+            Frame::Compressed(bytes) => (TAG_COMPRESSED, bytes.clone()),
+            Frame::Resume(seq) => (TAG_RESUME, seq.to_be_bytes().to_vec()),
+        };
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.push(tag);
+        bytes.extend_from_slice(&u32::try_from(payload.len())?.to_be_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    /// Read the next frame from the stream.
+    pub async fn read(recv: &mut RecvStream) -> Result<Frame, BoxError> {
+        let mut header = [0; HEADER_LEN];
+        recv.read_exact(&mut header).await?;
+        let [tag, len @ ..] = header;
+        let len = u32::from_be_bytes(len);
+        if len > MAX_PAYLOAD_LEN {
+            return Err(format!("the other player sent a frame of {len} bytes").into());
+        }
+        let mut payload = vec![0; len as usize];
+        recv.read_exact(&mut payload).await?;
+        Self::parse(tag, &payload)
+    }
+
+    /// Decode a whole frame, header and all, like one reassembled from
+    /// [`Frame::Chunk`]s.
+    pub fn decode(bytes: &[u8]) -> Result<Frame, BoxError> {
+        let [tag, l0, l1, l2, l3, payload @ ..] = bytes else {
+            return Err("the other player sent a truncated frame".into());
+        };
+        if u32::from_be_bytes([*l0, *l1, *l2, *l3]) as usize != payload.len() {
+            return Err("the other player sent a truncated frame".into());
+        }
+        Self::parse(*tag, payload)
+    }
+
+    /// Decode a frame's payload, given its tag.
+    fn parse(tag: u8, payload: &[u8]) -> Result<Frame, BoxError> {
+        match (tag, payload) {
+            (
+                TAG_HELLO,
+                [
+                    s0,
+                    s1,
+                    s2,
+                    s3,
+                    first_move,
+                    simultaneous @ (0 | 1),
+                    rest @ ..,
+                ],
+            ) => {
+                let (session_half, rest) = rest
+                    .split_first_chunk::<16>()
+                    .ok_or("the other player sent a truncated hello")?;
+                let (restore, rest) = match rest {
+                    [0, rest @ ..] => (None, rest),
+                    [1, rest @ ..] => {
+                        let (digest, rest) = rest
+                            .split_first_chunk::<32>()
+                            .ok_or("the other player sent a truncated hello")?;
+                        (Some(*digest), rest)
+                    }
+                    _ => return Err("the other player sent a malformed hello".into()),
+                };
+                let (send_size, rest) = rest
+                    .split_first_chunk::<4>()
+                    .ok_or("the other player sent a truncated hello")?;
+                let (recv_size, rest) = rest
+                    .split_first_chunk::<4>()
+                    .ok_or("the other player sent a truncated hello")?;
+                let (time_control, encoder) = decode_time_control(rest)?;
+                Ok(Frame::Hello(Hello {
+                    size: u32::from_be_bytes([*s0, *s1, *s2, *s3]),
+                    first_move: match *first_move {
+                        FIRST_MOVE_RANDOM => None,
+                        role => Some(byte_to_role(role)?),
+                    },
+                    simultaneous: *simultaneous == 1,
+                    encoder: match encoder {
+                        [] => None,
+                        name => Some(String::from_utf8(name.to_vec())?),
+                    },
+                    turn_sizes: (
+                        u32::from_be_bytes(*send_size),
+                        u32::from_be_bytes(*recv_size),
+                    ),
+                    time_control,
+                    session_half: *session_half,
+                    restore,
+                }))
+            }
+            (TAG_TURNS, payload) => decode_turns(None, None, payload),
+            (TAG_HASHED_TURNS, payload) => {
+                let (hash, payload) = split_u64(payload)?;
+                decode_turns(None, Some(hash), payload)
+            }
+            (TAG_CLOCKED_TURNS, payload) => {
+                let (clock, payload) = split_u64(payload)?;
+                decode_turns(Some(clock), None, payload)
+            }
+            (TAG_CLOCKED_HASHED_TURNS, payload) => {
+                let (clock, payload) = split_u64(payload)?;
+                let (hash, payload) = split_u64(payload)?;
+                decode_turns(Some(clock), Some(hash), payload)
+            }
+            (TAG_SPECTATED, [side, turn @ ..]) => Ok(Frame::Spectated {
+                side: byte_to_role(*side)?,
+                turn: turn.to_vec(),
+            }),
+            (TAG_COMMIT, hash) => Ok(Frame::Commit(hash.try_into()?)),
+            (TAG_REVEAL, nonce) => Ok(Frame::Reveal(nonce.try_into()?)),
+            (TAG_CONTROL, control) => Ok(Frame::Control(ControlFrame::decode(control)?)),
+            (TAG_SETUP, [0]) => Ok(Frame::Setup(None)),
+            (TAG_SETUP, [1, setup @ ..]) => Ok(Frame::Setup(Some(setup.to_vec()))),
+            (TAG_METADATA, metadata) => Ok(Frame::Metadata(Metadata::decode(metadata)?)),
+            (TAG_ACK, seq) => Ok(Frame::Ack(u64::from_be_bytes(seq.try_into()?))),
+            (TAG_OPEN_GAME, game_id) => {
+                Ok(Frame::OpenGame(u32::from_be_bytes(game_id.try_into()?)))
+            }
+            (TAG_ESTABLISHED_AT, millis) => {
+                Ok(Frame::EstablishedAt(u64::from_be_bytes(millis.try_into()?)))
+            }
+            (TAG_CHUNK, [last @ (0 | 1), bytes @ ..]) => Ok(Frame::Chunk {
+                last: *last == 1,
+                bytes: bytes.to_vec(),
+            }),
+            (TAG_COMPRESSED, bytes) => Ok(Frame::Compressed(bytes.to_vec())),
+            (TAG_RESUME, seq) => Ok(Frame::Resume(u64::from_be_bytes(seq.try_into()?))),
+            _ => Err(format!("the other player sent a malformed frame with tag {tag}").into()),
+        }
+    }
+}
+
+/// Split the `u64` a turn's payload starts with from the rest.
+fn split_u64(payload: &[u8]) -> Result<(u64, &[u8]), BoxError> {
+    let (value, rest) = payload
+        .split_first_chunk::<8>()
+        .ok_or("the other player sent a truncated turn")?;
+    Ok((u64::from_be_bytes(*value), rest))
+}
+
+/// The inverse of how [`Frame::Turns`] is encoded, after the clock and the
+/// state hash.
+fn decode_turns(
+    clock: Option<u64>,
+    state_hash: Option<u64>,
+    payload: &[u8],
+) -> Result<Frame, BoxError> {
+    let (seq, rest) = payload
+        .split_first_chunk::<8>()
+        .ok_or("the other player sent a truncated turn")?;
+    let (game, mut rest) = rest
+        .split_first_chunk::<4>()
+        .ok_or("the other player sent a truncated turn")?;
+    let mut turns = Vec::new();
+    while let Some((len, after)) = rest.split_first_chunk::<4>() {
+        let Some((turn, after)) = after.split_at_checked(u32::from_be_bytes(*len) as usize) else {
+            break;
+        };
+        turns.push(turn.to_vec());
+        rest = after;
+    }
+    if !rest.is_empty() {
+        return Err("the other player sent a truncated turn".into());
+    }
+    Ok(Frame::Turns {
+        seq: u64::from_be_bytes(*seq),
+        game: u32::from_be_bytes(*game),
+        clock,
+        state_hash,
+        turns,
+    })
+}
+
+/// Write a time control into a hello: 0 for none, 1 for an increment, or 2
+/// for a delay, then the initial time and the increment or delay, each in
+/// milliseconds as a `u64`.
+fn encode_time_control(
+    time_control: Option<TimeControl>,
+    payload: &mut Vec<u8>,
+) -> Result<(), BoxError> {
+    let (kind, initial, extra) = match time_control {
+        None => {
+            payload.push(0);
+            return Ok(());
+        }
+        Some(TimeControl::Increment { initial, increment }) => (1, initial, increment),
+        Some(TimeControl::Delay { initial, delay }) => (2, initial, delay),
+    };
+    payload.push(kind);
+    payload.extend_from_slice(&u64::try_from(initial.as_millis())?.to_be_bytes());
+    payload.extend_from_slice(&u64::try_from(extra.as_millis())?.to_be_bytes());
+    Ok(())
+}
+
+/// The inverse of [`encode_time_control`], returning the rest of the hello.
+fn decode_time_control(payload: &[u8]) -> Result<(Option<TimeControl>, &[u8]), BoxError> {
+    let truncated = "the other player sent a truncated hello";
+    let (kind, rest) = payload.split_first().ok_or(truncated)?;
+    if *kind == 0 {
+        return Ok((None, rest));
+    }
+    let (initial, rest) = rest.split_first_chunk::<8>().ok_or(truncated)?;
+    let (extra, rest) = rest.split_first_chunk::<8>().ok_or(truncated)?;
+    let initial = Duration::from_millis(u64::from_be_bytes(*initial));
+    let extra = Duration::from_millis(u64::from_be_bytes(*extra));
+    let time_control = match kind {
+        1 => TimeControl::Increment {
+            initial,
+            increment: extra,
+        },
+        2 => TimeControl::Delay {
+            initial,
+            delay: extra,
+        },
+        _ => return Err(format!("the other player sent an unknown time control {kind}").into()),
+    };
+    Ok((Some(time_control), rest))
+}
+
+/// Stands in for a role in a hello when the first move is random.
+const FIRST_MOVE_RANDOM: u8 = 2;
+
+fn role_to_byte(role: Role) -> u8 {
+    match role {
+        Role::Host => 0,
+        Role::Client => 1,
+    }
+}
+
+fn byte_to_role(byte: u8) -> Result<Role, BoxError> {
+    match byte {
+        0 => Ok(Role::Host),
+        1 => Ok(Role::Client),
+        _ => Err(format!("the other player sent an unknown role {byte}").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(frame: Frame) {
+        let bytes = frame.encode().unwrap();
+        assert_eq!(Frame::decode(&bytes).unwrap(), frame);
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut metadata = Metadata::default();
+        metadata.hard.insert("rules".into(), "2".into());
+        metadata.soft.insert("name".into(), "saffron".into());
+        let frames = [
+            Frame::Hello(Hello {
+                size: 4,
+                first_move: None,
+                simultaneous: false,
+                encoder: None,
+                turn_sizes: (4, 4),
+                time_control: None,
+                session_half: [7; 16],
+                restore: None,
+            }),
+            Frame::Hello(Hello {
+                size: 2,
+                first_move: Some(Role::Client),
+                simultaneous: true,
+                encoder: Some("xor".into()),
+                turn_sizes: (2, 3),
+                time_control: Some(TimeControl::Delay {
+                    initial: Duration::from_secs(60),
+                    delay: Duration::from_secs(2),
+                }),
+                session_half: [1; 16],
+                restore: Some([9; 32]),
+            }),
+            Frame::Turns {
+                seq: 1,
+                game: 0,
+                clock: None,
+                state_hash: None,
+                turns: vec![vec![1, 2]],
+            },
+            Frame::Turns {
+                seq: u64::MAX,
+                game: 3,
+                clock: Some(1500),
+                state_hash: Some(42),
+                turns: vec![vec![], vec![3; 10]],
+            },
+            Frame::Spectated {
+                side: Role::Host,
+                turn: vec![5],
+            },
+            Frame::Commit([1; 32]),
+            Frame::Reveal([2; 32]),
+            Frame::Setup(None),
+            Frame::Setup(Some(vec![4, 5])),
+            Frame::Metadata(metadata),
+            Frame::Ack(8),
+            Frame::EstablishedAt(1_700_000_000_000),
+            Frame::OpenGame(2),
+            Frame::Chunk {
+                last: true,
+                bytes: vec![6; 3],
+            },
+            Frame::Compressed(vec![0, 1]),
+            Frame::Resume(12),
+        ];
+        for frame in frames {
+            round_trip(frame);
+        }
+    }
+
+    #[test]
+    fn control_frames_round_trip() {
+        let controls = [
+            ControlFrame::NewGame,
+            ControlFrame::Pass(1),
+            ControlFrame::RoundCommit([3; 32]),
+            ControlFrame::RoundReveal(vec![1, 2, 3]),
+            ControlFrame::Message(b"gg".to_vec()),
+            ControlFrame::DrawOffer,
+            ControlFrame::DrawAccept,
+            ControlFrame::DrawDecline,
+            ControlFrame::TakebackRequest(7),
+            ControlFrame::TakebackAccept,
+            ControlFrame::TakebackDecline,
+            ControlFrame::RematchOffer(true),
+            ControlFrame::RematchAccept,
+            ControlFrame::RematchDecline,
+            ControlFrame::Disconnect,
+            ControlFrame::RejectTurn(2, b"illegal".to_vec()),
+            ControlFrame::TimedOut(4),
+            ControlFrame::Clock(4, 30_000),
+            ControlFrame::FlagFell(4),
+            ControlFrame::PauseRequest,
+            ControlFrame::PauseAccept,
+            ControlFrame::PauseDecline,
+            ControlFrame::Resume,
+            ControlFrame::ResumeAck,
+            ControlFrame::Emote(3),
+            ControlFrame::SuspendRequest(1, 10),
+            ControlFrame::SuspendAccept,
+            ControlFrame::SuspendDecline,
+            ControlFrame::Finish(b"1-0".to_vec()),
+            ControlFrame::FinishAck,
+        ];
+        for control in controls {
+            round_trip(Frame::Control(control));
+        }
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let bytes = Frame::Ack(8).encode().unwrap();
+        assert!(Frame::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Frame::decode(&bytes[..3]).is_err());
+
+        let bytes = Frame::Turns {
+            seq: 1,
+            game: 0,
+            clock: None,
+            state_hash: None,
+            turns: vec![vec![1, 2, 3]],
+        }
+        .encode()
+        .unwrap();
+        // cut off the last byte of the turn, and fix up the length.
+        let mut cut = bytes[..bytes.len() - 1].to_vec();
+        let len = u32::try_from(cut.len() - HEADER_LEN).unwrap();
+        cut[1..HEADER_LEN].copy_from_slice(&len.to_be_bytes());
+        assert!(Frame::decode(&cut).is_err());
+    }
+
+    #[test]
+    fn malformed_frames_are_rejected() {
+        assert!(Frame::parse(TAG_RESUME + 1, &[]).is_err());
+        assert!(Frame::parse(TAG_CHUNK, &[2, 0]).is_err());
+        assert!(Frame::parse(TAG_SETUP, &[2]).is_err());
+        assert!(Frame::parse(TAG_SPECTATED, &[2, 0]).is_err());
+        assert!(Frame::parse(TAG_CONTROL, &[11, 2]).is_err());
+        assert!(Frame::parse(TAG_CONTROL, &[30]).is_err());
+        let long = [&[4][..], &[0; MAX_MESSAGE_LEN + 1]].concat();
+        assert!(Frame::parse(TAG_CONTROL, &long).is_err());
+    }
+}
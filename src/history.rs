@@ -0,0 +1,81 @@
+//! A record of every turn sent or received, for move lists and post-game
+//! analysis.
+
+use std::time::{Duration, Instant};
+
+/// Whether the user sent a turn, or received it from the other player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A single turn in the
+/// [`history`](crate::NetcodeInterface::history).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnRecord<const SIZE: usize> {
+    pub turn: [u8; SIZE],
+    pub direction: Direction,
+    /// The number of the game the turn was taken in, counting from zero.
+    pub game: u32,
+    /// The [`ply`](crate::NetcodeInterface::ply) after the move the turn
+    /// belongs to. Every turn in a move has the same ply.
+    pub ply: u64,
+    pub timestamp: Instant,
+}
+
+/// Every turn of the session, if the game asked to record them.
+#[derive(Debug, Default)]
+pub(crate) struct History<const SIZE: usize> {
+    records: Option<Vec<TurnRecord<SIZE>>>,
+}
+
+impl<const SIZE: usize> History<SIZE> {
+    pub fn new(record: bool) -> Self {
+        Self {
+            records: record.then(Vec::new),
+        }
+    }
+
+    /// Record a turn, if we are recording.
+    pub fn record(&mut self, turn: [u8; SIZE], direction: Direction, game: u32, ply: u32) {
+        if let Some(records) = &mut self.records {
+            records.push(TurnRecord {
+                turn,
+                direction,
+                game,
+                ply: u64::from(ply),
+                timestamp: Instant::now(),
+            });
+        }
+    }
+
+    /// Forget the turns of the move at `ply` in `game`, because it was taken
+    /// back.
+    pub fn undo(&mut self, game: u32, ply: u32) {
+        if let Some(records) = &mut self.records {
+            while records
+                .last()
+                .is_some_and(|last| last.game == game && last.ply == u64::from(ply))
+            {
+                records.pop();
+            }
+        }
+    }
+
+    pub fn records(&self) -> &[TurnRecord<SIZE>] {
+        self.records.as_deref().unwrap_or_default()
+    }
+
+    /// How long it took to make the move of the turn at `index`, since the
+    /// move before it.
+    pub fn wall_clock_time(&self, index: usize) -> Option<Duration> {
+        let records = self.records();
+        let this = records.get(index)?;
+        let previous = records[..index]
+            .iter()
+            .rev()
+            .find(|record| record.ply != this.ply || record.game != this.game)?;
+        (previous.game == this.game).then(|| this.timestamp.duration_since(previous.timestamp))
+    }
+}
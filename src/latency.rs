@@ -0,0 +1,53 @@
+//! Binning the connection's round-trip time, for signal-strength indicators.
+
+use std::time::Duration;
+
+/// How good the connection to the other player is, from
+/// [`peer_latency_class`](crate::NetcodeInterface::peer_latency_class).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LatencyClass {
+    Excellent,
+    Good,
+    Fair,
+    Poor,
+    /// Still connecting, or the connection was lost.
+    Unknown,
+}
+
+/// The round-trip times below which the connection is in each
+/// [`LatencyClass`], set with
+/// [`latency_thresholds`](crate::NetcodeInterfaceBuilder::latency_thresholds).
+///
+/// Anything at or above `fair` is [`LatencyClass::Poor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LatencyThresholds {
+    pub excellent: Duration,
+    pub good: Duration,
+    pub fair: Duration,
+}
+
+impl Default for LatencyThresholds {
+    /// 20 ms, 100 ms, and 250 ms.
+    fn default() -> Self {
+        Self {
+            excellent: Duration::from_millis(20),
+            good: Duration::from_millis(100),
+            fair: Duration::from_millis(250),
+        }
+    }
+}
+
+impl LatencyThresholds {
+    /// The class of a connection with this round-trip time.
+    pub(crate) fn classify(&self, rtt: Duration) -> LatencyClass {
+        if rtt < self.excellent {
+            LatencyClass::Excellent
+        } else if rtt < self.good {
+            LatencyClass::Good
+        } else if rtt < self.fair {
+            LatencyClass::Fair
+        } else {
+            LatencyClass::Poor
+        }
+    }
+}
@@ -26,6 +26,17 @@
 //! - connecting two game instances (peer-to-peer via [iroh](https://www.iroh.computer/))
 //! - sending byte buffers of a constant size between the two game instances
 //! - doing so in a strictly turn-based manner (as described above)
+//! - deciding who moves first with a fair coin flip
+//! - optionally letting spectators watch the game live
+//! - sending chat messages regardless of whose turn it is, and optionally logging them
+//! - offering, accepting, and declining draws
+//! - negotiating takebacks of the last move
+//! - starting rematches over the same connection
+//! - playing simultaneous rounds, where neither player sees the other's turn early
+//! - playing several games at once over one connection
+//! - reconnecting after the connection drops
+//! - suspending a session when both players agree, and restoring it later
+//! - hosting several independent games from one process, like a lobby
 //!
 //! # What sfn-tpn cannot do
 //!
@@ -36,8 +47,10 @@
 //!
 //! Additionally, these features are currently considered out of scope for sfn-tpn:
 //!
-//! - connecting multiple game instances
+//! - connecting more than two players to one game, other than as spectators
 //! - anything not turn-based
+//! - picking a game back up after a crash, since only a suspended session can be restored
+//! - building the interface from your own channels or iroh endpoint
 //! - wasm is probably not supported because we use threading
 //!   - (I'd like it to be, to be able to use this with macroquad for wasm, but this spawns a host of issues :/)
 //!
@@ -45,24 +58,354 @@
 //!
 //! - See the examples directory at <https://github.com/wade-cheng/sfn-tpn>
 
+#[cfg(feature = "bincode")]
+mod bincode_turns;
+mod builder;
+mod chat;
+mod clock;
+#[cfg(any(feature = "bincode", feature = "postcard"))]
+mod codec;
+mod compression;
+mod connection_log;
+mod diff;
+mod draw;
+mod dynamic;
+mod emote;
+mod error;
+#[cfg(feature = "session-export")]
+mod export;
+mod frame;
+mod history;
+mod latency;
+mod metadata;
+mod pause;
+#[cfg(feature = "chess-pgn")]
+mod pgn;
+mod pool;
+#[cfg(feature = "postcard")]
+mod postcard_turns;
 mod protocol;
+mod reconnect;
+#[cfg(feature = "iroh-relay")]
+mod relay;
+mod rematch;
+mod replay;
+mod schema;
+mod session;
+mod shared;
+mod simultaneous;
+mod spectator;
+mod split;
+mod status;
+#[cfg(feature = "stream")]
+mod stream;
+mod suspend;
+mod sync;
+mod takeback;
+mod transfer;
+mod transform;
+#[cfg(any(feature = "bincode", feature = "postcard"))]
+mod typed;
+mod url;
 
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt, io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, ready},
+    time::{Duration, Instant, SystemTime},
+};
+
+use iroh::{Endpoint, SecretKey};
+use sha2::{Digest, Sha256};
 use tokio::{
     sync::{
+        Notify,
         mpsc::{self, error::TryRecvError},
         oneshot::{self},
+        watch,
     },
-    task::{self, JoinHandle},
+    task::JoinHandle,
+    time,
 };
 
+use clock::Clocks;
+use draw::Draw;
+use emote::EmoteLimit;
+use frame::ControlFrame;
+use history::History;
+use pause::Pause;
+use protocol::Move;
+use protocol::Transport;
+use rematch::Rematch;
+use replay::ReplayWriter;
+use suspend::Suspend;
+use takeback::Takeback;
+
+pub use builder::NetcodeInterfaceBuilder;
+pub use chat::{ChatLog, ChatMessage};
+pub use clock::{FlagFell, TimeControl};
+#[cfg(feature = "bincode")]
+pub use codec::BincodeCodec;
+#[cfg(feature = "postcard")]
+pub use codec::PostcardCodec;
+#[cfg(any(feature = "bincode", feature = "postcard"))]
+pub use codec::{CodecError, TurnCodec};
+#[cfg(feature = "zstd")]
+pub use compression::Compression;
+pub use connection_log::ConnectionEvent;
+pub use diff::{DiffEncoder, TurnDiff};
+pub use draw::DrawEvent;
+pub use dynamic::{DynNetcodeInterface, DynTurnPoll, MAX_DYN_SIZE};
+pub use emote::EmoteReceived;
+pub use error::NetcodeError;
+#[cfg(feature = "session-export")]
+pub use export::SESSION_EXPORT_VERSION;
+pub use history::{Direction, TurnRecord};
+pub use latency::{LatencyClass, LatencyThresholds};
+pub use pause::PauseEvent;
+#[cfg(feature = "chess-pgn")]
+pub use pgn::PgnHeaders;
+pub use pool::{GameId, NetcodeInterfacePool};
+pub use reconnect::Reconnect;
+pub use rematch::RematchEvent;
+pub use replay::{Replay, ReplayRecord};
+pub use schema::{SchemaError, TurnReader, TurnSchema, TurnWriter};
+pub use session::Session;
+pub use shared::SharedNetcodeInterface;
+pub use simultaneous::{Round, SimultaneousInterface};
+pub use spectator::{SpectatorInterface, SpectatorTicket};
+pub use split::{TurnReceiver, TurnSender};
+pub use status::NetcodeStatus;
+#[cfg(feature = "stream")]
+pub use stream::{IncomingTurns, Turns};
+pub use suspend::{SessionSnapshot, SuspendEvent};
+pub use sync::SyncTurnAdapter;
+pub use takeback::TakebackEvent;
+pub use transfer::TransferProgress;
+pub use transform::TurnTransformer;
+#[cfg(any(feature = "bincode", feature = "postcard"))]
+pub use typed::TypedInterface;
+pub use url::{decode_ticket_from_url, encode_ticket_as_url};
+
+/// The longest message that can be sent with
+/// [`send_message`](NetcodeInterface::send_message), in bytes.
+pub const MAX_MESSAGE_LEN: usize = 4096;
+
+/// The least time between two activity pings. Pings sent any sooner are
+/// dropped.
+const ACTIVITY_PING_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The longest [`setup`](NetcodeInterfaceBuilder::setup) the host can send,
+/// in bytes.
+pub const MAX_SETUP_LEN: usize = frame::MAX_PAYLOAD_LEN as usize - 1;
+
+/// Return the version of sfn-tpn in use, like `"2.0.0"`, for bug reports.
+pub fn sfn_tpn_version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// The release series of the iroh dependency in `Cargo.toml`, which cargo
+/// does not tell us, so it is kept in step by hand.
+const IROH_VERSION: &str = "0.90";
+
+/// Return the version of iroh sfn-tpn was built against, like `"0.90"`,
+/// for bug reports.
+///
+/// This is the release series of the iroh dependency in sfn-tpn's
+/// `Cargo.toml`. Any patch release in it may have been picked, so check
+/// `Cargo.lock` for the exact one.
+pub fn iroh_version() -> &'static str {
+    IROH_VERSION
+}
+
 /// Config used to create a new [`NetcodeInterface`].
 ///
-/// The user was either given a ticket, or is generating a new ticket.
+/// The user was either given a ticket, or is generating a new ticket, or is
+/// picking up a suspended session.
 pub enum Config {
     /// A ticket string obtained from the other player.
     Ticket(String),
+    /// An `iroh://` URI pointing at the other player, like from a "click to
+    /// play" link.
+    ///
+    /// It looks like `iroh://<node>?relay=<url>&addr=<ip:port>`, where the
+    /// node is the host's node ID, or a whole ticket. The parameters are
+    /// optional, percent-encoded, and `addr` can be repeated. With just a
+    /// node ID, the host is found through discovery. If the URI cannot be
+    /// parsed, the connection state becomes
+    /// [`Disconnected`](ConnectionState::Disconnected) with
+    /// [`NetcodeError::InvalidUri`].
+    Uri(String),
     /// Holds a oneshot sender that will send a newly generated ticket.
     TicketSender(oneshot::Sender<String>),
+    /// A snapshot of a suspended session. See [`Config::restore`].
+    Restore(Box<SessionSnapshot>),
+}
+
+impl Config {
+    /// Pick a [suspended](NetcodeInterface::suspend) session back up.
+    ///
+    /// Both players restore from the snapshots they got when they suspended,
+    /// on the same sides as before. No tickets are exchanged: the host binds
+    /// to the same node ID as before, and the client reconnects to it, so
+    /// the host should restore first. Only the other player from the
+    /// snapshot can connect.
+    ///
+    /// A deserialized snapshot needs its secret key handed back with
+    /// [`SessionSnapshot::with_secret_key`] first, or the connection state
+    /// becomes [`Disconnected`](ConnectionState::Disconnected) with
+    /// [`NetcodeError::MissingSecretKey`].
+    ///
+    /// The handshake fails with [`NetcodeError::SnapshotMismatch`] unless
+    /// both snapshots agree. The game, ply, whose turn it is, the seed, and
+    /// the session ID pick up where they left off. Everything else, like
+    /// clocks and history, starts over, and the interface is configured
+    /// with its builder as usual.
+    pub fn restore(snapshot: SessionSnapshot) -> Self {
+        Config::Restore(Box::new(snapshot))
+    }
+}
+
+/// Which side of the connection a player is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Role {
+    /// The player who generated the ticket, using [`Config::TicketSender`].
+    Host,
+    /// The player who connected using the host's ticket, with [`Config::Ticket`]
+    /// or [`Config::Uri`].
+    Client,
+}
+
+impl Role {
+    /// The role of the player using this config.
+    pub(crate) fn of(config: &Config) -> Role {
+        match config {
+            Config::Ticket(_) | Config::Uri(_) => Role::Client,
+            Config::TicketSender(_) => Role::Host,
+            Config::Restore(snapshot) => snapshot.role,
+        }
+    }
+
+    /// The role of the other player.
+    pub(crate) fn other(self) -> Role {
+        match self {
+            Role::Host => Role::Client,
+            Role::Client => Role::Host,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Host => write!(f, "host"),
+            Role::Client => write!(f, "client"),
+        }
+    }
+}
+
+/// The state of the connection to the other player.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Still looking for, or waiting on, the other player.
+    Connecting,
+    /// Connected to the other player.
+    Connected,
+    /// The connection could not be established, or was lost.
+    Disconnected(NetcodeError),
+}
+
+/// The result of polling for the other player's turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnPoll<const SIZE: usize> {
+    /// The other player's turn arrived. It is now the user's turn, unless more
+    /// turns from the same [move](NetcodeInterface::send_turns) are still to
+    /// be received.
+    Ready([u8; SIZE]),
+    /// The other player passed instead of taking their turn. It is now the
+    /// user's turn.
+    OpponentPassed,
+    /// The other player has not sent their turn yet.
+    Pending,
+    /// The other player has not sent their turn yet, and we have been waiting
+    /// for longer than the
+    /// [`turn_recv_timeout`](NetcodeInterfaceBuilder::turn_recv_timeout).
+    ///
+    /// The connection is left untouched. This is only a hint that the game
+    /// may want to show a "waiting..." indicator or prompt the user.
+    WaitingTooLong,
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GameOutcome {
+    /// Both players agreed to a draw, or both passed in a row with
+    /// [`auto_end_on_double_pass`](NetcodeInterfaceBuilder::auto_end_on_double_pass)
+    /// set.
+    Draw,
+}
+
+/// Something that happened to a move the user sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeliveryEvent {
+    /// The other player's game can now receive the move that made this
+    /// [`ply`](NetcodeInterface::ply).
+    Acked(u64),
+}
+
+/// The other player rejected the user's last move, so it is the user's turn
+/// again.
+///
+/// See [`reject_turn`](NetcodeInterface::reject_turn).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnRejected {
+    /// The [`ply`](NetcodeInterface::ply) the move would have made.
+    pub ply: u64,
+    pub reason: Vec<u8>,
+}
+
+/// The other player finished the game with this result, like checkmate, and
+/// is closing the connection.
+///
+/// See [`finish_game`](NetcodeInterface::finish_game).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameFinished(pub Vec<u8>);
+
+/// The other player did something, like selecting a piece or typing in chat,
+/// so the game can show that they are thinking.
+///
+/// See [`send_activity_ping`](NetcodeInterface::send_activity_ping).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpponentActive {
+    /// When the ping arrived.
+    pub at: Instant,
+}
+
+/// A player's [`turn_deadline`](NetcodeInterfaceBuilder::turn_deadline)
+/// passed before they moved.
+///
+/// See [`try_recv_timeout`](NetcodeInterface::try_recv_timeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnTimedOut {
+    pub player: Role,
+    /// The [`ply`](NetcodeInterface::ply) the player's move would have made.
+    pub ply: u64,
+}
+
+/// The other player's state hash did not match ours after a move.
+///
+/// See [`state_hash`](NetcodeInterfaceBuilder::state_hash).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateDivergence {
+    /// The [`ply`](NetcodeInterface::ply) after the move.
+    pub ply: u64,
+    pub ours: u64,
+    pub theirs: u64,
 }
 
 /// The interface for netcode.
@@ -74,28 +417,185 @@ pub enum Config {
 /// A [`new`][`NetcodeInterface::new`] `NetcodeInterface` should be created on
 /// the two players' machines. The first, the "server," must provide a oneshot
 /// sender that receives a newly generated ticket. The second, the "client,"
-/// must provide a ticket string from that server.
+/// must provide a ticket string from that server. Use a
+/// [`builder`][`NetcodeInterface::builder`] instead to configure timeouts.
 ///
-/// The server moves second and the client moves first.
+/// By default, the server moves second and the client moves first. This can
+/// be changed with [`first_move`][`NetcodeInterfaceBuilder::first_move`], or
+/// left to a coin flip with
+/// [`random_first_move`][`NetcodeInterfaceBuilder::random_first_move`].
 ///
 /// If it is the user's turn, they may:
 ///
-/// - [`send_turn`][`NetcodeInterface::send_turn`] once
+/// - [`send_turn`][`NetcodeInterface::send_turn`] or
+///   [`pass_turn`][`NetcodeInterface::pass_turn`] once
 /// - it will no longer be the user's turn
 ///
 /// If it is not the user's turn, they may:
 ///
 /// - [`try_recv_turn`][`NetcodeInterface::try_recv_turn`] repeatedly
-/// - if it returns `Ok`, it will be the user's turn.
+/// - if it returns [`TurnPoll::Ready`] or [`TurnPoll::OpponentPassed`], it
+///   will be the user's turn.
 ///
 /// Turns are represented as byte buffers of a constant size. Both players'
 /// buffer sizes must be the same.
 ///
+/// Once a game is over, both players may call
+/// [`new_game`][`NetcodeInterface::new_game`] to play another over the same
+/// connection, or one may
+/// [`offer_rematch`][`NetcodeInterface::offer_rematch`] for the other to
+/// accept.
+///
 /// Deviations from this procedure are undefined behavior.
 pub struct NetcodeInterface<const SIZE: usize> {
-    is_my_turn: bool,
-    recv_from_iroh: mpsc::Receiver<[u8; SIZE]>,
-    send_to_iroh: mpsc::Sender<[u8; SIZE]>,
+    role: Role,
+    /// The number of the current game, counting from zero.
+    game: u32,
+    /// Whether it is the user's turn, or `None` if we do not know who moves
+    /// first yet.
+    is_my_turn: Option<bool>,
+    /// Mirrors [`my_turn`](NetcodeInterface::my_turn) for other tasks.
+    my_turn_watch: watch::Sender<bool>,
+    /// Who moved first in the first game, or `None` if the coin has not been
+    /// flipped yet.
+    first_move: watch::Receiver<Option<Role>>,
+    /// Whether the first move has swapped sides since the first game.
+    first_move_swapped: bool,
+    /// Whether we asked for a new game that has not started yet.
+    we_want_new_game: bool,
+    /// Whether the other player asked for a new game that has not started yet.
+    they_want_new_game: bool,
+    /// Whether the game has yet to be told the other player wants a new game.
+    new_game_unseen: bool,
+    /// Whether the other player passed, and the game has yet to be told.
+    they_passed: bool,
+    /// Whether either player finished the game.
+    finished: bool,
+    /// Whether the other player acknowledged the user's result.
+    finish_acked: bool,
+    /// Results from the other player that the game has yet to receive.
+    finishes: VecDeque<GameFinished>,
+    draw: Draw,
+    pause: Pause,
+    suspend: Suspend,
+    /// The snapshot both players agreed to suspend at, if they did.
+    snapshot: Option<SessionSnapshot>,
+    /// When the game paused, if it is paused.
+    paused_at: Option<Instant>,
+    /// How many moves and passes have been made in the current game.
+    plies: u32,
+    /// Mirrors [`ply`](NetcodeInterface::ply) for [`NetcodeStatus`].
+    ply_watch: watch::Sender<u64>,
+    /// How many of the last plies were passes.
+    consecutive_passes: u32,
+    auto_end_on_double_pass: bool,
+    takeback: Takeback,
+    /// Whether the game received the other player's last move, and has yet to
+    /// accept it by moving, or reject it.
+    can_reject: bool,
+    /// Rejections of the user's moves that the game has yet to receive.
+    rejections: VecDeque<TurnRejected>,
+    rematch: Rematch,
+    /// Messages from the other player that the game has yet to receive.
+    messages: VecDeque<Vec<u8>>,
+    /// Limits the emotes the user sends.
+    emotes_sent: EmoteLimit,
+    /// Limits the emotes the other player sends, in case they do not.
+    emotes_received: EmoteLimit,
+    /// Emotes from the other player that the game has yet to receive.
+    emotes: VecDeque<EmoteReceived>,
+    chat_log: Option<Arc<Mutex<ChatLog>>>,
+    history: History<SIZE>,
+    replay: Option<ReplayWriter>,
+    /// Why the replay stopped being written, if it did.
+    replay_error: Option<io::Error>,
+    /// Turns from the other player's current move that the game has yet to
+    /// receive.
+    received: VecDeque<[u8; SIZE]>,
+    /// When the move `received` is from arrived.
+    received_at: Option<Instant>,
+    /// The turns of the move the user started with `begin_turn`, if any.
+    bundle: Option<Vec<[u8; SIZE]>>,
+    state_hash: Option<Box<dyn Fn() -> u64 + Send>>,
+    /// The other player's state hash for the move in `received`.
+    received_state_hash: Option<u64>,
+    /// The ply and the other player's state hash for the move the game
+    /// received last, if we have yet to compare it with ours.
+    unchecked_state_hash: Option<(u32, u64)>,
+    /// Divergences that the game has yet to receive.
+    divergences: VecDeque<StateDivergence>,
+    transformer: Option<Box<dyn TurnTransformer<SIZE>>>,
+    recv_from_iroh: mpsc::UnboundedReceiver<(Instant, Move<SIZE>)>,
+    send_to_iroh: mpsc::Sender<Move<SIZE>>,
+    recv_control_from_iroh: mpsc::UnboundedReceiver<ControlFrame>,
+    send_control_to_iroh: mpsc::UnboundedSender<ControlFrame>,
+    recv_activity_from_iroh: mpsc::UnboundedReceiver<Instant>,
+    send_activity_to_iroh: mpsc::UnboundedSender<()>,
+    send_reconnect_to_iroh: mpsc::UnboundedSender<()>,
+    /// Notified whenever the protocol sends us a move or control frame.
+    turn_notify: Arc<Notify>,
+    /// Every step the connection went through, added to by the protocol.
+    connection_log: Arc<Mutex<Vec<ConnectionEvent>>>,
+    /// When the user last sent an activity ping.
+    last_activity_ping: Option<Instant>,
+    connection_state: watch::Receiver<ConnectionState>,
+    /// Resolves once the connection is lost, kept between calls to
+    /// [`poll_recv_turn`](NetcodeInterface::poll_recv_turn) so it only
+    /// allocates once.
+    disconnected: Option<Pin<Box<dyn Future<Output = NetcodeError> + Send>>>,
+    spectator_ticket: watch::Receiver<Option<SpectatorTicket>>,
+    bytes_in_flight: watch::Receiver<usize>,
+    connected_at: watch::Receiver<Option<Instant>>,
+    established_at: watch::Receiver<Option<SystemTime>>,
+    setup: watch::Receiver<Option<Vec<u8>>>,
+    seed: watch::Receiver<Option<[u8; 32]>>,
+    session_id: watch::Receiver<Option<[u8; 16]>>,
+    /// How many moves the other player acknowledged.
+    acked: watch::Receiver<u64>,
+    /// How many of the user's moves were written to the stream in full.
+    written: watch::Receiver<u64>,
+    rtt: watch::Receiver<Option<Duration>>,
+    latency_thresholds: LatencyThresholds,
+    /// How many bytes of turns were sent, then received.
+    turn_bytes: watch::Receiver<(u64, u64)>,
+    /// How many bytes of compressed frames there were before, then after,
+    /// compression.
+    compressed_bytes: watch::Receiver<(u64, u64)>,
+    outgoing_transfer: watch::Receiver<Option<TransferProgress>>,
+    incoming_transfer: watch::Receiver<Option<TransferProgress>>,
+    /// How many moves we sent.
+    sent_moves: u64,
+    /// The number and ply of each move we sent that the game has yet to hear
+    /// was acknowledged.
+    unacked: VecDeque<(u64, u32)>,
+    /// Acknowledgments that the game has yet to receive.
+    deliveries: VecDeque<DeliveryEvent>,
+    /// The user's own metadata, both hard and soft.
+    metadata: BTreeMap<String, String>,
+    peer_metadata: watch::Receiver<Option<BTreeMap<String, String>>>,
+    node_keys: watch::Receiver<Option<([u8; 32], [u8; 32])>>,
+    endpoint: watch::Receiver<Option<Endpoint>>,
+    connect_timeout: Option<Duration>,
+    turn_recv_timeout: Option<Duration>,
+    turn_deadline: Option<Duration>,
+    /// When the user's current turn became available to them, if it has
+    /// since the first game started.
+    turn_started: Option<Instant>,
+    /// Whether the deadline of the user's current turn already passed.
+    deadline_passed: bool,
+    /// Timeouts that the game has yet to receive.
+    timeouts: VecDeque<TurnTimedOut>,
+    clocks: Option<Clocks>,
+    /// Flag falls that the game has yet to receive.
+    flag_falls: VecDeque<FlagFell>,
+    /// When we started waiting on the other player's turn.
+    waiting_since: Instant,
+    /// When the user sent the move the other player has yet to answer.
+    sent_at: Option<Instant>,
+    last_turn_latency: Option<Duration>,
+    /// When each of the latest plies was made, oldest first.
+    recent_plies: VecDeque<Instant>,
+    turn_rate_window: usize,
     /// A handle to the thread running iroh under the hood.
     ///
     /// Might need to be dropped if we want to be pedantic about the code.
@@ -103,59 +603,3070 @@ pub struct NetcodeInterface<const SIZE: usize> {
 }
 
 impl<const SIZE: usize> NetcodeInterface<SIZE> {
-    /// Create a new interface.
+    /// Create a new interface with the default options.
     ///
     /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
     pub fn new(config: Config) -> Self {
-        // hand-coding a bidirectional channel, sorta :p
-        let (send_to_iroh, recv_from_game) = mpsc::channel(1);
-        let (send_to_game, recv_from_iroh) = mpsc::channel(1);
-        let is_my_turn = match &config {
-            Config::Ticket(_) => true,
-            Config::TicketSender(_) => false,
-        };
-        let _iroh_handle = task::spawn(protocol::start_iroh_protocol(
-            send_to_game,
-            recv_from_game,
-            config,
-        ));
+        Self::builder(config).build()
+    }
+
+    /// Create a builder to configure a new interface.
+    pub fn builder(config: Config) -> NetcodeInterfaceBuilder<SIZE> {
+        NetcodeInterfaceBuilder::new(config)
+    }
+
+    /// Wrap the interface so it can be used through a shared reference, for
+    /// GUI frameworks that only pass `&` references into event handlers.
+    pub fn into_shared(self) -> SharedNetcodeInterface<SIZE> {
+        SharedNetcodeInterface::new(self)
+    }
+
+    /// Spawn the iroh protocol and create the interface around it.
+    fn from_builder(builder: NetcodeInterfaceBuilder<SIZE>) -> Self {
+        let role = builder.transport.role();
+        // a restored session picks up where it left off.
+        let restored = match &builder.transport {
+            Transport::Config(Config::Restore(snapshot)) => Some(snapshot.as_ref().clone()),
+            _ => None,
+        };
+        let first_move = match &restored {
+            Some(snapshot) => Some(snapshot.first_move),
+            None => builder.first_move,
+        };
+        let is_my_turn = match &restored {
+            Some(snapshot) => Some(snapshot.my_turn),
+            None => first_move.map(|first| first == role),
+        };
+        let metadata = builder.metadata.all();
+        let replay = builder.replay.map(|writer| {
+            let size = u32::try_from(SIZE).expect("turns are small");
+            ReplayWriter::new(writer, size, &builder.metadata)
+        });
+        let protocol::ProtocolHandles {
+            recv_from_iroh,
+            send_to_iroh,
+            recv_control_from_iroh,
+            send_control_to_iroh,
+            recv_activity_from_iroh,
+            send_activity_to_iroh,
+            send_reconnect_to_iroh,
+            connection_state,
+            spectator_ticket,
+            first_move,
+            bytes_in_flight,
+            connected_at,
+            established_at,
+            setup,
+            seed,
+            session_id,
+            acked,
+            written,
+            rtt,
+            turn_bytes,
+            compressed_bytes,
+            outgoing_transfer,
+            incoming_transfer,
+            node_keys,
+            peer_metadata,
+            endpoint,
+            turn_notify,
+            connection_log,
+            iroh_handle: _iroh_handle,
+        } = protocol::spawn_iroh_protocol(
+            builder.transport,
+            protocol::ProtocolOptions {
+                first_move,
+                check_internet: builder.check_internet,
+                internet_check_target: builder.internet_check_target,
+                relay_url: builder.relay_url,
+                reconnect: builder.reconnect,
+                max_spectators: builder.max_spectators,
+                simultaneous: false,
+                encoder: builder.encoder,
+                compression: builder.compression,
+                size: builder.size,
+                turn_sizes: builder.turn_sizes,
+                time_control: builder.time_control,
+                setup: builder.setup,
+                metadata: builder.metadata,
+            },
+        );
 
         Self {
+            role,
+            game: restored.as_ref().map_or(0, |snapshot| snapshot.game),
             is_my_turn,
+            my_turn_watch: watch::Sender::new(is_my_turn.unwrap_or(false)),
+            first_move,
+            first_move_swapped: false,
+            we_want_new_game: false,
+            they_want_new_game: false,
+            new_game_unseen: false,
+            they_passed: false,
+            finished: false,
+            finish_acked: false,
+            finishes: VecDeque::new(),
+            draw: Draw::default(),
+            pause: Pause::default(),
+            suspend: Suspend::default(),
+            snapshot: None,
+            paused_at: None,
+            plies: restored.as_ref().map_or(0, |snapshot| snapshot.plies),
+            ply_watch: watch::Sender::new(
+                restored
+                    .as_ref()
+                    .map_or(0, |snapshot| u64::from(snapshot.plies)),
+            ),
+            consecutive_passes: 0,
+            auto_end_on_double_pass: builder.auto_end_on_double_pass,
+            takeback: Takeback::default(),
+            can_reject: false,
+            rejections: VecDeque::new(),
+            rematch: Rematch::default(),
+            messages: VecDeque::new(),
+            emotes_sent: EmoteLimit::default(),
+            emotes_received: EmoteLimit::default(),
+            emotes: VecDeque::new(),
+            chat_log: builder.chat_log,
+            history: History::new(builder.record_history),
+            replay,
+            replay_error: None,
+            received: VecDeque::new(),
+            received_at: None,
+            bundle: None,
+            state_hash: builder.state_hash,
+            received_state_hash: None,
+            unchecked_state_hash: None,
+            divergences: VecDeque::new(),
+            transformer: builder.transformer,
             _iroh_handle,
             recv_from_iroh,
             send_to_iroh,
+            recv_control_from_iroh,
+            send_control_to_iroh,
+            recv_activity_from_iroh,
+            send_activity_to_iroh,
+            send_reconnect_to_iroh,
+            turn_notify,
+            connection_log,
+            last_activity_ping: None,
+            disconnected: None,
+            connection_state,
+            spectator_ticket,
+            bytes_in_flight,
+            connected_at,
+            established_at,
+            setup,
+            seed,
+            session_id,
+            acked,
+            written,
+            rtt,
+            latency_thresholds: builder.latency_thresholds,
+            turn_bytes,
+            compressed_bytes,
+            outgoing_transfer,
+            incoming_transfer,
+            sent_moves: 0,
+            unacked: VecDeque::new(),
+            deliveries: VecDeque::new(),
+            metadata,
+            peer_metadata,
+            node_keys,
+            endpoint,
+            connect_timeout: builder.connect_timeout,
+            turn_recv_timeout: builder.turn_recv_timeout,
+            turn_deadline: builder.turn_deadline,
+            turn_started: None,
+            deadline_passed: false,
+            timeouts: VecDeque::new(),
+            clocks: builder.time_control.map(Clocks::new),
+            flag_falls: VecDeque::new(),
+            waiting_since: Instant::now(),
+            sent_at: None,
+            last_turn_latency: None,
+            recent_plies: VecDeque::new(),
+            turn_rate_window: builder.turn_rate_window,
+        }
+    }
+
+    /// Wait until the other player has connected.
+    ///
+    /// Returns [`NetcodeError::ConnectTimeout`] if the
+    /// [`connect_timeout`](NetcodeInterfaceBuilder::connect_timeout) elapses
+    /// first, or the error that stopped the connection from being established.
+    pub async fn wait_for_connection(&mut self) -> Result<(), NetcodeError> {
+        let mut state = self.connection_state.clone();
+        let settled = state.wait_for(|s| *s != ConnectionState::Connecting);
+        let settled = match self.connect_timeout {
+            Some(timeout) => time::timeout(timeout, settled)
+                .await
+                .map_err(|_| NetcodeError::ConnectTimeout)?,
+            None => settled.await,
+        };
+
+        match settled.as_deref() {
+            Ok(ConnectionState::Connected) => Ok(()),
+            Ok(ConnectionState::Disconnected(e)) => Err(e.clone()),
+            Ok(ConnectionState::Connecting) => unreachable!("we waited for this to change"),
+            Err(_) => Err(NetcodeError::Disconnected(
+                "the iroh protocol stopped".to_string(),
+            )),
+        }
+    }
+
+    /// Wait until the other player has connected, then return the host's
+    /// [`setup`](NetcodeInterfaceBuilder::setup), or `None` if they did not
+    /// configure one.
+    ///
+    /// The setup is exchanged before any turns, so it is known before either
+    /// player can receive one. Returns the same errors as
+    /// [`wait_for_connection`](NetcodeInterface::wait_for_connection).
+    pub async fn await_setup(&mut self) -> Result<Option<Vec<u8>>, NetcodeError> {
+        self.wait_for_connection().await?;
+        Ok(self.setup.borrow().clone())
+    }
+
+    /// Return a random seed that both players agree on, for games with random
+    /// elements like shuffled decks.
+    ///
+    /// Both players contribute to the seed during the handshake, committing
+    /// to their contribution before seeing the other's, so neither can bias
+    /// it. Returns `None` until the connection is established. Each
+    /// connection gets a new seed.
+    pub fn shared_seed(&self) -> Option<[u8; 32]> {
+        *self.seed.borrow()
+    }
+
+    /// Return an ID for this session that both players agree on, for
+    /// correlating logs, replays, and bug reports between their machines.
+    ///
+    /// Each player picks half of the ID at random and sends it in their
+    /// hello. Returns `None` until the connection is established. Each
+    /// connection gets a new ID.
+    pub fn session_id(&self) -> Option<[u8; 16]> {
+        *self.session_id.borrow()
+    }
+
+    /// Return the iroh endpoint the game connects through, for what the
+    /// interface does not cover, like opening more streams, or inspecting
+    /// statistics.
+    ///
+    /// **Unstable:** this exposes iroh's own API, which changes between its
+    /// versions, even when sfn-tpn's does not. Returns `None` until the
+    /// endpoint is bound in the background, and always for games in a
+    /// [`Session`], which share an endpoint they do not own.
+    pub fn iroh_endpoint(&self) -> Option<Endpoint> {
+        self.endpoint.borrow().clone()
+    }
+
+    /// Return a fingerprint of this connection that both players can compare
+    /// out of band, like by reading it aloud, to check that they are talking
+    /// to each other rather than to someone in between.
+    ///
+    /// It is the SHA-256 hash of both players' node IDs, in sorted order, so
+    /// both players get the same one. This is only a casual check, not
+    /// authentication. Returns `None` unless connected, and always for games
+    /// in a [`Session`], which share a connection they do not own.
+    pub fn connection_fingerprint(&self) -> Option<[u8; 32]> {
+        if *self.connection_state.borrow() != ConnectionState::Connected {
+            return None;
+        }
+        let (secret_key, theirs) = (*self.node_keys.borrow())?;
+        let ours = *SecretKey::from_bytes(&secret_key).public().as_bytes();
+        let (low, high) = if ours < theirs {
+            (ours, theirs)
+        } else {
+            (theirs, ours)
+        };
+        Some(
+            Sha256::new()
+                .chain_update(low)
+                .chain_update(high)
+                .finalize()
+                .into(),
+        )
+    }
+
+    /// Return the other player's
+    /// [`metadata`](NetcodeInterfaceBuilder::metadata), both hard and soft,
+    /// or `None` until the connection is established.
+    ///
+    /// Useful for tolerating soft differences, like the other player's
+    /// locale, or for showing their
+    /// [`soft_metadata`](NetcodeInterfaceBuilder::soft_metadata), like their
+    /// name.
+    pub fn peer_metadata(&self) -> Option<BTreeMap<String, String>> {
+        self.peer_metadata.borrow().clone()
+    }
+
+    /// Return the current state of the connection.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection_state.borrow().clone()
+    }
+
+    /// Return every step the connection went through so far, oldest first,
+    /// for debugging why it failed.
+    ///
+    /// This is meant for developers, like to print when a connection takes
+    /// too long, so its events may change between versions. See
+    /// [`ConnectionEvent`] for what is logged.
+    pub fn connection_log(&self) -> Vec<ConnectionEvent> {
+        self.connection_log
+            .lock()
+            .expect("nothing panics while holding the lock")
+            .clone()
+    }
+
+    /// Return a one-line summary of the connection, for debug overlays and
+    /// logs, like
+    /// `Connected to node 1a2b3c4d5e as host for 3m42s, game 0 ply 47, rtt 45ms, 0 errors`.
+    ///
+    /// Everything in it comes from other methods: the
+    /// [`connection_state`](NetcodeInterface::connection_state), the start
+    /// of the other player's node ID, the
+    /// [`connection_age`](NetcodeInterface::connection_age), the game
+    /// number and its [`ply`](NetcodeInterface::ply), the latest
+    /// [`rtt`](NetcodeInterface::rtt), and how many times the
+    /// [`connection_log`](NetcodeInterface::connection_log) says the
+    /// connection was lost. It is meant to be read, or searched for in logs,
+    /// but not parsed, so it may change between versions.
+    pub fn connection_summary(&self) -> String {
+        let peer = (*self.node_keys.borrow()).map_or_else(
+            || "the other player".to_string(),
+            |(_, theirs)| {
+                let short: String = theirs[..5]
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect();
+                format!("node {short}")
+            },
+        );
+        let state = match self.connection_state() {
+            ConnectionState::Connecting => format!("Connecting as {}", self.role),
+            ConnectionState::Connected => {
+                let secs = self.connection_age().unwrap_or_default().as_secs();
+                let age = match (secs / 3600, secs / 60 % 60, secs % 60) {
+                    (0, 0, s) => format!("{s}s"),
+                    (0, m, s) => format!("{m}m{s:02}s"),
+                    (h, m, s) => format!("{h}h{m:02}m{s:02}s"),
+                };
+                format!("Connected to {peer} as {} for {age}", self.role)
+            }
+            ConnectionState::Disconnected(e) => {
+                format!("Disconnected from {peer} as {} ({e})", self.role)
+            }
+        };
+        let rtt = self.rtt().map_or_else(
+            || "unknown".to_string(),
+            |rtt| format!("{}ms", rtt.as_millis()),
+        );
+        let errors = self
+            .connection_log()
+            .iter()
+            .filter(|event| matches!(event, ConnectionEvent::Disconnected { .. }))
+            .count();
+        format!(
+            "{state}, game {} ply {}, rtt {rtt}, {errors} errors",
+            self.game,
+            self.ply()
+        )
+    }
+
+    /// Reconnect to the other player after the connection dropped, if the
+    /// game was built with [`Reconnect::Manual`].
+    ///
+    /// The client dials the host again, while the host waits for the client
+    /// to. Returns `Ok(())` right away if we are connected, or once we are
+    /// again, with any lost moves sent again. Otherwise, returns why we are
+    /// disconnected, and may be called again to retry.
+    pub async fn reconnect(&mut self) -> Result<(), NetcodeError> {
+        let mut state = self.connection_state.clone();
+        match self.connection_state() {
+            ConnectionState::Disconnected(e) => {
+                state.mark_unchanged();
+                // the protocol only listens for requests once it gave up on
+                // the connection, and stops once it is never reconnecting.
+                if self.send_reconnect_to_iroh.send(()).is_err() || state.changed().await.is_err() {
+                    return Err(e);
+                }
+            }
+            ConnectionState::Connecting | ConnectionState::Connected => {}
+        }
+        match state
+            .wait_for(|s| *s != ConnectionState::Connecting)
+            .await
+            .as_deref()
+        {
+            Ok(ConnectionState::Connected) => Ok(()),
+            Ok(ConnectionState::Disconnected(e)) => Err(e.clone()),
+            Ok(ConnectionState::Connecting) => unreachable!("we waited for this to change"),
+            Err(_) => Err(NetcodeError::Disconnected(
+                "the iroh protocol stopped".to_string(),
+            )),
+        }
+    }
+
+    /// Return a ticket that lets a [`SpectatorInterface`] watch this game.
+    ///
+    /// Only the host generates spectator tickets, and only if spectators are
+    /// [allowed](NetcodeInterfaceBuilder::allow_spectators). The ticket is
+    /// available at the same time as the player's ticket.
+    pub fn spectator_ticket(&self) -> Option<SpectatorTicket> {
+        self.spectator_ticket.borrow().clone()
+    }
+
+    /// Return how many bytes the user sent that are still waiting to be
+    /// accepted by the connection.
+    ///
+    /// This stays above zero while the connection is congested, so the game
+    /// can warn the user about poor connectivity. Returns `0` until the
+    /// connection is established.
+    pub fn bytes_in_flight(&self) -> usize {
+        *self.bytes_in_flight.borrow()
+    }
+
+    /// Return how long the connection has been established, or `None` if it
+    /// is still connecting or was lost.
+    pub fn connection_age(&self) -> Option<Duration> {
+        if *self.connection_state.borrow() != ConnectionState::Connected {
+            return None;
+        }
+        self.connected_at.borrow().map(|at| at.elapsed())
+    }
+
+    /// Return the connection's latest round-trip time, sampled every second,
+    /// or `None` if it is still connecting or was lost.
+    ///
+    /// Always `None` for games opened from a [`Session`].
+    pub fn rtt(&self) -> Option<Duration> {
+        if *self.connection_state.borrow() != ConnectionState::Connected {
+            return None;
+        }
+        *self.rtt.borrow()
+    }
+
+    /// Return how good the connection is, binned by the
+    /// [`latency_thresholds`](NetcodeInterfaceBuilder::latency_thresholds),
+    /// for a signal-strength indicator.
+    ///
+    /// Returns [`LatencyClass::Unknown`] whenever [`rtt`](NetcodeInterface::rtt)
+    /// returns `None`.
+    pub fn peer_latency_class(&self) -> LatencyClass {
+        match self.rtt() {
+            Some(rtt) => self.latency_thresholds.classify(rtt),
+            None => LatencyClass::Unknown,
         }
     }
 
+    /// Return how many bytes of turns the user sent since the interface was
+    /// created, for games on metered connections.
+    ///
+    /// Only counts the turns themselves, as sent over the connection, so
+    /// [`diff_encoder`](NetcodeInterfaceBuilder::diff_encoder) and
+    /// [`turn_sizes`](NetcodeInterfaceBuilder::turn_sizes) are accounted for,
+    /// but everything else the protocol sends is not.
+    pub fn turn_bytes_sent_total(&self) -> u64 {
+        self.turn_bytes.borrow().0
+    }
+
+    /// Return how many bytes of turns the other player sent since the
+    /// interface was created, counted like
+    /// [`turn_bytes_sent_total`](NetcodeInterface::turn_bytes_sent_total).
+    ///
+    /// Turns are counted as they arrive, even if the game has yet to receive
+    /// them.
+    pub fn turn_bytes_received_total(&self) -> u64 {
+        self.turn_bytes.borrow().1
+    }
+
+    /// Return how many bytes the moves sent or received
+    /// [compressed](NetcodeInterfaceBuilder::compression) since the interface
+    /// was created took up before compression.
+    ///
+    /// Compare it with
+    /// [`compressed_bytes_total`](NetcodeInterface::compressed_bytes_total)
+    /// to see how much compression saves. Moves too small to be worth
+    /// compressing are sent as-is, and not counted.
+    pub fn uncompressed_bytes_total(&self) -> u64 {
+        self.compressed_bytes.borrow().0
+    }
+
+    /// Return how many bytes the moves sent or received compressed since the
+    /// interface was created took up after compression, counted like
+    /// [`uncompressed_bytes_total`](NetcodeInterface::uncompressed_bytes_total).
+    pub fn compressed_bytes_total(&self) -> u64 {
+        self.compressed_bytes.borrow().1
+    }
+
+    /// Return how much of the user's latest large move has been sent, or
+    /// `None` if they have yet to send one.
+    ///
+    /// Moves over 16 KiB, like a whole serialized board, are sent in chunks,
+    /// so that acks and control frames are not held up behind them. It is
+    /// done once [`done`](TransferProgress::done) reaches
+    /// [`total`](TransferProgress::total).
+    pub fn outgoing_transfer(&self) -> Option<TransferProgress> {
+        *self.outgoing_transfer.borrow()
+    }
+
+    /// Return how much of the other player's latest large move has arrived,
+    /// like [`outgoing_transfer`](NetcodeInterface::outgoing_transfer).
+    ///
+    /// The move is only received once all of it has arrived, so a connection
+    /// lost partway through never delivers part of it.
+    pub fn incoming_transfer(&self) -> Option<TransferProgress> {
+        *self.incoming_transfer.borrow()
+    }
+
+    /// Return the wall-clock time the connection was established, or `None`
+    /// if it is still connecting or was lost.
+    ///
+    /// Both players get the same time, by the client's clock, so it can be
+    /// used to line up logs from both machines.
+    pub fn connection_established_at(&self) -> Option<SystemTime> {
+        if *self.connection_state.borrow() != ConnectionState::Connected {
+            return None;
+        }
+        *self.established_at.borrow()
+    }
+
+    /// Return how long it took from the user's last move to the other
+    /// player's answer, or `None` if they have not answered a move yet.
+    ///
+    /// This is measured from [`send_turn`](NetcodeInterface::send_turn) or
+    /// [`pass_turn`](NetcodeInterface::pass_turn) to receiving the last turn
+    /// of the other player's next move, or their pass. It includes the other
+    /// player's thinking time, so it reflects the latency players perceive
+    /// rather than the connection's round-trip time.
+    pub fn last_turn_latency(&self) -> Option<Duration> {
+        self.last_turn_latency
+    }
+
+    /// Return how long the other player's turn has been waiting for
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn), or `None` if no
+    /// turn is waiting, like to flash a notification once it waited a few
+    /// seconds.
+    ///
+    /// A move's turns all arrive together, so they share its age. Takes
+    /// `&mut self` because it takes the move out of the channel it arrived
+    /// on, without receiving it. Passes are only noticed by `try_recv_turn`.
+    pub fn turn_age(&mut self) -> Option<Duration> {
+        if self.my_turn() || (self.received.is_empty() && !self.recv_move()) {
+            return None;
+        }
+        self.received_at.map(|arrived| arrived.elapsed())
+    }
+
+    /// Return how many moves and passes are made per second, averaged over
+    /// the latest
+    /// [`turn_rate_window`](NetcodeInterfaceBuilder::turn_rate_window)
+    /// of them this session.
+    ///
+    /// Both players' plies count, including the time the players spend
+    /// thinking. Returns 0.0 until at least two plies were made.
+    pub fn turn_rate(&self) -> f64 {
+        let (Some(oldest), Some(newest)) = (self.recent_plies.front(), self.recent_plies.back())
+        else {
+            return 0.0;
+        };
+        let elapsed = newest.duration_since(*oldest).as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        (self.recent_plies.len() - 1) as f64 / elapsed
+    }
+
+    /// Return which side of the connection the user is on.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Return every turn sent or received this session, oldest first, if
+    /// [`record_history`](NetcodeInterfaceBuilder::record_history) is set.
+    /// Otherwise, returns an empty slice.
+    ///
+    /// Turns from every game are kept, and turns that were taken back are
+    /// forgotten. Passes are not turns, so they are never recorded.
+    pub fn history(&self) -> &[TurnRecord<SIZE>] {
+        self.history.records()
+    }
+
+    /// Return how long the move of the turn at `index` in the
+    /// [`history`](NetcodeInterface::history) took, for reconstructing game
+    /// clocks.
+    ///
+    /// This is the time between the previous move and this one, as seen by
+    /// the user. So, the other player's moves also include the time it took
+    /// for them to arrive, and for the game to receive them. Returns `None`
+    /// if there is no such turn, or it is in the first move of its game.
+    pub fn wall_clock_time(&self, index: usize) -> Option<Duration> {
+        self.history.wall_clock_time(index)
+    }
+
+    /// Return why the [`replay`](NetcodeInterfaceBuilder::replay) stopped
+    /// being written, if writing it failed.
+    ///
+    /// The game goes on without it, so turns after the failure are missing
+    /// from the replay, and the last record may be truncated.
+    pub fn replay_error(&self) -> Option<&io::Error> {
+        self.replay_error.as_ref()
+    }
+
+    /// Return whether the user moves first in the current game.
+    ///
+    /// Returns `None` until the coin is flipped if the first move is
+    /// [random](NetcodeInterfaceBuilder::random_first_move). The coin is
+    /// flipped during the handshake, so this is always known once
+    /// [`wait_for_connection`](NetcodeInterface::wait_for_connection) returns
+    /// successfully.
+    pub fn moves_first(&self) -> Option<bool> {
+        self.first_move
+            .borrow()
+            .map(|first| (first == self.role) != self.first_move_swapped)
+    }
+
+    /// Return how many moves and passes have been made in the current game.
+    ///
+    /// Starts at 0 for every new game, and goes back by one for every
+    /// [takeback](NetcodeInterface::request_takeback).
+    pub fn ply(&self) -> u64 {
+        u64::from(self.plies)
+    }
+
+    /// Fill in whose turn it is, if we only just learned who moves first.
+    fn resolve_first_move(&mut self) {
+        if self.is_my_turn.is_none() {
+            self.is_my_turn = self.moves_first();
+            self.publish_my_turn();
+        }
+    }
+
+    /// Tell other tasks whether it is the user's turn, if that changed.
+    ///
+    /// Called wherever the turn may have flipped, so the mirror never stays
+    /// stale past the call that flipped it.
+    fn publish_my_turn(&self) {
+        let mine = self.my_turn();
+        self.my_turn_watch
+            .send_if_modified(|theirs| std::mem::replace(theirs, mine) != mine);
+    }
+
     /// Send a turn to the other player.
     ///
     /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the game is [paused](NetcodeInterface::request_pause),
+    /// either player [finished](NetcodeInterface::finish_game) it, or the
+    /// user asked to [suspend](NetcodeInterface::suspend) and has yet to hear
+    /// back.
     pub fn send_turn(&mut self, turn: &[u8; SIZE]) {
-        assert!(self.is_my_turn);
-        self.send_to_iroh
-            .try_send(*turn)
+        self.send_turns(std::slice::from_ref(turn));
+    }
+
+    /// Send several turns to the other player as a single move.
+    ///
+    /// For games where one move takes several turn buffers. The turns are
+    /// sent together, and the other player receives them one at a time from
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn). It is only their
+    /// turn once they have received the last one.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `turns` is empty, the game is
+    /// [paused](NetcodeInterface::request_pause), either player
+    /// [finished](NetcodeInterface::finish_game) it, or the user asked to
+    /// [suspend](NetcodeInterface::suspend) and has yet to hear back.
+    pub fn send_turns(&mut self, turns: &[[u8; SIZE]]) {
+        assert!(!turns.is_empty());
+        if let Err(e) = self.check_send() {
+            panic!("cannot send a turn: {e}");
+        }
+        let permit = self
+            .send_to_iroh
+            .clone()
+            .try_reserve_owned()
             .expect("we should never have a full buffer");
-        self.is_my_turn = false;
+        permit.send(self.build_and_record_move(turns));
     }
 
-    /// Check if the other player has sent a turn to the user.
+    /// Make sure the user can send a turn, for both ways of sending one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not the user's turn, or the user asked to
+    /// [suspend](NetcodeInterface::suspend) and has yet to hear back.
+    fn check_send(&mut self) -> Result<(), NetcodeError> {
+        self.drain_controls();
+        if self.finished {
+            return Err(NetcodeError::GameOver);
+        }
+        self.resolve_first_move();
+        assert!(self.my_turn());
+        assert!(!self.suspend.requested());
+        if self.pause.paused() {
+            return Err(NetcodeError::Paused);
+        }
+        Ok(())
+    }
+
+    /// Stop the user's clock, and build the move with `turns` for the
+    /// protocol to send. It is the other player's turn once it is built, so
+    /// it must be sent.
+    fn build_and_record_move(&mut self, turns: &[[u8; SIZE]]) -> Move<SIZE> {
+        let clock = self.stop_clock();
+        let sent = Move {
+            game: self.game,
+            state_hash: self.state_hash.as_ref().map(|hash| hash()),
+            clock,
+            turns: turns.iter().map(|&turn| self.transform_out(turn)).collect(),
+        };
+        self.sent_turn(false);
+        self.sent_move();
+        for &turn in turns {
+            self.record_turn(turn, Direction::Sent, self.plies);
+        }
+        sent
+    }
+
+    /// Send a turn to the other player, waiting for room in the buffer
+    /// instead of panicking if it is full.
+    ///
+    /// Returns [`NetcodeError::SendFailed`] if the iroh protocol stopped,
+    /// [`NetcodeError::Paused`] if the game is
+    /// [paused](NetcodeInterface::request_pause), or
+    /// [`NetcodeError::GameOver`] if either player
+    /// [finished](NetcodeInterface::finish_game) the game.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the user asked to [suspend](NetcodeInterface::suspend) and
+    /// has yet to hear back.
+    pub async fn send_turn_checked(&mut self, turn: &[u8; SIZE]) -> Result<(), NetcodeError> {
+        self.check_send()?;
+        let permit = self
+            .send_to_iroh
+            .clone()
+            .reserve_owned()
+            .await
+            .map_err(|_| NetcodeError::SendFailed)?;
+        permit.send(self.build_and_record_move(std::slice::from_ref(turn)));
+        Ok(())
+    }
+
+    /// Send a turn to the other player like
+    /// [`send_turn_checked`](NetcodeInterface::send_turn_checked), then wait
+    /// until they acknowledge receiving it.
+    ///
+    /// Returns [`NetcodeError::AckTimeout`] if they do not within `timeout`.
+    /// The turn was still sent, so it is the other player's turn, and the
+    /// connection keeps retransmitting it. The game can keep waiting with
+    /// [`last_turn_acked`](NetcodeInterface::last_turn_acked), or give up on
+    /// the connection. Returns [`NetcodeError::Disconnected`] if the
+    /// connection is lost while waiting.
     ///
     /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
-    pub fn try_recv_turn(&mut self) -> Result<[u8; SIZE], ()> {
-        assert!(!self.is_my_turn);
-        match self.recv_from_iroh.try_recv() {
-            Ok(t) => {
-                self.is_my_turn = true;
-                Ok(t)
+    pub async fn send_turn_with_ack_timeout(
+        &mut self,
+        turn: &[u8; SIZE],
+        timeout: Duration,
+    ) -> Result<(), NetcodeError> {
+        self.send_turn_checked(turn).await?;
+        let sent = self.sent_moves;
+        let mut acked = self.acked.clone();
+        match time::timeout(timeout, acked.wait_for(|&acked| acked >= sent)).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(_)) => Err(NetcodeError::Disconnected(
+                "the iroh protocol stopped".to_string(),
+            )),
+            Err(_) => Err(NetcodeError::AckTimeout),
+        }
+    }
+
+    /// Start a move made of several turns, like a move, then a capture, then
+    /// a promotion.
+    ///
+    /// Add each turn with [`push_action`](NetcodeInterface::push_action),
+    /// then send them together with
+    /// [`commit_turn`](NetcodeInterface::commit_turn), like
+    /// [`send_turns`](NetcodeInterface::send_turns).
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not the user's turn, or a move was already started.
+    pub fn begin_turn(&mut self) {
+        self.resolve_first_move();
+        assert!(self.my_turn());
+        assert!(self.bundle.is_none());
+        self.bundle = Some(Vec::new());
+    }
+
+    /// Add a turn to the move started with
+    /// [`begin_turn`](NetcodeInterface::begin_turn). Nothing is sent yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no move was started.
+    pub fn push_action(&mut self, action: &[u8; SIZE]) {
+        self.bundle
+            .as_mut()
+            .expect("begin_turn should be called first")
+            .push(*action);
+    }
+
+    /// Send the move started with [`begin_turn`](NetcodeInterface::begin_turn)
+    /// to the other player.
+    ///
+    /// The other player receives it whole from
+    /// [`try_recv_bundle`](NetcodeInterface::try_recv_bundle), and it counts as
+    /// a single [`ply`](NetcodeInterface::ply).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no move was started, or no turns were added to it.
+    pub fn commit_turn(&mut self) {
+        let bundle = self
+            .bundle
+            .take()
+            .expect("begin_turn should be called first");
+        self.send_turns(&bundle);
+    }
+
+    /// It is now the other player's turn.
+    fn sent_turn(&mut self, passed: bool) {
+        self.is_my_turn = Some(false);
+        self.can_reject = false;
+        self.count_ply(passed);
+        self.update_takeback();
+        self.update_suspend();
+        self.waiting_since = Instant::now();
+        self.sent_at = Some(self.waiting_since);
+        self.publish_my_turn();
+    }
+
+    /// It is now the user's turn.
+    fn received_turn(&mut self, passed: bool) {
+        self.is_my_turn = Some(true);
+        self.start_clock();
+        self.count_ply(passed);
+        self.update_takeback();
+        self.update_suspend();
+        if let Some(sent_at) = self.sent_at.take() {
+            self.last_turn_latency = Some(sent_at.elapsed());
+        }
+        self.publish_my_turn();
+    }
+
+    /// Wait for the other player to acknowledge the move we just sent.
+    fn sent_move(&mut self) {
+        self.sent_moves += 1;
+        self.unacked.push_back((self.sent_moves, self.plies));
+    }
+
+    /// Return whether the other player acknowledged the last move the user
+    /// sent, meaning their game can receive it. Also returns `true` if the
+    /// user has not sent a move.
+    ///
+    /// For showing that a move is still on its way on a bad connection.
+    pub fn last_turn_acked(&self) -> bool {
+        *self.acked.borrow() >= self.sent_moves
+    }
+
+    /// Return whether the last move the user sent was written to the
+    /// connection in full, so it is only up to the network to deliver it.
+    /// Also returns `true` if the user has not sent a move.
+    ///
+    /// A move can take a while to be written when it is large, or the
+    /// connection is congested, like while
+    /// [`bytes_in_flight`](NetcodeInterface::bytes_in_flight) stays above
+    /// zero.
+    pub fn last_turn_written(&self) -> bool {
+        *self.written.borrow() >= self.sent_moves
+    }
+
+    /// Check if the other player acknowledged any of the user's moves.
+    ///
+    /// Returns each event once, in the order the moves were sent.
+    pub fn try_recv_delivery(&mut self) -> Option<DeliveryEvent> {
+        self.drain_controls();
+        self.deliveries.pop_front()
+    }
+
+    /// Add a turn to the history and the replay, if we are keeping them.
+    fn record_turn(&mut self, turn: [u8; SIZE], direction: Direction, ply: u32) {
+        self.history.record(turn, direction, self.game, ply);
+        if let Some(replay) = &mut self.replay {
+            let seed = *self.seed.borrow();
+            if let Err(e) = replay.record(seed, self.game, ply, direction, &turn) {
+                // the game goes on, but the replay is unusable past here.
+                self.replay = None;
+                self.replay_error = Some(e);
             }
-            Err(TryRecvError::Empty) => Err(()),
-            Err(TryRecvError::Disconnected) => unreachable!("unreachable if all goes well"),
         }
     }
 
-    /// Return whether it is the user's turn.
-    pub fn my_turn(&self) -> bool {
-        self.is_my_turn
+    /// The last move or pass was taken back.
+    fn undo_ply(&mut self) {
+        self.can_reject = false;
+        self.unchecked_state_hash = None;
+        self.history.undo(self.game, self.plies);
+        self.plies -= 1;
+        self.ply_watch.send_replace(self.ply());
+        self.consecutive_passes = self.consecutive_passes.saturating_sub(1);
+        // the clocks are not wound back, but whoever moves again starts now.
+        if let Some(clocks) = &mut self.clocks {
+            clocks.start();
+        }
+    }
+
+    fn count_ply(&mut self, passed: bool) {
+        self.plies += 1;
+        self.ply_watch.send_replace(self.ply());
+        if self.recent_plies.len() == self.turn_rate_window {
+            self.recent_plies.pop_front();
+        }
+        self.recent_plies.push_back(Instant::now());
+        self.consecutive_passes = if passed {
+            self.consecutive_passes + 1
+        } else {
+            0
+        };
+    }
+
+    /// Pass instead of sending a turn to the other player.
+    ///
+    /// The other player receives [`TurnPoll::OpponentPassed`]. What passing
+    /// means is up to the game, though two passes in a row can end the game
+    /// in a draw with
+    /// [`auto_end_on_double_pass`](NetcodeInterfaceBuilder::auto_end_on_double_pass).
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the game is [paused](NetcodeInterface::request_pause), or
+    /// the user asked to [suspend](NetcodeInterface::suspend) and has yet to
+    /// hear back.
+    pub fn pass_turn(&mut self) {
+        self.resolve_first_move();
+        assert!(self.my_turn());
+        assert!(!self.pause.paused());
+        assert!(!self.suspend.requested());
+        if let Some(clock) = self.stop_clock() {
+            let millis = u64::try_from(clock.as_millis()).unwrap_or(u64::MAX);
+            self.send_control(ControlFrame::Clock(self.game, millis));
+        }
+        self.send_control(ControlFrame::Pass(self.game));
+        self.sent_turn(true);
+    }
+
+    /// Check if the other player has sent a turn to the user.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    #[must_use = "check whether a turn was received before calling game logic"]
+    pub fn try_recv_turn(&mut self) -> TurnPoll<SIZE> {
+        self.resolve_first_move();
+        assert!(!self.my_turn());
+        self.drain_controls();
+        if self.my_turn() {
+            // the other player accepted a takeback while we were waiting.
+            return TurnPoll::Pending;
+        }
+        if self.received.is_empty() {
+            // turns alternate, so a pass can never be waiting behind a turn.
+            if std::mem::take(&mut self.they_passed) {
+                self.received_turn(true);
+                return TurnPoll::OpponentPassed;
+            }
+            if !self.recv_move() {
+                return match self.turn_recv_timeout {
+                    Some(timeout) if self.waiting_since.elapsed() >= timeout => {
+                        TurnPoll::WaitingTooLong
+                    }
+                    _ => TurnPoll::Pending,
+                };
+            }
+        }
+        TurnPoll::Ready(self.pop_received())
+    }
+
+    /// Check if the other player has sent a turn to the user, like
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn), writing it into
+    /// `turn` rather than returning it, for games with large turns that
+    /// keep a buffer around.
+    ///
+    /// Returns `Ok(())` once a turn was written. Otherwise, `turn` is left
+    /// untouched, and the error is what `try_recv_turn` would have returned,
+    /// which is never [`TurnPoll::Ready`].
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub fn try_recv_turn_into(&mut self, turn: &mut [u8; SIZE]) -> Result<(), TurnPoll<SIZE>> {
+        match self.try_recv_turn() {
+            TurnPoll::Ready(received) => {
+                *turn = received;
+                Ok(())
+            }
+            poll => Err(poll),
+        }
+    }
+
+    /// Wait for the other player's turn, returning as soon as it arrives,
+    /// rather than polling [`try_recv_turn`](NetcodeInterface::try_recv_turn).
+    ///
+    /// Returns `Ok(None)` if it became the user's turn without one, because
+    /// the other player passed, or a takeback was accepted. Returns the
+    /// error that disconnected us if the connection is lost first, rather
+    /// than waiting forever. Flag falls and turn timeouts do not stop the
+    /// wait, so games with clocks should race it against their own timer.
+    ///
+    /// This is cancellation safe, so it can be raced with `select!`, like
+    /// against a shutdown signal. A turn is only taken off the queue, and
+    /// [`my_turn`](NetcodeInterface::my_turn) only flips, in the same poll
+    /// that returns it, so dropping the future never loses a turn or leaves
+    /// it the user's turn without one. A turn that arrives as it is dropped
+    /// stays queued for the next call.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub async fn recv_turn(&mut self) -> Result<Option<[u8; SIZE]>, NetcodeError> {
+        std::future::poll_fn(|cx| self.poll_recv_turn(cx)).await
+    }
+
+    /// Poll for the other player's turn, like
+    /// [`recv_turn`](NetcodeInterface::recv_turn), for games that write
+    /// their own futures, or run them on an executor of their own.
+    ///
+    /// Returns `Poll::Pending` until there is something to return, after
+    /// arranging for `cx`'s waker to be woken once a move or control frame
+    /// arrives, or the connection is lost, so it never needs to be polled in
+    /// a loop. Only the waker of the latest call is woken. The interface
+    /// must still have been created in the context of a Tokio runtime that
+    /// keeps running, like on another thread.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub fn poll_recv_turn(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<[u8; SIZE]>, NetcodeError>> {
+        self.poll_wait_for_turn(cx).map_ok(|poll| match poll {
+            TurnPoll::Ready(turn) => Some(turn),
+            _ => None,
+        })
+    }
+
+    /// Wait for the other player's turn like
+    /// [`recv_turn`](NetcodeInterface::recv_turn), but for at most `timeout`,
+    /// like to redraw a "still waiting" animation every few seconds.
+    ///
+    /// Returns what [`try_recv_turn`](NetcodeInterface::try_recv_turn) would
+    /// have, once it is anything but [`TurnPoll::Pending`]. That is
+    /// [`TurnPoll::WaitingTooLong`] if `timeout` elapses first, or `Pending`
+    /// if it became the user's turn without one, because a takeback was
+    /// accepted. Returns the error that disconnected us if the connection is
+    /// lost first.
+    ///
+    /// This is cancellation safe, like `recv_turn`: a turn that arrives just
+    /// as the timeout elapses stays queued for the next call.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub async fn recv_turn_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<TurnPoll<SIZE>, NetcodeError> {
+        time::timeout(timeout, self.wait_for_turn())
+            .await
+            .unwrap_or(Ok(TurnPoll::WaitingTooLong))
+    }
+
+    /// Return a future that resolves once there may be a turn to receive,
+    /// without receiving it, for a networking task that sleeps until
+    /// something happens, then has the main thread call
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn).
+    ///
+    /// It resolves right away if a turn is already waiting. Otherwise, it
+    /// resolves once a move or control frame arrives, like a pass, or once
+    /// the connection is lost. It may resolve without there being a turn to
+    /// receive, but never misses one, as long as each time it resolves is
+    /// followed by a call to `try_recv_turn`. The future does not borrow the
+    /// interface, so it can be moved to another task.
+    pub fn turn_notified(&self) -> impl Future<Output = ()> + Send + 'static {
+        // a move may be waiting with its notification used up, if an earlier
+        // future resolved for the move before it.
+        let ready = !self.received.is_empty()
+            || self.they_passed
+            || !self.recv_from_iroh.is_empty()
+            || !self.recv_control_from_iroh.is_empty();
+        let notify = self.turn_notify.clone();
+        let mut state = self.connection_state.clone();
+        async move {
+            if ready {
+                return;
+            }
+            tokio::select! {
+                () = notify.notified() => {}
+                _ = state.wait_for(|s| matches!(s, ConnectionState::Disconnected(_))) => {}
+            }
+        }
+    }
+
+    /// Wait until [`try_recv_turn`](NetcodeInterface::try_recv_turn) returns
+    /// something other than [`TurnPoll::Pending`] or
+    /// [`TurnPoll::WaitingTooLong`], or it becomes the user's turn, in which
+    /// case it returns `Pending`.
+    ///
+    /// Anything received is handled before the next await, so dropping this
+    /// never loses it.
+    pub(crate) async fn wait_for_turn(&mut self) -> Result<TurnPoll<SIZE>, NetcodeError> {
+        std::future::poll_fn(|cx| self.poll_wait_for_turn(cx)).await
+    }
+
+    /// Poll for what [`wait_for_turn`](NetcodeInterface::wait_for_turn)
+    /// returns, handling anything received right away.
+    fn poll_wait_for_turn(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<TurnPoll<SIZE>, NetcodeError>> {
+        // a move received below is returned by the same poll, which is what
+        // keeps every future built on this cancellation safe.
+        loop {
+            match self.try_recv_turn() {
+                TurnPoll::Pending | TurnPoll::WaitingTooLong if self.my_turn() => {
+                    return Poll::Ready(Ok(TurnPoll::Pending));
+                }
+                TurnPoll::Pending | TurnPoll::WaitingTooLong => {}
+                poll => return Poll::Ready(Ok(poll)),
+            }
+            // moves come first, so one that arrived just before the
+            // connection was lost is still received.
+            if let Poll::Ready(Some(received)) = self.recv_from_iroh.poll_recv(cx) {
+                self.accept_move(received);
+                continue;
+            }
+            if let Poll::Ready(Some(control)) = self.recv_control_from_iroh.poll_recv(cx) {
+                self.handle_control(control);
+                continue;
+            }
+            let disconnected = self.disconnected.get_or_insert_with(|| {
+                let mut state = self.connection_state.clone();
+                Box::pin(async move {
+                    match state
+                        .wait_for(|s| matches!(s, ConnectionState::Disconnected(_)))
+                        .await
+                        .as_deref()
+                    {
+                        Ok(ConnectionState::Disconnected(e)) => e.clone(),
+                        _ => NetcodeError::Disconnected("the iroh protocol stopped".to_string()),
+                    }
+                })
+            });
+            let e = ready!(disconnected.as_mut().poll(cx));
+            self.disconnected = None;
+            return Poll::Ready(Err(e));
+        }
+    }
+
+    /// Receive every remaining turn of the other player's move at once, or
+    /// `None` if there is no move yet.
+    ///
+    /// For moves sent with [`commit_turn`](NetcodeInterface::commit_turn) or
+    /// [`send_turns`](NetcodeInterface::send_turns), whose turns should be
+    /// applied together. A move is sent in a single frame, so it is never
+    /// received in part, even if the connection drops. Passes are left for
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn) to report.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    #[must_use = "check whether a move was received before calling game logic"]
+    pub fn try_recv_bundle(&mut self) -> Option<Vec<[u8; SIZE]>> {
+        self.resolve_first_move();
+        assert!(!self.my_turn());
+        self.drain_controls();
+        if self.my_turn() || self.they_passed {
+            return None;
+        }
+        if self.received.is_empty() && !self.recv_move() {
+            return None;
+        }
+        let mut bundle = Vec::with_capacity(self.received.len());
+        while !self.received.is_empty() {
+            bundle.push(self.pop_received());
+        }
+        Some(bundle)
+    }
+
+    /// Move the other player's next move into `received`, returning whether
+    /// there was one.
+    fn recv_move(&mut self) -> bool {
+        loop {
+            match self.recv_from_iroh.try_recv() {
+                Ok(received) => {
+                    if self.accept_move(received) {
+                        return true;
+                    }
+                }
+                Err(TryRecvError::Empty) => return false,
+                // the protocol stopped, and the connection state says why.
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Queue the turns of a move the other player sent, returning whether
+    /// they were, rather than being dropped for belonging to a finished game.
+    ///
+    /// Only called once the queue is empty, so the move's turns become the
+    /// queue as they are, rather than being copied into it.
+    fn accept_move(&mut self, (arrived, received): (Instant, Move<SIZE>)) -> bool {
+        // a move from a finished game that was still in flight.
+        if received.game < self.game {
+            return false;
+        }
+        if let (Some(clocks), Some(clock)) = (&mut self.clocks, received.clock) {
+            clocks.set_theirs(clock);
+        }
+        self.received_state_hash = received.state_hash;
+        let mut turns = received.turns;
+        if let Some(transformer) = &self.transformer {
+            for turn in &mut turns {
+                *turn = transformer.transform_in(*turn);
+            }
+        }
+        self.received = turns.into();
+        self.received_at = Some(arrived);
+        true
+    }
+
+    /// Pass a turn the user sent through the
+    /// [`turn_transformer`](NetcodeInterfaceBuilder::turn_transformer), if
+    /// any.
+    fn transform_out(&self, turn: [u8; SIZE]) -> [u8; SIZE] {
+        match &self.transformer {
+            Some(transformer) => transformer.transform_out(turn),
+            None => turn,
+        }
+    }
+
+    /// Hand the next turn in `received` to the game.
+    fn pop_received(&mut self) -> [u8; SIZE] {
+        let turn = self
+            .received
+            .pop_front()
+            .expect("moves should have at least one turn");
+        // the move counts as a ply once its last turn is received.
+        self.record_turn(turn, Direction::Received, self.plies + 1);
+        if self.received.is_empty() {
+            self.received_turn(false);
+            self.can_reject = true;
+            // the game has yet to apply the move, so we compare later.
+            self.unchecked_state_hash = self
+                .received_state_hash
+                .take()
+                .map(|theirs| (self.plies, theirs));
+        }
+        turn
+    }
+
+    /// Return whether [`try_recv_turn`](NetcodeInterface::try_recv_turn) may
+    /// have a turn ready, without receiving it.
+    ///
+    /// For telling other parts of the game that a turn arrived. This can be
+    /// `true` for a move from a finished game that is still in flight, which
+    /// `try_recv_turn` discards. It is always `false` for passes, which are
+    /// only noticed by `try_recv_turn`.
+    pub fn try_peek_turn(&self) -> bool {
+        !self.my_turn() && (!self.received.is_empty() || !self.recv_from_iroh.is_empty())
+    }
+
+    /// Handle everything the other player sent besides turns.
+    fn drain_controls(&mut self) {
+        while let Ok(control) = self.recv_control_from_iroh.try_recv() {
+            self.handle_control(control);
+        }
+        self.update_takeback();
+        self.update_suspend();
+        self.check_state_hash();
+        self.check_deadline();
+        self.check_flag();
+        let acked = *self.acked.borrow();
+        while let Some(&(seq, ply)) = self.unacked.front()
+            && seq <= acked
+        {
+            self.unacked.pop_front();
+            self.deliveries
+                .push_back(DeliveryEvent::Acked(u64::from(ply)));
+        }
+        self.publish_my_turn();
+    }
+
+    /// Compare our state hash with the other player's, if the game received
+    /// their last move since we last compared.
+    fn check_state_hash(&mut self) {
+        let Some((ply, theirs)) = self.unchecked_state_hash.take() else {
+            return;
+        };
+        let Some(state_hash) = &self.state_hash else {
+            return;
+        };
+        if ply != self.plies {
+            // the user already moved, or the move was taken back, so our
+            // state no longer matches theirs at that ply.
+            return;
+        }
+        let ours = state_hash();
+        if ours != theirs {
+            self.divergences.push_back(StateDivergence {
+                ply: u64::from(ply),
+                ours,
+                theirs,
+            });
+        }
+    }
+
+    /// Check if the players' states drifted apart, as reported by their
+    /// [`state_hash`](NetcodeInterfaceBuilder::state_hash).
+    ///
+    /// The other player's last move is compared the first time this, or any
+    /// other method that checks for the other player's messages, is called
+    /// after [`try_recv_turn`](NetcodeInterface::try_recv_turn) returns its
+    /// last turn. So, poll this after applying the move and before sending
+    /// the user's own, or the move goes unchecked.
+    ///
+    /// Returns each divergence once, in order.
+    pub fn try_recv_divergence(&mut self) -> Option<StateDivergence> {
+        self.drain_controls();
+        self.divergences.pop_front()
+    }
+
+    fn update_takeback(&mut self) {
+        if let Some(control) = self.takeback.update(self.plies) {
+            self.send_control(control);
+        }
+    }
+
+    fn handle_control(&mut self, control: ControlFrame) {
+        match control {
+            ControlFrame::NewGame => {
+                self.they_want_new_game = true;
+                self.new_game_unseen = true;
+            }
+            ControlFrame::Pass(game) => {
+                // a pass from a finished game may still have been in flight.
+                if game == self.game {
+                    self.they_passed = true;
+                }
+            }
+            ControlFrame::Message(message) => {
+                self.record_message(self.role.other(), &message);
+                self.messages.push_back(message);
+            }
+            draw @ (ControlFrame::DrawOffer
+            | ControlFrame::DrawAccept
+            | ControlFrame::DrawDecline) => self.draw.handle(draw),
+            takeback @ (ControlFrame::TakebackRequest(_)
+            | ControlFrame::TakebackAccept
+            | ControlFrame::TakebackDecline) => {
+                if self.takeback.handle(takeback) {
+                    // our last move is undone, so it is our turn again.
+                    self.undo_ply();
+                    self.is_my_turn = Some(true);
+                    self.start_clock();
+                }
+            }
+            rematch @ (ControlFrame::RematchOffer(_)
+            | ControlFrame::RematchAccept
+            | ControlFrame::RematchDecline) => {
+                if let Some(swap_first_move) = self.rematch.handle(rematch) {
+                    self.start_rematch(swap_first_move);
+                }
+            }
+            // only sent in simultaneous rounds, which the handshake rules out.
+            ControlFrame::RoundCommit(_) | ControlFrame::RoundReveal(_) => {}
+            // handled by the protocol, which stops.
+            ControlFrame::Disconnect => {}
+            ControlFrame::RejectTurn(game, reason) => {
+                // a rejection from a finished game may still have been in
+                // flight.
+                if game == self.game {
+                    self.rejections.push_back(TurnRejected {
+                        ply: u64::from(self.plies),
+                        reason,
+                    });
+                    self.undo_ply();
+                    self.is_my_turn = Some(true);
+                    self.start_clock();
+                }
+            }
+            pause @ (ControlFrame::PauseRequest
+            | ControlFrame::PauseAccept
+            | ControlFrame::PauseDecline
+            | ControlFrame::Resume
+            | ControlFrame::ResumeAck) => {
+                let was_paused = self.pause.paused();
+                if let Some(reply) = self.pause.handle(pause) {
+                    self.send_control(reply);
+                }
+                self.update_pause(was_paused);
+            }
+            suspend @ (ControlFrame::SuspendRequest(..)
+            | ControlFrame::SuspendAccept
+            | ControlFrame::SuspendDecline) => {
+                self.suspend.handle(suspend);
+                self.take_snapshot();
+            }
+            ControlFrame::Finish(result) => {
+                self.finished = true;
+                self.finishes.push_back(GameFinished(result));
+            }
+            ControlFrame::FinishAck => self.finish_acked = true,
+            ControlFrame::Emote(emote) => {
+                if self.emotes_received.allow(Instant::now()) {
+                    self.emotes.push_back(EmoteReceived(emote));
+                }
+            }
+            ControlFrame::Clock(game, millis) => {
+                if game == self.game
+                    && let Some(clocks) = &mut self.clocks
+                {
+                    clocks.set_theirs(Duration::from_millis(millis));
+                }
+            }
+            ControlFrame::FlagFell(game) => {
+                if game == self.game
+                    && let Some(clocks) = &mut self.clocks
+                {
+                    clocks.their_flag_fell();
+                    self.flag_falls.push_back(FlagFell(self.role.other()));
+                }
+            }
+            ControlFrame::TimedOut(game) => {
+                if game == self.game {
+                    self.timeouts.push_back(TurnTimedOut {
+                        player: self.role.other(),
+                        ply: u64::from(self.plies) + 1,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Send a message to the other player, regardless of whose turn it is.
+    ///
+    /// Messages arrive in the order they were sent, but not necessarily in
+    /// order with respect to turns. Once the connection is lost for good,
+    /// messages are dropped, like every other request to the other player.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the message is longer than [`MAX_MESSAGE_LEN`].
+    pub fn send_message(&mut self, message: &[u8]) {
+        assert!(message.len() <= MAX_MESSAGE_LEN);
+        self.record_message(self.role, message);
+        self.send_control(ControlFrame::Message(message.to_vec()));
+    }
+
+    /// Add a message to the [`chat_log`](NetcodeInterface::chat_log), if any.
+    fn record_message(&self, sender: Role, message: &[u8]) {
+        if let Some(chat_log) = &self.chat_log {
+            chat_log
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .record(sender, message, self.ply());
+        }
+    }
+
+    /// Return the log every message is recorded in, if one was given to
+    /// [`chat_log`](NetcodeInterfaceBuilder::chat_log).
+    pub fn chat_log(&self) -> Option<Arc<Mutex<ChatLog>>> {
+        self.chat_log.clone()
+    }
+
+    /// Check if the other player has sent a message to the user.
+    ///
+    /// Can be called regardless of whose turn it is. Returns messages in the
+    /// order they were sent.
+    pub fn try_recv_message(&mut self) -> Option<Vec<u8>> {
+        self.drain_controls();
+        self.messages.pop_front()
+    }
+
+    /// Send the other player an emote, like "Good game". What the byte means
+    /// is up to the game.
+    ///
+    /// Can be called regardless of whose turn it is. Emotes are limited to
+    /// one a second, with bursts of up to three. Returns whether the emote
+    /// was sent, or dropped for going over the limit. Emotes from the other
+    /// player are limited the same way as they arrive.
+    pub fn send_emote(&mut self, emote: u8) -> bool {
+        if !self.emotes_sent.allow(Instant::now()) {
+            return false;
+        }
+        self.send_control(ControlFrame::Emote(emote));
+        true
+    }
+
+    /// Check if the other player sent an emote.
+    ///
+    /// Returns each emote once, in order.
+    pub fn try_recv_emote(&mut self) -> Option<EmoteReceived> {
+        self.drain_controls();
+        self.emotes.pop_front()
+    }
+
+    /// Tell the other player the user is doing something, like selecting a
+    /// piece or typing in chat, so their game can show that the user is
+    /// thinking.
+    ///
+    /// Can be called as often as the game likes, regardless of whose turn it
+    /// is, though pings less than a quarter of a second apart are dropped.
+    /// Pings are sent as datagrams, separately from turns, so they never
+    /// hold up a turn, but may be lost.
+    pub fn send_activity_ping(&mut self) {
+        if self
+            .last_activity_ping
+            .is_some_and(|at| at.elapsed() < ACTIVITY_PING_INTERVAL)
+        {
+            return;
+        }
+        self.last_activity_ping = Some(Instant::now());
+        // pings are best-effort, so a stopped protocol is no reason to panic.
+        let _ = self.send_activity_to_iroh.send(());
+    }
+
+    /// Check if the other player was active since this was last called.
+    ///
+    /// Only the latest ping is returned, as the game only needs to know
+    /// whether the other player is still active.
+    pub fn try_recv_activity(&mut self) -> Option<OpponentActive> {
+        let mut latest = None;
+        while let Ok(at) = self.recv_activity_from_iroh.try_recv() {
+            latest = Some(OpponentActive { at });
+        }
+        latest
+    }
+
+    /// Offer the other player a draw.
+    ///
+    /// Can be called regardless of whose turn it is. The offer stays
+    /// outstanding, even as turns are taken, until the other player answers.
+    /// Offering again before then does nothing, and offering while the other
+    /// player's offer is outstanding accepts it. If both players offer at the
+    /// same time, both offers are accepted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a draw was already agreed in this game.
+    pub fn offer_draw(&mut self) {
+        self.drain_controls();
+        if let Some(control) = self.draw.offer() {
+            self.send_control(control);
+        }
+    }
+
+    /// Accept or decline the other player's outstanding draw offer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the other player has no outstanding offer.
+    pub fn respond_draw(&mut self, accept: bool) {
+        self.drain_controls();
+        let control = self.draw.respond(accept);
+        self.send_control(control);
+    }
+
+    /// Check if anything happened to a draw offer.
+    ///
+    /// Returns each event once, in order. Once a draw is agreed, the game is
+    /// over, and it is nobody's turn until a
+    /// [`new_game`](NetcodeInterface::new_game) starts.
+    pub fn try_recv_draw(&mut self) -> Option<DrawEvent> {
+        self.drain_controls();
+        self.draw.next_event()
+    }
+
+    /// Ask the other player to pause the game.
+    ///
+    /// They answer with [`respond_pause`](NetcodeInterface::respond_pause),
+    /// and the game hears back from
+    /// [`try_recv_pause`](NetcodeInterface::try_recv_pause). Asking while
+    /// the other player's request is outstanding accepts it. Asking again,
+    /// or while paused, does nothing.
+    ///
+    /// While paused, clocks and turn deadlines stop, and no turns can be
+    /// sent. The connection is unaffected, so it stays open however long the
+    /// pause lasts.
+    pub fn request_pause(&mut self) {
+        self.drain_controls();
+        let was_paused = self.pause.paused();
+        if let Some(control) = self.pause.request() {
+            self.send_control(control);
+        }
+        self.update_pause(was_paused);
+    }
+
+    /// Accept or decline the other player's outstanding pause request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the game has not received [`PauseEvent::Requested`], or
+    /// already answered it.
+    pub fn respond_pause(&mut self, accept: bool) {
+        self.drain_controls();
+        let was_paused = self.pause.paused();
+        let control = self.pause.respond(accept);
+        self.send_control(control);
+        self.update_pause(was_paused);
+    }
+
+    /// Resume the paused game.
+    ///
+    /// Either player can resume. The game stays paused until the other
+    /// player acknowledges it, so both players' clocks restart at about the
+    /// same time. Both players then receive [`PauseEvent::Resumed`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the game is not paused.
+    pub fn resume(&mut self) {
+        self.drain_controls();
+        if let Some(control) = self.pause.resume() {
+            self.send_control(control);
+        }
+    }
+
+    /// Return whether the game is paused.
+    pub fn is_paused(&mut self) -> bool {
+        self.drain_controls();
+        self.pause.paused()
+    }
+
+    /// Check if anything happened to a pause.
+    ///
+    /// Returns each event once, in order.
+    pub fn try_recv_pause(&mut self) -> Option<PauseEvent> {
+        self.drain_controls();
+        self.pause.next_event()
+    }
+
+    /// Stop or restart the clocks and the turn deadline, if the game just
+    /// paused or resumed.
+    fn update_pause(&mut self, was_paused: bool) {
+        let connected_at = *self.connected_at.borrow();
+        match (was_paused, self.pause.paused()) {
+            (false, true) => {
+                self.paused_at = Some(Instant::now());
+                self.turn_started = self.turn_started.or(connected_at);
+                if let Some(clocks) = &mut self.clocks {
+                    clocks.pause(connected_at);
+                }
+            }
+            (true, false) => {
+                if let Some(paused_at) = self.paused_at.take() {
+                    self.turn_started = self
+                        .turn_started
+                        .map(|started| started + paused_at.elapsed());
+                }
+                if let Some(clocks) = &mut self.clocks {
+                    clocks.resume();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Ask the other player to suspend the session, to pick it up later over
+    /// a new connection, like for correspondence games.
+    ///
+    /// They answer with [`respond_suspend`](NetcodeInterface::respond_suspend),
+    /// and the game hears back from
+    /// [`try_recv_suspend`](NetcodeInterface::try_recv_suspend). The request
+    /// is about the current ply, so it is declined if the other player moves
+    /// first, and the user cannot move until they hear back. Asking while
+    /// the other player's request for the same ply is outstanding accepts
+    /// it. Asking again does nothing.
+    ///
+    /// Once both players agree, it is nobody's turn. The game should save
+    /// the [`snapshot`](NetcodeInterface::snapshot), then drop the interface,
+    /// and later [`restore`](Config::restore) the session from it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the connection is not established, or the game was opened
+    /// from a [`Session`].
+    pub fn suspend(&mut self) {
+        self.drain_controls();
+        assert!(self.node_keys.borrow().is_some());
+        if let Some(control) = self.suspend.request((self.game, self.plies)) {
+            self.send_control(control);
+        }
+        self.take_snapshot();
+    }
+
+    /// Accept or decline the other player's outstanding request to suspend.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the game has not received [`SuspendEvent::Requested`], or
+    /// already answered it, or the user moved since.
+    pub fn respond_suspend(&mut self, accept: bool) {
+        self.drain_controls();
+        let control = self.suspend.respond((self.game, self.plies), accept);
+        self.send_control(control);
+        self.take_snapshot();
+    }
+
+    /// Check if anything happened to a request to suspend.
+    ///
+    /// Returns each event once, in order.
+    pub fn try_recv_suspend(&mut self) -> Option<SuspendEvent> {
+        self.drain_controls();
+        self.suspend.next_event()
+    }
+
+    /// Return the snapshot to [`restore`](Config::restore) the session from,
+    /// or `None` until both players agree to
+    /// [`suspend`](NetcodeInterface::suspend).
+    pub fn snapshot(&self) -> Option<SessionSnapshot> {
+        self.snapshot.clone()
+    }
+
+    fn update_suspend(&mut self) {
+        if let Some(control) = self.suspend.update((self.game, self.plies)) {
+            self.send_control(control);
+        }
+    }
+
+    /// Take the snapshot, if the players just agreed to suspend.
+    fn take_snapshot(&mut self) {
+        if !self.suspend.suspended() || self.snapshot.is_some() {
+            return;
+        }
+        let (secret_key, peer) = self
+            .node_keys
+            .borrow()
+            .expect("we can only suspend once connected");
+        let moves_first = self
+            .moves_first()
+            .expect("the coin is flipped once connected");
+        self.snapshot = Some(SessionSnapshot {
+            role: self.role,
+            game: self.game,
+            plies: self.plies,
+            my_turn: self.is_my_turn.unwrap_or(moves_first),
+            first_move: match moves_first {
+                true => self.role,
+                false => self.role.other(),
+            },
+            seed: self
+                .shared_seed()
+                .expect("the seed is agreed on once connected"),
+            session_id: self
+                .session_id()
+                .expect("the ID is agreed on once connected"),
+            metadata: self.metadata.clone(),
+            peer_metadata: self.peer_metadata().unwrap_or_default(),
+            node_id: *SecretKey::from_bytes(&secret_key).public().as_bytes(),
+            secret_key: Some(secret_key),
+            peer,
+        });
+    }
+
+    /// Ask the other player to take back the move the user just made.
+    ///
+    /// Can only be called right after the user moved or passed, while it is
+    /// the other player's turn. Asking again before the other player answers
+    /// does nothing. If the other player replies to the move before they see
+    /// the request, it is declined.
+    ///
+    /// If the other player accepts, it is the user's turn again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the user has not moved yet, or it is their turn.
+    pub fn request_takeback(&mut self) {
+        self.drain_controls();
+        assert!(self.plies > 0 && !self.my_turn());
+        if let Some(control) = self.takeback.request(self.plies) {
+            self.send_control(control);
+        }
+    }
+
+    /// Accept or decline the other player's takeback request.
+    ///
+    /// Must be called before the user takes their turn; taking it declines
+    /// the request. If accepted, it is the other player's turn again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the game has not received [`TakebackEvent::Requested`], or
+    /// the user already moved since.
+    pub fn respond_takeback(&mut self, accept: bool) {
+        self.drain_controls();
+        let control = self.takeback.respond(self.plies, accept);
+        if accept {
+            self.undo_ply();
+            self.is_my_turn = Some(false);
+            self.waiting_since = Instant::now();
+        }
+        self.send_control(control);
+    }
+
+    /// Check if anything happened to a takeback request.
+    ///
+    /// Returns each event once, in order. On [`TakebackEvent::Accepted`],
+    /// both players should undo the last move.
+    pub fn try_recv_takeback(&mut self) -> Option<TakebackEvent> {
+        self.drain_controls();
+        self.takeback.next_event()
+    }
+
+    /// Reject the move the user just received, instead of moving, because
+    /// it is invalid under the game's rules.
+    ///
+    /// The move is undone, and it is the other player's turn again, so they
+    /// can send a corrected move. They receive a [`TurnRejected`] with
+    /// `reason` from [`try_recv_rejection`](NetcodeInterface::try_recv_rejection).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the user has not just received the last turn of a move from
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn), or already moved,
+    /// or rejected it, since. Also panics if `reason` is longer than
+    /// [`MAX_MESSAGE_LEN`].
+    pub fn reject_turn(&mut self, reason: &[u8]) {
+        self.drain_controls();
+        assert!(self.can_reject);
+        assert!(reason.len() <= MAX_MESSAGE_LEN);
+        self.send_control(ControlFrame::RejectTurn(self.game, reason.to_vec()));
+        self.undo_ply();
+        self.is_my_turn = Some(false);
+        self.waiting_since = Instant::now();
+        self.publish_my_turn();
+    }
+
+    /// Check if the other player rejected one of the user's moves.
+    ///
+    /// Returns each rejection once, in order. The game should undo the
+    /// rejected move, and send a different one.
+    pub fn try_recv_rejection(&mut self) -> Option<TurnRejected> {
+        self.drain_controls();
+        self.rejections.pop_front()
+    }
+
+    /// Start the clock on the user's turn, which just became available.
+    fn start_clock(&mut self) {
+        // while paused, the turn starts once the game resumes.
+        self.turn_started = Some(self.paused_at.unwrap_or_else(Instant::now));
+        self.deadline_passed = false;
+        if let Some(clocks) = &mut self.clocks {
+            clocks.start();
+        }
+    }
+
+    /// Stop the user's chess clock as they move, returning the time they
+    /// have left to send along with it, if there are clocks.
+    fn stop_clock(&mut self) -> Option<Duration> {
+        // a flag that fell since the game last checked still counts.
+        self.check_flag();
+        let connected_at = *self.connected_at.borrow();
+        Some(self.clocks.as_mut()?.stop_mine(connected_at))
+    }
+
+    /// Tell both players if the user's flag fell.
+    fn check_flag(&mut self) {
+        let my_turn = self.my_turn();
+        let connected_at = *self.connected_at.borrow();
+        let Some(clocks) = &mut self.clocks else {
+            return;
+        };
+        if clocks.check_my_flag(my_turn, connected_at) {
+            self.flag_falls.push_back(FlagFell(self.role));
+            // if the other player is gone, only the user hears of it.
+            self.send_control(ControlFrame::FlagFell(self.game));
+        }
+    }
+
+    /// Return how much time the user has left, then the other player.
+    ///
+    /// The clock of the player to move keeps running, so this can be called
+    /// every frame to render both clocks. The other player's time is only as
+    /// up to date as their last move.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no
+    /// [`time_control`](NetcodeInterfaceBuilder::time_control) was set.
+    pub fn clocks(&self) -> (Duration, Duration) {
+        self.clocks
+            .as_ref()
+            .expect("clocks require a time control")
+            .clocks(self.my_turn(), *self.connected_at.borrow())
+    }
+
+    /// Check if either player's flag fell, meaning they ran out of time.
+    ///
+    /// Returns each flag fall once. A player's flag is decided by their own
+    /// clock, so both players always agree on who ran out of time, though the
+    /// other player's flag fall arrives a little later. What happens next is
+    /// up to the game, which usually ends it.
+    pub fn try_recv_flag_fall(&mut self) -> Option<FlagFell> {
+        self.drain_controls();
+        self.flag_falls.pop_front()
+    }
+
+    /// Tell both players if the user's
+    /// [`turn_deadline`](NetcodeInterfaceBuilder::turn_deadline) passed.
+    fn check_deadline(&mut self) {
+        let Some(deadline) = self.turn_deadline else {
+            return;
+        };
+        if self.deadline_passed || self.paused_at.is_some() || !self.my_turn() {
+            return;
+        }
+        // the first turn of the first game is available once we connect.
+        let Some(started) = self.turn_started.or(*self.connected_at.borrow()) else {
+            return;
+        };
+        if started.elapsed() >= deadline {
+            self.deadline_passed = true;
+            self.timeouts.push_back(TurnTimedOut {
+                player: self.role,
+                ply: u64::from(self.plies) + 1,
+            });
+            // if the other player is gone, only the user hears of it.
+            self.send_control(ControlFrame::TimedOut(self.game));
+        }
+    }
+
+    /// Check if either player's
+    /// [`turn_deadline`](NetcodeInterfaceBuilder::turn_deadline) passed.
+    ///
+    /// Returns each timeout once, in order. Both players see the same
+    /// timeouts, though the other player's arrive a little later. The turn
+    /// is still the timed out player's; what happens next is up to the game.
+    pub fn try_recv_timeout(&mut self) -> Option<TurnTimedOut> {
+        self.drain_controls();
+        self.timeouts.pop_front()
+    }
+
+    fn send_control(&self, control: ControlFrame) {
+        // the protocol only stops once the connection is gone for good, which
+        // the connection state already reports, so there is no one to tell.
+        let _ = self.send_control_to_iroh.send(control);
+        // answering the other player, like accepting a draw, may end the
+        // user's turn.
+        self.publish_my_turn();
+    }
+
+    /// Start a new game over the same connection, once the other player agrees.
+    ///
+    /// Tells the other player we want a new game, then waits for them to call
+    /// `new_game` too. Whoever moved second in the last game moves first in
+    /// the new one.
+    ///
+    /// This should only be called once the current game is over, when
+    /// no turns are in flight. It is safe to drop the returned future and
+    /// call `new_game` again later.
+    pub async fn new_game(&mut self) -> Result<(), NetcodeError> {
+        if !self.we_want_new_game {
+            self.send_control_to_iroh
+                .send(ControlFrame::NewGame)
+                .map_err(|_| NetcodeError::Disconnected("the iroh protocol stopped".to_string()))?;
+            self.we_want_new_game = true;
+        }
+
+        self.drain_controls();
+        while !self.they_want_new_game {
+            match self.recv_control_from_iroh.recv().await {
+                Some(control) => self.handle_control(control),
+                None => {
+                    return Err(NetcodeError::Disconnected(
+                        "the iroh protocol stopped".to_string(),
+                    ));
+                }
+            }
+        }
+
+        self.we_want_new_game = false;
+        self.they_want_new_game = false;
+        self.new_game_unseen = false;
+        self.start_next_game(true);
+        Ok(())
+    }
+
+    /// End the game for good with `result`, like checkmate, then close the
+    /// connection.
+    ///
+    /// The other player receives [`GameFinished`] with `result` from
+    /// [`try_recv_finish`](NetcodeInterface::try_recv_finish). It always
+    /// arrives before the connection closes, so a game that sees
+    /// [`NetcodeError::PeerClosed`] should check for it first. This waits
+    /// until the other player acknowledges the result, which does not
+    /// depend on their game, then closes the connection.
+    ///
+    /// Both players can finish at the same time, even with different
+    /// results, and each receives the other's. Once either player finishes,
+    /// it is nobody's turn, and
+    /// [`send_turn_checked`](NetcodeInterface::send_turn_checked) returns
+    /// [`NetcodeError::GameOver`].
+    ///
+    /// Returns [`NetcodeError::Disconnected`] if the connection is lost
+    /// before the result is acknowledged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `result` is longer than [`MAX_MESSAGE_LEN`].
+    pub async fn finish_game(&mut self, result: &[u8]) -> Result<(), NetcodeError> {
+        assert!(result.len() <= MAX_MESSAGE_LEN);
+        let stopped = || NetcodeError::Disconnected("the iroh protocol stopped".to_string());
+        self.finished = true;
+        self.send_control_to_iroh
+            .send(ControlFrame::Finish(result.to_vec()))
+            .map_err(|_| stopped())?;
+
+        self.drain_controls();
+        while !self.finish_acked {
+            match self.recv_control_from_iroh.recv().await {
+                Some(control) => self.handle_control(control),
+                None => return Err(stopped()),
+            }
+        }
+
+        // the goodbye is written after everything else we sent, like acks
+        // of the other player's result.
+        self.send_control_to_iroh
+            .send(ControlFrame::Disconnect)
+            .map_err(|_| stopped())
+    }
+
+    /// Check if the other player
+    /// [finished](NetcodeInterface::finish_game) the game.
+    ///
+    /// Returns their result once.
+    pub fn try_recv_finish(&mut self) -> Option<GameFinished> {
+        self.drain_controls();
+        self.finishes.pop_front()
+    }
+
+    /// Offer the other player a rematch over the same connection.
+    ///
+    /// With `swap_first_move`, whoever moved second moves first in the
+    /// rematch, like for players who alternate the first-move advantage.
+    /// Otherwise, the same player moves first again. Roles themselves never
+    /// change, since they only say who hosts the connection.
+    ///
+    /// Can be called at any time, though usually once the current game is
+    /// over. Offering again before the other player answers does nothing, and
+    /// offering while the other player's offer is outstanding accepts it. If
+    /// both players offer at the same time, both offers are accepted, and the
+    /// rematch swaps who moves first if either offer asked to.
+    ///
+    /// Once a rematch starts, both players receive
+    /// [`RematchEvent::Started`], and turns still in flight from the previous
+    /// game are discarded.
+    pub fn offer_rematch(&mut self, swap_first_move: bool) {
+        self.drain_controls();
+        let (control, started) = self.rematch.offer(swap_first_move);
+        if let Some(control) = control {
+            self.send_control(control);
+        }
+        if let Some(swap_first_move) = started {
+            self.start_rematch(swap_first_move);
+        }
+    }
+
+    /// Accept or decline the other player's outstanding rematch offer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the other player has no outstanding offer.
+    pub fn respond_rematch(&mut self, accept: bool) {
+        self.drain_controls();
+        let (control, started) = self.rematch.respond(accept);
+        self.send_control(control);
+        if let Some(swap_first_move) = started {
+            self.start_rematch(swap_first_move);
+        }
+    }
+
+    /// Check if anything happened to a rematch offer.
+    ///
+    /// Returns each event once, in order.
+    pub fn try_recv_rematch(&mut self) -> Option<RematchEvent> {
+        self.drain_controls();
+        self.rematch.next_event()
+    }
+
+    fn start_rematch(&mut self, swap_first_move: bool) {
+        self.start_next_game(swap_first_move);
+        let you_move_first = self.my_turn();
+        self.rematch.started(you_move_first);
+    }
+
+    /// Reset everything about the current game, and start the next one.
+    fn start_next_game(&mut self, swap_first_move: bool) {
+        self.game += 1;
+        self.received.clear();
+        self.bundle = None;
+        self.received_state_hash = None;
+        self.unchecked_state_hash = None;
+        self.they_passed = false;
+        self.draw = Draw::default();
+        self.plies = 0;
+        self.ply_watch.send_replace(0);
+        self.consecutive_passes = 0;
+        self.takeback = Takeback::default();
+        self.can_reject = false;
+        self.sent_at = None;
+        if let Some(clocks) = &mut self.clocks {
+            clocks.reset();
+        }
+        self.first_move_swapped ^= swap_first_move;
+        self.is_my_turn = self.moves_first();
+        self.waiting_since = Instant::now();
+        self.start_clock();
+        self.publish_my_turn();
+    }
+
+    /// Check if the other player has asked for a new game.
+    ///
+    /// Returns `true` once per request, so the game can ask the user whether
+    /// they accept. To accept, call [`new_game`](NetcodeInterface::new_game).
+    pub fn try_recv_new_game(&mut self) -> bool {
+        self.drain_controls();
+        std::mem::take(&mut self.new_game_unseen)
+    }
+
+    /// Return whether it is the user's turn.
+    ///
+    /// If the first move is [random](NetcodeInterfaceBuilder::random_first_move),
+    /// it is nobody's turn until the coin is flipped. It is also nobody's turn
+    /// once the game has an [`outcome`](NetcodeInterface::outcome), the
+    /// session is [suspended](NetcodeInterface::suspend), or either player
+    /// [finished](NetcodeInterface::finish_game) the game.
+    pub fn my_turn(&self) -> bool {
+        self.outcome().is_none()
+            && !self.suspend.suspended()
+            && !self.finished
+            && self
+                .is_my_turn
+                .or_else(|| self.moves_first())
+                .unwrap_or(false)
+    }
+
+    /// Return a receiver that mirrors [`my_turn`](NetcodeInterface::my_turn),
+    /// for other tasks, like a UI that only enables input on the user's turn,
+    /// to [`borrow`](watch::Receiver::borrow) or await
+    /// [`changed`](watch::Receiver::changed) without the interface.
+    ///
+    /// It is updated by whichever call on the interface flips the turn, like
+    /// [`send_turn`](NetcodeInterface::send_turn), receiving a turn, or
+    /// accepting a takeback. So it only changes while the interface is being
+    /// used, and only when the turn actually flips.
+    pub fn my_turn_watch(&self) -> watch::Receiver<bool> {
+        self.my_turn_watch.subscribe()
+    }
+
+    /// Overwrite whether it is the user's turn.
+    ///
+    /// This is an escape hatch for games where turns do not always alternate,
+    /// like forced extra moves or penalty turns, and not a replacement for
+    /// [`send_turn`](NetcodeInterface::send_turn) and
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn). The game must have
+    /// already decided, by its own rules, that the turn order changes, and
+    /// both players must agree: if the user takes an extra turn, the other
+    /// player must call this with `false` after receiving it, and the user
+    /// with `true` after sending it.
+    ///
+    /// Any turns or passes still in flight are unaffected.
+    pub fn reset_is_my_turn(&mut self, is_my_turn: bool) {
+        self.is_my_turn = Some(is_my_turn);
+        self.waiting_since = Instant::now();
+        self.start_clock();
+        self.publish_my_turn();
+    }
+
+    /// Return how the current game ended, or `None` if it is still going.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        let double_passed = self.auto_end_on_double_pass && self.consecutive_passes >= 2;
+        if self.draw.agreed() || double_passed {
+            Some(GameOutcome::Draw)
+        } else {
+            None
+        }
+    }
+
+    /// Return which player's turn it is.
+    ///
+    /// This is [`my_turn`](NetcodeInterface::my_turn) in absolute terms, so
+    /// it is also the other player's turn until the coin is flipped.
+    pub fn current_turn_player(&self) -> Role {
+        if self.my_turn() {
+            self.role
+        } else {
+            self.role.other()
+        }
+    }
+}
+
+impl<const SIZE: usize> Drop for NetcodeInterface<SIZE> {
+    /// Tell the other player the game was closed on purpose, so they see
+    /// [`NetcodeError::PeerClosed`] rather than a lost connection.
+    ///
+    /// This never blocks. The goodbye is only sent if the Tokio runtime
+    /// outlives the interface for long enough.
+    fn drop(&mut self) {
+        // the protocol may already have stopped, which is fine.
+        let _ = self.send_control_to_iroh.send(ControlFrame::Disconnect);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Start a host and a client that connect over loopback, configured by
+    /// `host` and `client`, without waiting for the handshake.
+    pub(crate) async fn start_with<const SIZE: usize>(
+        host: impl FnOnce(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE>,
+        client: impl FnOnce(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE>,
+    ) -> (NetcodeInterface<SIZE>, NetcodeInterface<SIZE>) {
+        let (ticket_sender, ticket) = oneshot::channel();
+        let host = host(NetcodeInterface::builder(Config::TicketSender(
+            ticket_sender,
+        )))
+        .build();
+        let ticket = ticket.await.unwrap();
+        let client = client(NetcodeInterface::builder(Config::Ticket(ticket))).build();
+        (host, client)
+    }
+
+    /// Connect a host and a client over loopback, configured by `host` and
+    /// `client`.
+    pub(crate) async fn connect_with<const SIZE: usize>(
+        host: impl FnOnce(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE>,
+        client: impl FnOnce(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE>,
+    ) -> (NetcodeInterface<SIZE>, NetcodeInterface<SIZE>) {
+        let (mut host, mut client) = start_with(host, client).await;
+        let (host_connected, client_connected) =
+            tokio::join!(host.wait_for_connection(), client.wait_for_connection());
+        host_connected.unwrap();
+        client_connected.unwrap();
+        (host, client)
+    }
+
+    /// Connect a host and a client over loopback with the default options.
+    pub(crate) async fn connect<const SIZE: usize>()
+    -> (NetcodeInterface<SIZE>, NetcodeInterface<SIZE>) {
+        connect_with(|host| host, |client| client).await
+    }
+
+    /// Start a host and a client configured by `host` and `client`, which
+    /// disagree, returning the errors each handshake failed with.
+    pub(crate) async fn refused<const SIZE: usize>(
+        host: impl FnOnce(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE>,
+        client: impl FnOnce(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE>,
+    ) -> (NetcodeError, NetcodeError) {
+        let (mut host, mut client) = start_with(host, client).await;
+        let (host_connected, client_connected) =
+            tokio::join!(host.wait_for_connection(), client.wait_for_connection());
+        (host_connected.unwrap_err(), client_connected.unwrap_err())
+    }
+
+    /// Call `poll` every few milliseconds, like a game loop, until it returns
+    /// something.
+    ///
+    /// # Panics
+    ///
+    /// Panics if that takes more than ten seconds.
+    pub(crate) async fn eventually<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Some(value) = poll() {
+                    return value;
+                }
+                time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("nothing happened for ten seconds")
+    }
+
+    /// Wait until the protocol gives up on the connection, returning why.
+    pub(crate) async fn stopped<const SIZE: usize>(
+        netcode: &NetcodeInterface<SIZE>,
+    ) -> NetcodeError {
+        eventually(|| netcode.send_control_to_iroh.is_closed().then_some(())).await;
+        match netcode.connection_state() {
+            ConnectionState::Disconnected(e) => e,
+            state => panic!("the protocol stopped while {state:?}"),
+        }
+    }
+
+    /// Poll for the other player's turn until it arrives.
+    pub(crate) async fn recv<const SIZE: usize>(
+        netcode: &mut NetcodeInterface<SIZE>,
+    ) -> [u8; SIZE] {
+        eventually(|| match netcode.try_recv_turn() {
+            TurnPoll::Ready(turn) => Some(turn),
+            _ => None,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn turns_ping_pong() {
+        let (mut host, mut client) = connect::<4>().await;
+        client.send_turn(b"ping");
+        assert_eq!(&recv(&mut host).await, b"ping");
+        host.send_turn(b"pong");
+        assert_eq!(&recv(&mut client).await, b"pong");
+    }
+
+    #[tokio::test]
+    async fn turns_alternate() {
+        let (mut host, mut client) = connect::<1>().await;
+        // the client moves first by default.
+        assert!(client.my_turn() && !host.my_turn());
+        assert_eq!(client.current_turn_player(), Role::Client);
+        assert_eq!(host.current_turn_player(), Role::Client);
+
+        client.send_turn(&[1]);
+        assert!(!client.my_turn() && !host.my_turn());
+        recv(&mut host).await;
+        assert!(host.my_turn() && !client.my_turn());
+        assert_eq!(host.current_turn_player(), Role::Host);
+        // nothing arrives until the host moves.
+        time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(client.try_recv_turn(), TurnPoll::Pending);
+        assert_eq!((host.ply(), client.ply()), (1, 1));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "self.my_turn()")]
+    async fn sending_out_of_turn_panics() {
+        let (mut host, _client) = connect::<1>().await;
+        host.send_turn(&[1]);
+    }
+
+    #[tokio::test]
+    async fn split_sender_refuses_to_send_out_of_turn() {
+        let (host, _client) = connect::<1>().await;
+        let (mut sender, _receiver) = host.split();
+        assert_eq!(sender.send_turn(&[1]), Err(NetcodeError::NotYourTurn));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "!self.my_turn()")]
+    async fn receiving_on_the_users_turn_panics() {
+        let (_host, mut client) = connect::<1>().await;
+        let _ = client.try_recv_turn();
+    }
+
+    #[tokio::test]
+    async fn recv_turn_resolves_once_the_other_player_sends() {
+        let (mut host, mut client) = connect::<1>().await;
+        let (received, ()) = tokio::join!(host.recv_turn(), async {
+            time::sleep(Duration::from_millis(100)).await;
+            client.send_turn(&[7]);
+        });
+        assert_eq!(received, Ok(Some([7])));
+        assert!(host.my_turn());
+    }
+
+    #[tokio::test]
+    async fn ten_turn_game() {
+        let (mut host, mut client) = connect::<1>().await;
+        for turn in 0..10 {
+            let (mover, waiter) = match turn % 2 {
+                0 => (&mut client, &mut host),
+                _ => (&mut host, &mut client),
+            };
+            assert!(mover.my_turn() && !waiter.my_turn());
+            mover.send_turn(&[turn]);
+            assert_eq!(recv(waiter).await, [turn]);
+        }
+        assert_eq!((host.ply(), client.ply()), (10, 10));
+        assert!(client.my_turn() && !host.my_turn());
+    }
+
+    #[tokio::test]
+    async fn chat_flows_both_ways_during_a_turn() {
+        let (mut host, mut client) = connect::<1>().await;
+        // the host's turn is on its way, but not received yet.
+        client.send_turn(&[1]);
+        host.send_message(b"hi");
+        client.send_message(b"hello");
+        client.send_message(b"how are you");
+        assert_eq!(eventually(|| client.try_recv_message()).await, b"hi");
+        assert_eq!(eventually(|| host.try_recv_message()).await, b"hello");
+        assert_eq!(eventually(|| host.try_recv_message()).await, b"how are you");
+        assert_eq!(recv(&mut host).await, [1]);
+        assert_eq!(client.try_recv_message(), None);
+    }
+
+    #[tokio::test]
+    async fn requests_after_the_other_player_leaves_are_dropped() {
+        let (host, mut client) = connect::<1>().await;
+        drop(host);
+        assert_eq!(stopped(&client).await, NetcodeError::PeerClosed);
+        client.send_message(b"gg");
+        client.offer_draw();
+        client.pass_turn();
+        assert_eq!(client.try_recv_message(), None);
+        assert_eq!(client.ply(), 1);
+    }
+
+    fn quick_deadline(builder: NetcodeInterfaceBuilder<1>) -> NetcodeInterfaceBuilder<1> {
+        builder.turn_deadline(Duration::from_millis(200))
+    }
+
+    #[tokio::test]
+    async fn turn_deadlines_time_out_on_both_machines() {
+        let (mut host, mut client) = connect_with(quick_deadline, quick_deadline).await;
+        let timed_out = TurnTimedOut {
+            player: Role::Client,
+            ply: 1,
+        };
+        assert_eq!(eventually(|| client.try_recv_timeout()).await, timed_out);
+        assert_eq!(eventually(|| host.try_recv_timeout()).await, timed_out);
+        // each timeout is only reported once.
+        time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(client.try_recv_timeout(), None);
+        assert_eq!(host.try_recv_timeout(), None);
+    }
+
+    #[tokio::test]
+    async fn deadlines_passing_after_the_other_player_leaves_are_still_reported() {
+        let (host, mut client) = connect_with(quick_deadline, quick_deadline).await;
+        drop(host);
+        stopped(&client).await;
+        time::sleep(Duration::from_millis(200)).await;
+        // a poll that only reads checks the deadline too.
+        assert_eq!(client.try_recv_message(), None);
+        assert_eq!(
+            client.try_recv_timeout(),
+            Some(TurnTimedOut {
+                player: Role::Client,
+                ply: 1,
+            })
+        );
+    }
+
+    fn quick_clock(builder: NetcodeInterfaceBuilder<1>) -> NetcodeInterfaceBuilder<1> {
+        builder.time_control(TimeControl::Increment {
+            initial: Duration::from_millis(200),
+            increment: Duration::ZERO,
+        })
+    }
+
+    #[tokio::test]
+    async fn flags_fall_on_both_machines() {
+        let (mut host, mut client) = connect_with(quick_clock, quick_clock).await;
+        let flag_fell = FlagFell(Role::Client);
+        assert_eq!(eventually(|| client.try_recv_flag_fall()).await, flag_fell);
+        assert_eq!(eventually(|| host.try_recv_flag_fall()).await, flag_fell);
+        assert_eq!(client.clocks().0, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn flags_falling_after_the_other_player_leaves_are_still_reported() {
+        let (host, mut client) = connect_with(quick_clock, quick_clock).await;
+        drop(host);
+        stopped(&client).await;
+        time::sleep(Duration::from_millis(200)).await;
+        // stopping the clock to pass checks the flag first.
+        client.pass_turn();
+        assert_eq!(client.try_recv_flag_fall(), Some(FlagFell(Role::Client)));
+        assert_eq!(client.try_recv_flag_fall(), None);
+    }
+
+    #[tokio::test]
+    async fn a_turn_arriving_as_the_timeout_elapses_stays_queued() {
+        let (mut host, mut client) = connect::<1>().await;
+        // with time paused, the timeout and the client's move are due at the
+        // same instant, and the timeout is polled first.
+        time::pause();
+        let (waited, ()) = tokio::join!(host.recv_turn_timeout(Duration::from_secs(1)), async {
+            time::sleep(Duration::from_secs(1)).await;
+            client.send_turn(&[1]);
+        });
+        time::resume();
+        assert_eq!(waited, Ok(TurnPoll::WaitingTooLong));
+        assert!(!host.my_turn());
+        assert_eq!(
+            host.recv_turn_timeout(Duration::from_secs(10)).await,
+            Ok(TurnPoll::Ready([1]))
+        );
+        assert!(host.my_turn());
+    }
+
+    #[tokio::test]
+    async fn turn_notified_wakes_a_task_for_each_turn() {
+        let (mut host, mut client) = connect::<1>().await;
+        for turn in 0..5 {
+            client.send_turn(&[turn]);
+            // like a networking task that wakes the game loop up.
+            let received = loop {
+                let notified = tokio::spawn(host.turn_notified());
+                time::timeout(Duration::from_secs(10), notified)
+                    .await
+                    .expect("a turn arrived without waking the task")
+                    .unwrap();
+                // waking up without a turn is allowed, missing one is not.
+                if let TurnPoll::Ready(received) = host.try_recv_turn() {
+                    break received;
+                }
+            };
+            assert_eq!(received, [turn]);
+            host.send_turn(&[turn]);
+            assert_eq!(recv(&mut client).await, [turn]);
+        }
+    }
+
+    #[tokio::test]
+    async fn my_turn_watch_follows_a_game_with_a_takeback() {
+        let (mut host, mut client) = connect::<1>().await;
+        // like a UI task, telling the test each time it sees the turn flip.
+        let mut my_turn = client.my_turn_watch();
+        let (flipped, mut seen) = mpsc::unbounded_channel();
+        let observer = tokio::spawn(async move {
+            flipped.send(*my_turn.borrow_and_update()).unwrap();
+            while my_turn.changed().await.is_ok() {
+                flipped.send(*my_turn.borrow_and_update()).unwrap();
+            }
+        });
+        assert_eq!(seen.recv().await, Some(true));
+
+        client.send_turn(&[1]);
+        assert_eq!(seen.recv().await, Some(false));
+        recv(&mut host).await;
+        host.send_turn(&[2]);
+        recv(&mut client).await;
+        assert_eq!(seen.recv().await, Some(true));
+        client.send_turn(&[3]);
+        assert_eq!(seen.recv().await, Some(false));
+
+        client.request_takeback();
+        recv(&mut host).await;
+        assert_eq!(
+            eventually(|| host.try_recv_takeback()).await,
+            TakebackEvent::Requested
+        );
+        host.respond_takeback(true);
+        assert_eq!(
+            eventually(|| client.try_recv_takeback()).await,
+            TakebackEvent::Accepted
+        );
+        assert_eq!(seen.recv().await, Some(true));
+        assert_eq!(client.my_turn(), *client.my_turn_watch().borrow());
+
+        // the watch closes with the interface.
+        drop(client);
+        observer.await.unwrap();
+        assert_eq!(seen.recv().await, None);
+    }
+
+    /// A waker that only remembers it was woken.
+    #[derive(Default)]
+    struct WokenFlag(std::sync::atomic::AtomicBool);
+
+    impl std::task::Wake for WokenFlag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    impl WokenFlag {
+        fn woken(&self) -> bool {
+            self.0.swap(false, std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn poll_recv_turn_wakes_the_waker_once_a_turn_arrives() {
+        let (mut host, mut client) = connect::<1>().await;
+        let flag = Arc::new(WokenFlag::default());
+        let waker = std::task::Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+        assert!(host.poll_recv_turn(&mut cx).is_pending());
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(!flag.woken());
+
+        client.send_turn(&[1]);
+        // nothing polls the interface until the waker fires.
+        eventually(|| flag.woken().then_some(())).await;
+        assert_eq!(host.poll_recv_turn(&mut cx), Poll::Ready(Ok(Some([1]))));
+        assert!(host.my_turn());
+    }
+
+    #[tokio::test]
+    async fn cancelling_recv_turn_never_loses_or_repeats_a_turn() {
+        let (mut host, mut client) = connect::<1>().await;
+        let (mut sent, mut received) = (Vec::new(), Vec::new());
+        let mut cancelled = 0;
+        for _ in 0..5000 {
+            if client.my_turn() {
+                let turn = sent.len() as u8;
+                client.send_turn(&[turn]);
+                sent.push(turn);
+            } else {
+                // the host's reply, which only hands the turn back.
+                let _ = client.try_recv_turn();
+            }
+            tokio::select! {
+                turn = host.recv_turn() => {
+                    received.push(turn.unwrap().unwrap()[0]);
+                    host.send_turn(&[0]);
+                }
+                () = tokio::task::yield_now() => cancelled += 1,
+            }
+        }
+        if !host.my_turn() && received.len() < sent.len() {
+            received.push(host.recv_turn().await.unwrap().unwrap()[0]);
+        }
+        assert!(cancelled > 0 && sent.len() > 1);
+        assert_eq!(received, sent);
+    }
+
+    #[tokio::test]
+    async fn either_player_can_move_first() {
+        for first in [Role::Host, Role::Client] {
+            let (mut host, mut client) = connect_with::<1>(
+                |host| host.first_move(first),
+                |client| client.first_move(first),
+            )
+            .await;
+            let host_first = first == Role::Host;
+            assert_eq!(host.moves_first(), Some(host_first));
+            assert_eq!(client.moves_first(), Some(!host_first));
+            let (mover, waiter) = match first {
+                Role::Host => (&mut host, &mut client),
+                Role::Client => (&mut client, &mut host),
+            };
+            assert!(mover.my_turn() && !waiter.my_turn());
+            assert_eq!(mover.current_turn_player(), first);
+            mover.send_turn(&[1]);
+            assert_eq!(recv(waiter).await, [1]);
+            assert!(waiter.my_turn());
+        }
+    }
+
+    #[tokio::test]
+    async fn disagreeing_on_the_first_move_fails_the_handshake() {
+        let (host, client) = refused::<1>(
+            |host| host.first_move(Role::Host),
+            |client| client.first_move(Role::Client),
+        )
+        .await;
+        assert!(matches!(host, NetcodeError::HandshakeFailed(_)));
+        assert!(matches!(client, NetcodeError::HandshakeFailed(_)));
+    }
+
+    #[tokio::test]
+    async fn both_players_agree_on_a_fair_coin_flip() {
+        const FLIPS: usize = 16;
+        let games = (0..FLIPS).map(|_| {
+            connect_with::<1>(
+                NetcodeInterfaceBuilder::random_first_move,
+                NetcodeInterfaceBuilder::random_first_move,
+            )
+        });
+        let mut host_first = 0;
+        for (host, client) in futures::future::join_all(games).await {
+            assert!(host.shared_seed().is_some());
+            assert_eq!(host.shared_seed(), client.shared_seed());
+            let moves_first = host.moves_first().unwrap();
+            assert_eq!(client.moves_first(), Some(!moves_first));
+            assert!(host.my_turn() != client.my_turn());
+            host_first += usize::from(moves_first);
+        }
+        // both sides of the coin come up, unless it landed the same way
+        // every time, which happens about once in 30000 runs.
+        assert!(host_first > 0 && host_first < FLIPS);
+    }
+
+    #[tokio::test]
+    async fn passes_interleave_with_turns() {
+        let (mut host, mut client) = connect::<1>().await;
+        // `None` is a pass.
+        let plays = [Some(1), None, None, Some(2), Some(3), None, Some(4)];
+        for (ply, play) in plays.into_iter().enumerate() {
+            let (mover, waiter) = match ply % 2 {
+                0 => (&mut client, &mut host),
+                _ => (&mut host, &mut client),
+            };
+            let expected = match play {
+                Some(turn) => {
+                    mover.send_turn(&[turn]);
+                    TurnPoll::Ready([turn])
+                }
+                None => {
+                    mover.pass_turn();
+                    TurnPoll::OpponentPassed
+                }
+            };
+            let received = eventually(|| match waiter.try_recv_turn() {
+                TurnPoll::Pending | TurnPoll::WaitingTooLong => None,
+                poll => Some(poll),
+            })
+            .await;
+            assert_eq!(received, expected);
+            assert!(waiter.my_turn() && !mover.my_turn());
+            assert_eq!(
+                (mover.ply(), waiter.ply()),
+                (ply as u64 + 1, ply as u64 + 1)
+            );
+        }
+        // the double pass did not end the game, since that is opt-in.
+        assert_eq!(host.outcome(), None);
+    }
+
+    #[tokio::test]
+    async fn matching_metadata_is_shared() {
+        let (host, client) = connect_with::<1>(
+            |host| {
+                host.metadata(&[("game", "go")])
+                    .soft_metadata(&[("name", "alice")])
+            },
+            |client| {
+                client
+                    .metadata(&[("game", "go")])
+                    .soft_metadata(&[("name", "bob"), ("locale", "fr")])
+            },
+        )
+        .await;
+        let theirs = host.peer_metadata().unwrap();
+        assert_eq!(theirs["game"], "go");
+        assert_eq!(theirs["name"], "bob");
+        assert_eq!(theirs["locale"], "fr");
+        assert_eq!(client.peer_metadata().unwrap()["name"], "alice");
+    }
+
+    /// The error for the players disagreeing on the game's version.
+    fn version_mismatch(ours: Option<&str>, theirs: Option<&str>) -> NetcodeError {
+        NetcodeError::MetadataMismatch {
+            key: "version".to_string(),
+            ours: ours.map(str::to_string),
+            theirs: theirs.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_metadata_fails_the_handshake() {
+        let (host, client) =
+            refused::<1>(|host| host.metadata(&[("version", "2")]), |client| client).await;
+        assert_eq!(host, version_mismatch(Some("2"), None));
+        assert_eq!(client, version_mismatch(None, Some("2")));
+    }
+
+    #[tokio::test]
+    async fn conflicting_metadata_fails_the_handshake() {
+        let (host, client) = refused::<1>(
+            |host| host.metadata(&[("version", "2")]),
+            |client| client.metadata(&[("version", "3")]),
+        )
+        .await;
+        assert_eq!(host, version_mismatch(Some("2"), Some("3")));
+        assert_eq!(client, version_mismatch(Some("3"), Some("2")));
+    }
+
+    #[tokio::test]
+    async fn diverging_states_are_caught_at_the_ply_they_diverge() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        // each player's state is the sum of the turns played.
+        let host_state = Arc::new(AtomicU64::new(0));
+        let client_state = Arc::new(AtomicU64::new(0));
+        let hashed = |state: &Arc<AtomicU64>| {
+            let state = state.clone();
+            move |builder: NetcodeInterfaceBuilder<1>| {
+                builder.state_hash(move || state.load(Ordering::SeqCst))
+            }
+        };
+        let (mut host, mut client) = connect_with(hashed(&host_state), hashed(&client_state)).await;
+        for ply in 1..=4 {
+            let (mover, mover_state, waiter, waiter_state) = match ply % 2 {
+                1 => (&mut client, &client_state, &mut host, &host_state),
+                _ => (&mut host, &host_state, &mut client, &client_state),
+            };
+            mover_state.fetch_add(ply, Ordering::SeqCst);
+            mover.send_turn(&[ply as u8]);
+            let [turn] = recv(waiter).await;
+            // the host misapplies the third move.
+            let misapplied = u64::from(ply == 3);
+            waiter_state.fetch_add(u64::from(turn) + misapplied, Ordering::SeqCst);
+            let divergence = waiter.try_recv_divergence();
+            if ply < 3 {
+                assert_eq!(divergence, None);
+            } else {
+                let (ours, theirs) = (
+                    waiter_state.load(Ordering::SeqCst),
+                    mover_state.load(Ordering::SeqCst),
+                );
+                assert_eq!(divergence, Some(StateDivergence { ply, ours, theirs }));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn both_players_record_the_same_history() {
+        let record = |builder: NetcodeInterfaceBuilder<1>| builder.record_history(true);
+        let (mut host, mut client) = connect_with(record, record).await;
+        for turn in 0..10 {
+            let (mover, waiter) = match turn % 2 {
+                0 => (&mut client, &mut host),
+                _ => (&mut host, &mut client),
+            };
+            mover.send_turn(&[turn]);
+            recv(waiter).await;
+        }
+        // timestamps are taken on each machine, so leave them out.
+        let moves = |netcode: &NetcodeInterface<1>, sent_by: Role| {
+            netcode
+                .history()
+                .iter()
+                .map(|record| {
+                    let role = match record.direction {
+                        Direction::Sent => sent_by,
+                        Direction::Received => sent_by.other(),
+                    };
+                    (record.turn, role, record.game, record.ply)
+                })
+                .collect::<Vec<_>>()
+        };
+        let host_moves = moves(&host, Role::Host);
+        assert_eq!(host_moves.len(), 10);
+        assert_eq!(host_moves, moves(&client, Role::Client));
+        assert_eq!(host_moves[0], ([0], Role::Client, 0, 1));
+        assert_eq!(host_moves[9], ([9], Role::Host, 0, 10));
+    }
+
+    #[tokio::test]
+    async fn turns_are_acked_once_received() {
+        let (mut host, mut client) = connect::<1>().await;
+        assert!(client.last_turn_acked());
+        client
+            .send_turn_with_ack_timeout(&[1], Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(client.last_turn_acked());
+        assert_eq!(recv(&mut host).await, [1]);
+        host.send_turn(&[2]);
+        eventually(|| host.last_turn_acked().then_some(())).await;
+    }
+
+    #[tokio::test]
+    async fn rejected_moves_can_be_sent_again() {
+        let (mut host, mut client) = connect::<1>().await;
+        for (turn, reason) in [(1, &b"illegal"[..]), (2, b"still illegal")] {
+            client.send_turn(&[turn]);
+            assert_eq!(recv(&mut host).await, [turn]);
+            host.reject_turn(reason);
+            assert!(!host.my_turn());
+            assert_eq!(host.ply(), 0);
+            let rejected = eventually(|| client.try_recv_rejection()).await;
+            assert_eq!(
+                rejected,
+                TurnRejected {
+                    ply: 1,
+                    reason: reason.to_vec(),
+                }
+            );
+            assert!(client.my_turn());
+            assert_eq!(client.ply(), 0);
+        }
+        client.send_turn(&[3]);
+        assert_eq!(recv(&mut host).await, [3]);
+        host.send_turn(&[4]);
+        assert_eq!(recv(&mut client).await, [4]);
+        assert_eq!((host.ply(), client.ply()), (2, 2));
+        assert_eq!(client.try_recv_rejection(), None);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "self.can_reject")]
+    async fn rejecting_the_same_move_twice_panics() {
+        let (mut host, mut client) = connect::<1>().await;
+        client.send_turn(&[1]);
+        recv(&mut host).await;
+        host.reject_turn(b"illegal");
+        host.reject_turn(b"illegal");
+    }
+
+    #[tokio::test]
+    async fn bundles_arrive_whole() {
+        let (mut host, mut client) = connect::<1>().await;
+        for (ply, len) in [(1, 1), (3, 10)] {
+            client.begin_turn();
+            for action in 0..len {
+                client.push_action(&[action]);
+            }
+            client.commit_turn();
+            let bundle = eventually(|| host.try_recv_bundle()).await;
+            assert_eq!(bundle, (0..len).map(|action| [action]).collect::<Vec<_>>());
+            // the whole bundle is a single ply.
+            assert_eq!(host.ply(), ply);
+            assert!(host.my_turn());
+            host.send_turn(&[0]);
+            recv(&mut client).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn leaving_mid_bundle_sends_nothing() {
+        let (mut host, mut client) = connect::<1>().await;
+        client.begin_turn();
+        client.push_action(&[1]);
+        client.push_action(&[2]);
+        drop(client);
+        assert_eq!(stopped(&host).await, NetcodeError::PeerClosed);
+        assert_eq!(host.try_recv_bundle(), None);
+        assert_eq!(host.ply(), 0);
+    }
+
+    #[tokio::test]
+    async fn a_bundle_sent_just_before_leaving_arrives_whole() {
+        let (mut host, mut client) = connect::<1>().await;
+        client.send_turns(&[[1], [2], [3]]);
+        drop(client);
+        assert_eq!(eventually(|| host.try_recv_bundle()).await, [[1], [2], [3]]);
+        assert_eq!(stopped(&host).await, NetcodeError::PeerClosed);
+    }
+
+    #[tokio::test]
+    async fn both_players_can_finish_at_once() {
+        let (mut host, mut client) = connect::<1>().await;
+        let (host_finished, client_finished) = tokio::join!(
+            host.finish_game(b"host resigns"),
+            client.finish_game(b"client resigns")
+        );
+        assert_eq!(host_finished, Ok(()));
+        assert_eq!(client_finished, Ok(()));
+        assert_eq!(
+            eventually(|| host.try_recv_finish()).await,
+            GameFinished(b"client resigns".to_vec())
+        );
+        assert_eq!(
+            eventually(|| client.try_recv_finish()).await,
+            GameFinished(b"host resigns".to_vec())
+        );
+        assert!(!host.my_turn() && !client.my_turn());
+        assert_eq!(
+            client.send_turn_checked(&[1]).await,
+            Err(NetcodeError::GameOver)
+        );
+    }
+
+    #[tokio::test]
+    async fn turn_sizes_mirror_each_other() {
+        let (mut host, mut client) = connect_with::<12>(
+            |host| host.turn_sizes(12, 4),
+            |client| client.turn_sizes(4, 12),
+        )
+        .await;
+        client.send_turn(b"movedropped!");
+        // only the client's first four bytes are sent.
+        assert_eq!(&recv(&mut host).await, b"move\0\0\0\0\0\0\0\0");
+        host.send_turn(b"a whole turn");
+        assert_eq!(&recv(&mut client).await, b"a whole turn");
+    }
+
+    #[tokio::test]
+    async fn identical_turn_sizes_fail_the_handshake() {
+        let (host, client) = refused::<12>(
+            |host| host.turn_sizes(12, 4),
+            |client| client.turn_sizes(12, 4),
+        )
+        .await;
+        assert!(matches!(host, NetcodeError::HandshakeFailed(_)));
+        assert!(matches!(client, NetcodeError::HandshakeFailed(_)));
+    }
+
+    #[test]
+    fn a_megabyte_turn_arrives_in_chunks() {
+        const MEGABYTE: usize = 1 << 20;
+        async fn game() {
+            let (mut host, mut client) = connect::<MEGABYTE>().await;
+            let turn: Box<[u8; MEGABYTE]> = (0..MEGABYTE)
+                .map(|i| i as u8)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            client.send_turn(&turn);
+            let mut received = Box::new([0; MEGABYTE]);
+            eventually(|| host.try_recv_turn_into(&mut received).ok()).await;
+            assert!(received == turn);
+
+            let progress = host.incoming_transfer().unwrap();
+            assert_eq!(progress.done, progress.total);
+            assert!(progress.total > MEGABYTE as u64);
+            let sent = eventually(|| {
+                client
+                    .outgoing_transfer()
+                    .filter(|progress| progress.done == progress.total)
+            })
+            .await;
+            assert_eq!(sent, progress);
+        }
+        // turns are moved around by value, which takes more stack than a
+        // test thread has.
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+                    .block_on(game())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_second_player_is_refused_because_the_game_is_full() {
+        let (ticket_sender, ticket) = oneshot::channel();
+        let mut host = NetcodeInterface::<1>::new(Config::TicketSender(ticket_sender));
+        let ticket = ticket.await.unwrap();
+        let mut first = NetcodeInterface::<1>::new(Config::Ticket(ticket.clone()));
+        let mut second = NetcodeInterface::<1>::new(Config::Ticket(ticket));
+        let (host_connected, first_connected, second_connected) = tokio::join!(
+            host.wait_for_connection(),
+            first.wait_for_connection(),
+            second.wait_for_connection()
+        );
+        host_connected.unwrap();
+        // either client may get there first.
+        let mut player = match (first_connected, second_connected) {
+            (Ok(()), Err(e)) => {
+                assert_eq!(e, NetcodeError::GameFull);
+                first
+            }
+            (Err(e), Ok(())) => {
+                assert_eq!(e, NetcodeError::GameFull);
+                second
+            }
+            results => panic!("expected one player to be refused, got {results:?}"),
+        };
+
+        // the refusal left the game alone.
+        player.send_turn(&[1]);
+        assert_eq!(recv(&mut host).await, [1]);
+        host.send_turn(&[2]);
+        assert_eq!(recv(&mut player).await, [2]);
+    }
+
+    #[test]
+    fn iroh_version_is_the_dependency_series() {
+        let requirement = include_str!("../Cargo.toml")
+            .lines()
+            .find_map(|line| line.strip_prefix("iroh = \""))
+            .expect("Cargo.toml should depend on iroh");
+        assert!(requirement.starts_with(&format!("{}.", iroh_version())));
     }
 }
@@ -0,0 +1,90 @@
+//! Metadata, like a game's name or rules version, that the players compare
+//! during the handshake.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{NetcodeError, protocol::BoxError};
+
+/// The metadata one player sends during the handshake.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Entries both players must agree on.
+    pub hard: BTreeMap<String, String>,
+    /// Entries that are only shared, never compared.
+    pub soft: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    /// Every entry, hard or soft.
+    pub fn all(&self) -> BTreeMap<String, String> {
+        let mut all = self.soft.clone();
+        all.extend(self.hard.clone());
+        all
+    }
+
+    fn get(&self, key: &str) -> Option<&String> {
+        self.hard.get(key).or_else(|| self.soft.get(key))
+    }
+
+    /// Make sure every key that is hard for either player has the same value
+    /// on both sides.
+    ///
+    /// Keys are checked in order, so both players report the same key.
+    pub fn check(&self, theirs: &Metadata) -> Result<(), NetcodeError> {
+        let hard_keys: BTreeSet<_> = self.hard.keys().chain(theirs.hard.keys()).collect();
+        for key in hard_keys {
+            let (ours, theirs) = (self.get(key), theirs.get(key));
+            if ours != theirs {
+                return Err(NetcodeError::MetadataMismatch {
+                    key: key.clone(),
+                    ours: ours.cloned(),
+                    theirs: theirs.cloned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Each entry is a hard or soft byte, then the key and value, each
+    /// prefixed with a big-endian `u16` length.
+    pub fn encode(&self) -> Result<Vec<u8>, BoxError> {
+        let mut bytes = Vec::new();
+        let hard = self.hard.iter().map(|entry| (1, entry));
+        let soft = self.soft.iter().map(|entry| (0, entry));
+        let entries = hard.chain(soft);
+        for (hard, (key, value)) in entries {
+            bytes.push(hard);
+            for s in [key, value] {
+                bytes.extend_from_slice(&u16::try_from(s.len())?.to_be_bytes());
+                bytes.extend_from_slice(s.as_bytes());
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// The inverse of [`encode`](Metadata::encode).
+    pub fn decode(mut bytes: &[u8]) -> Result<Metadata, BoxError> {
+        let mut metadata = Metadata::default();
+        let next_string = |bytes: &mut &[u8]| -> Result<String, BoxError> {
+            let (len, rest) = bytes
+                .split_first_chunk::<2>()
+                .ok_or("the other player sent truncated metadata")?;
+            let (s, rest) = rest
+                .split_at_checked(usize::from(u16::from_be_bytes(*len)))
+                .ok_or("the other player sent truncated metadata")?;
+            *bytes = rest;
+            Ok(String::from_utf8(s.to_vec())?)
+        };
+        while let Some((&hard, rest)) = bytes.split_first() {
+            bytes = rest;
+            let key = next_string(&mut bytes)?;
+            let value = next_string(&mut bytes)?;
+            match hard {
+                0 => metadata.soft.insert(key, value),
+                1 => metadata.hard.insert(key, value),
+                _ => return Err("the other player sent malformed metadata".into()),
+            };
+        }
+        Ok(metadata)
+    }
+}
@@ -0,0 +1,141 @@
+//! Pausing and resuming the game when both players agree.
+//!
+//! A pause is requested and answered like a draw. Either player can resume,
+//! but the player who resumes waits for the other to acknowledge it before
+//! unpausing, so both players' clocks restart at about the same time.
+
+use std::collections::VecDeque;
+
+use crate::frame::ControlFrame;
+
+/// Something that happened to a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseEvent {
+    /// The other player asked to pause. Answer with
+    /// [`respond_pause`](crate::NetcodeInterface::respond_pause).
+    Requested,
+    /// The other player declined the user's request.
+    Declined,
+    /// Both players agreed to pause, so clocks and turn deadlines stopped.
+    Paused,
+    /// The game resumed, so clocks and turn deadlines started again.
+    Resumed,
+}
+
+/// Who asked for the pause that has yet to be answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Ours,
+    Theirs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Playing,
+    Requested(Request),
+    Paused,
+    /// We resumed, and are waiting for the other player to acknowledge it.
+    Resuming,
+}
+
+/// Both players' view of pauses. Unlike draws, a pause lasts across games.
+#[derive(Debug, Default)]
+pub(crate) struct Pause {
+    state: State,
+    /// Events the game has yet to receive.
+    events: VecDeque<PauseEvent>,
+}
+
+impl Pause {
+    /// Whether the game is paused, including while our resume has yet to be
+    /// acknowledged.
+    pub fn paused(&self) -> bool {
+        matches!(self.state, State::Paused | State::Resuming)
+    }
+
+    /// Ask to pause, returning the frame to send, if any.
+    ///
+    /// Asking again while our request is outstanding, or while paused, does
+    /// nothing. Asking while theirs is outstanding accepts it.
+    pub fn request(&mut self) -> Option<ControlFrame> {
+        match self.state {
+            State::Requested(Request::Ours) | State::Paused | State::Resuming => None,
+            State::Requested(Request::Theirs) => {
+                self.pause();
+                Some(ControlFrame::PauseAccept)
+            }
+            State::Playing => {
+                self.state = State::Requested(Request::Ours);
+                Some(ControlFrame::PauseRequest)
+            }
+        }
+    }
+
+    /// Answer the other player's request, returning the frame to send.
+    pub fn respond(&mut self, accept: bool) -> ControlFrame {
+        assert_eq!(self.state, State::Requested(Request::Theirs));
+        if accept {
+            self.pause();
+            ControlFrame::PauseAccept
+        } else {
+            self.state = State::Playing;
+            ControlFrame::PauseDecline
+        }
+    }
+
+    /// Resume, returning the frame to send, if any.
+    pub fn resume(&mut self) -> Option<ControlFrame> {
+        assert!(self.paused());
+        if self.state == State::Resuming {
+            return None;
+        }
+        self.state = State::Resuming;
+        Some(ControlFrame::Resume)
+    }
+
+    /// Handle a pause frame from the other player, returning the frame to
+    /// send back, if any.
+    pub fn handle(&mut self, control: ControlFrame) -> Option<ControlFrame> {
+        match (control, self.state) {
+            // our requests crossed in flight, so both of us see the other's
+            // request while our own is outstanding, and both pause.
+            (ControlFrame::PauseRequest, State::Requested(Request::Ours)) => self.pause(),
+            (ControlFrame::PauseRequest, State::Playing) => {
+                self.state = State::Requested(Request::Theirs);
+                self.events.push_back(PauseEvent::Requested);
+            }
+            (ControlFrame::PauseAccept, State::Requested(Request::Ours)) => self.pause(),
+            (ControlFrame::PauseDecline, State::Requested(Request::Ours)) => {
+                self.state = State::Playing;
+                self.events.push_back(PauseEvent::Declined);
+            }
+            // if both of us resumed, both acknowledge, and both acks are
+            // ignored.
+            (ControlFrame::Resume, State::Paused | State::Resuming) => {
+                self.unpause();
+                return Some(ControlFrame::ResumeAck);
+            }
+            (ControlFrame::ResumeAck, State::Resuming) => self.unpause(),
+            // anything else answers a request that does not exist, which a
+            // well-behaved player never sends.
+            _ => {}
+        }
+        None
+    }
+
+    /// Return the next event the game has yet to receive.
+    pub fn next_event(&mut self) -> Option<PauseEvent> {
+        self.events.pop_front()
+    }
+
+    fn pause(&mut self) {
+        self.state = State::Paused;
+        self.events.push_back(PauseEvent::Paused);
+    }
+
+    fn unpause(&mut self) {
+        self.state = State::Playing;
+        self.events.push_back(PauseEvent::Resumed);
+    }
+}
@@ -0,0 +1,146 @@
+//! Exporting chess-like games to PGN, so they can be imported into any chess
+//! tool.
+
+use crate::NetcodeInterface;
+
+/// The headers of a PGN export. These are PGN's seven tag roster, except
+/// `Round`, which is always `-`.
+///
+/// Each defaults to `?`, meaning unknown, except `result`, which defaults to
+/// `*`, meaning the game is unfinished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnHeaders {
+    pub event: String,
+    pub site: String,
+    /// In the format `YYYY.MM.DD`.
+    pub date: String,
+    /// The player who moved first.
+    pub white: String,
+    pub black: String,
+    /// One of `1-0`, `0-1`, `1/2-1/2`, or `*`.
+    pub result: String,
+}
+
+impl Default for PgnHeaders {
+    fn default() -> Self {
+        Self {
+            event: "?".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+            result: "*".to_string(),
+        }
+    }
+}
+
+/// The longest line of movetext, as recommended for PGN exports.
+const MAX_LINE_LEN: usize = 79;
+
+impl NetcodeInterface<4> {
+    /// Export the current game in the [`history`](NetcodeInterface::history)
+    /// as PGN.
+    ///
+    /// This is only correct for games whose turns are encoded like the
+    /// pieceboard example's: the source rank as a number from 1 to 8, the
+    /// source file as an ASCII letter from `a` to `h`, then the same for the
+    /// destination. Each turn is written in long algebraic notation, like
+    /// `e2e4`, because the pieces that moved are not known.
+    ///
+    /// The history is only kept with
+    /// [`record_history`](crate::NetcodeInterfaceBuilder::record_history).
+    ///
+    /// Requires the `chess-pgn` feature.
+    pub fn to_pgn(&self, headers: PgnHeaders) -> String {
+        let turns = self
+            .history()
+            .iter()
+            .filter(|record| record.game == self.game)
+            .map(|record| record.turn)
+            .collect::<Vec<_>>();
+        format_pgn(headers, &turns)
+    }
+}
+
+/// Write the headers, then each turn in long algebraic notation.
+fn format_pgn(headers: PgnHeaders, turns: &[[u8; 4]]) -> String {
+    let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut pgn = String::new();
+    for (tag, value) in [
+        ("Event", headers.event.as_str()),
+        ("Site", &headers.site),
+        ("Date", &headers.date),
+        ("Round", "-"),
+        ("White", &headers.white),
+        ("Black", &headers.black),
+        ("Result", &headers.result),
+    ] {
+        pgn.push_str(&format!("[{tag} \"{}\"]\n", escape(value)));
+    }
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    for (ply, [src_rank, src_file, dest_rank, dest_file]) in turns.iter().enumerate() {
+        if ply % 2 == 0 {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        }
+        tokens.push(format!(
+            "{}{src_rank}{}{dest_rank}",
+            char::from(*src_file),
+            char::from(*dest_file)
+        ));
+    }
+    tokens.push(headers.result);
+
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > MAX_LINE_LEN {
+            pgn.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            pgn.push(' ');
+            line_len += 1;
+        }
+        line_len += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+    pgn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_are_escaped() {
+        let headers = PgnHeaders {
+            event: r#"the "big" one\"#.to_string(),
+            ..PgnHeaders::default()
+        };
+        let pgn = format_pgn(headers, &[]);
+        assert!(pgn.starts_with("[Event \"the \\\"big\\\" one\\\\\"]\n[Site \"?\"]\n"));
+        assert!(pgn.ends_with("[Result \"*\"]\n\n*\n"));
+    }
+
+    #[test]
+    fn moves_are_numbered_in_long_algebraic_notation() {
+        let headers = PgnHeaders {
+            result: "1-0".to_string(),
+            ..PgnHeaders::default()
+        };
+        let turns = [[2, b'e', 4, b'e'], [7, b'e', 5, b'e'], [1, b'g', 3, b'f']];
+        let pgn = format_pgn(headers, &turns);
+        assert!(pgn.ends_with("\n\n1. e2e4 e7e5 2. g1f3 1-0\n"));
+    }
+
+    #[test]
+    fn movetext_is_wrapped() {
+        let turns = [[2, b'e', 4, b'e']; 40];
+        let pgn = format_pgn(PgnHeaders::default(), &turns);
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        assert_eq!(movetext.split_whitespace().count(), 40 + 20 + 1);
+    }
+}
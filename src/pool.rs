@@ -0,0 +1,116 @@
+//! Hosting several independent games at once, like a game server's lobby.
+
+use std::{
+    collections::{BTreeMap, btree_map},
+    fmt,
+};
+
+use tokio::sync::oneshot;
+
+use crate::{Config, ConnectionState, NetcodeError, NetcodeInterface, NetcodeInterfaceBuilder};
+
+/// Configures each game a [`NetcodeInterfacePool`] hosts.
+type Configure<const SIZE: usize> =
+    Box<dyn Fn(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE> + Send>;
+
+/// Identifies a game in a [`NetcodeInterfacePool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GameId(u64);
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "game {}", self.0)
+    }
+}
+
+/// Hosts several independent two-player games at once, each with its own
+/// [`NetcodeInterface`].
+///
+/// Every game is hosted like one created with [`Config::TicketSender`], on
+/// its own endpoint, so each has its own ticket and node ID, and nothing is
+/// shared between them. Each game is still between two players: the pool
+/// only keeps track of the interfaces, and is polled like them.
+pub struct NetcodeInterfacePool<const SIZE: usize> {
+    games: BTreeMap<GameId, NetcodeInterface<SIZE>>,
+    next_id: u64,
+    configure: Configure<SIZE>,
+}
+
+impl<const SIZE: usize> Default for NetcodeInterfacePool<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SIZE: usize> NetcodeInterfacePool<SIZE> {
+    /// Create an empty pool, whose games have the default options.
+    pub fn new() -> Self {
+        Self::with_options(|builder| builder)
+    }
+
+    /// Create an empty pool, whose games are configured by `configure`.
+    pub fn with_options(
+        configure: impl Fn(NetcodeInterfaceBuilder<SIZE>) -> NetcodeInterfaceBuilder<SIZE>
+        + Send
+        + 'static,
+    ) -> Self {
+        Self {
+            games: BTreeMap::new(),
+            next_id: 0,
+            configure: Box::new(configure),
+        }
+    }
+
+    /// Host a new game, returning the ticket the other player connects
+    /// with, and the game's id.
+    ///
+    /// Returns the error that stopped the game, if it stopped before it had
+    /// a ticket, in which case it is not added to the pool.
+    pub async fn host_new_game(&mut self) -> Result<(String, GameId), NetcodeError> {
+        let (send, recv) = oneshot::channel();
+        let netcode =
+            (self.configure)(NetcodeInterface::builder(Config::TicketSender(send))).build();
+        let Ok(ticket) = recv.await else {
+            return Err(match netcode.connection_state() {
+                ConnectionState::Disconnected(e) => e,
+                _ => NetcodeError::Disconnected("the game stopped before it had a ticket".into()),
+            });
+        };
+        let id = GameId(self.next_id);
+        self.next_id += 1;
+        self.games.insert(id, netcode);
+        Ok((ticket, id))
+    }
+
+    /// Return the game with `id`, if it is in the pool.
+    pub fn get(&self, id: GameId) -> Option<&NetcodeInterface<SIZE>> {
+        self.games.get(&id)
+    }
+
+    /// Return the game with `id`, if it is in the pool.
+    pub fn get_mut(&mut self, id: GameId) -> Option<&mut NetcodeInterface<SIZE>> {
+        self.games.get_mut(&id)
+    }
+
+    /// Take the game with `id` out of the pool, like once it is over.
+    ///
+    /// Dropping the interface closes its connection.
+    pub fn remove(&mut self, id: GameId) -> Option<NetcodeInterface<SIZE>> {
+        self.games.remove(&id)
+    }
+
+    /// Return every game in the pool, oldest first, to poll each of them.
+    pub fn iter_mut(&mut self) -> btree_map::IterMut<'_, GameId, NetcodeInterface<SIZE>> {
+        self.games.iter_mut()
+    }
+
+    /// Return how many games are in the pool.
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Return whether the pool has no games.
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+}
@@ -0,0 +1,49 @@
+//! Sending game-specific structs as turns, encoded with postcard, whose
+//! compact encoding suits small turn sizes.
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{NetcodeError, NetcodeInterface, TurnPoll};
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Encode `turn` with postcard, then send it like
+    /// [`send_turn_checked`](NetcodeInterface::send_turn_checked).
+    ///
+    /// The turn is encoded straight into the turn buffer, padded with zeros.
+    /// Returns [`NetcodeError::TurnTooLarge`] without sending anything if it
+    /// does not fit, or the same errors as `send_turn_checked`.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub async fn send_turn_postcard<T: Serialize>(&mut self, turn: &T) -> Result<(), NetcodeError> {
+        let mut bytes = [0; SIZE];
+        match postcard::to_slice(turn, &mut bytes) {
+            Ok(_) => {}
+            Err(postcard::Error::SerializeBufferFull) => return Err(NetcodeError::TurnTooLarge),
+            Err(e) => return Err(NetcodeError::MalformedTurn(e.to_string())),
+        }
+        self.send_turn_checked(&bytes).await
+    }
+
+    /// Check if the other player has sent a turn, like
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn), and decode it with
+    /// postcard.
+    ///
+    /// Returns `Ok(None)` if there is no turn yet, or the other player passed,
+    /// in which case it is the user's turn. Returns
+    /// [`NetcodeError::MalformedTurn`] if the turn does not decode as a `T`.
+    /// The turn was still received, so the game may want to
+    /// [reject](NetcodeInterface::reject_turn) it.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    pub fn try_recv_turn_postcard<T: DeserializeOwned>(
+        &mut self,
+    ) -> Result<Option<T>, NetcodeError> {
+        let TurnPoll::Ready(bytes) = self.try_recv_turn() else {
+            return Ok(None);
+        };
+        // the padding after the encoded turn is ignored.
+        postcard::from_bytes(&bytes)
+            .map(Some)
+            .map_err(|e| NetcodeError::MalformedTurn(e.to_string()))
+    }
+}
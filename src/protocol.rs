@@ -2,93 +2,1974 @@
 //! The iroh protcol implementation that the interface uses under the hood.
 
 use iroh::Watcher;
-use iroh::{Endpoint, NodeAddr};
+use iroh::endpoint::{
+    Connection, ConnectionError, RecvStream, RelayMode, SendStream, TransportErrorCode, VarInt,
+};
+use iroh::{Endpoint, NodeAddr, NodeId, RelayMap, RelayUrl};
 use iroh_base::ticket::NodeTicket;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
 use std::str::FromStr;
-use tokio::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::net::TcpStream;
+use tokio::sync::{
+    Notify, Semaphore, broadcast,
+    mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender},
+    oneshot, watch,
+};
+use tokio::task::{self, JoinHandle};
+use tokio::time;
 
-use crate::Config;
+use crate::{
+    Config, ConnectionEvent, ConnectionState, DiffEncoder, NetcodeError, Role, TimeControl,
+    compression::{self, Compression},
+    frame::{ControlFrame, Frame, HEADER_LEN, Hello},
+    metadata::Metadata,
+    reconnect::Reconnect,
+    session::SessionGame,
+    spectator::{self, SPECTATOR_ALPN, SpectatorTicket},
+    suspend::SessionSnapshot,
+    transfer::{self, CHUNK_LEN, Reassembly, TransferProgress},
+    url,
+};
 
 /// ALPN string for the sfn-tpn protocol.
 ///
 /// The ALPN, or application-layer protocol negotiation, is exchanged in the connection handshake,
 /// and the connection is aborted unless both nodes pass the same bytestring.
-pub const ALPN: &[u8] = b"saffron/sfn-tpn/0";
+pub const ALPN: &[u8] = b"saffron/sfn-tpn/1";
+
+/// The ALPNs of earlier versions of the protocol, newest first, to tell
+/// which one a player who refuses ours speaks.
+const EARLIER_ALPNS: &[&[u8]] = &[b"saffron/sfn-tpn/0"];
+
+/// How long we wait for the other player to receive our last frames before
+/// closing the connection.
+const GOODBYE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The datagram sent when the player is active, like selecting a piece or
+/// typing in chat. It is the only datagram we send.
+const ACTIVITY_DATAGRAM: &[u8] = &[0];
+
+/// The application error code a connection is closed with when the host
+/// already has a player.
+const GAME_FULL: VarInt = VarInt::from_u32(1);
+
+/// The application error code a connection is closed with when the host is
+/// restoring a session with someone else.
+const NOT_THE_PLAYER: VarInt = VarInt::from_u32(2);
+
+/// How often we sample the connection's round-trip time.
+const RTT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long we try to reach the internet check's target before deciding
+/// that we cannot.
+const INTERNET_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How many turns we buffer for spectators that are slow to receive them.
+const SPECTATOR_BACKLOG: usize = 64;
+
+/// How long opening a stream and resuming the game may take once the other
+/// player is found again, after the connection dropped.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const STATE_LOCK: &str = "nothing panics while holding the lock";
+
+/// The TLS alert a node sends when it speaks none of the ALPNs it was offered.
+const NO_APPLICATION_PROTOCOL: u8 = 120;
+
+/// Any error that stops the protocol.
+///
+/// A [`NetcodeError`] in here is reported to the game as-is. Anything else
+/// is only ever displayed.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The protocol's ends of the channels to the interface.
+pub struct GameChannels<const SIZE: usize> {
+    /// Unbounded, because moves from a finished game may still be in flight
+    /// when the next game starts. Received moves are timestamped on arrival.
+    pub send_to_game: UnboundedSender<(Instant, Move<SIZE>)>,
+    pub recv_from_game: Receiver<Move<SIZE>>,
+    pub send_control_to_game: UnboundedSender<ControlFrame>,
+    pub recv_control_from_game: UnboundedReceiver<ControlFrame>,
+    /// Activity pings, sent as datagrams rather than on the stream, so they
+    /// never hold up anything else. Received pings are timestamped on arrival.
+    pub send_activity_to_game: UnboundedSender<Instant>,
+    pub recv_activity_from_game: UnboundedReceiver<()>,
+    /// Requests to reconnect, with [`Reconnect::Manual`].
+    pub recv_reconnect_from_game: UnboundedReceiver<()>,
+    /// Notified whenever a move or control frame is sent to the game.
+    pub turn_notify: Arc<Notify>,
+}
+
+/// One or more turns sent together, and the number of the game they belong to.
+///
+/// The turns are on the heap, so a move crosses the channels between the
+/// interface and the protocol without its turns being copied. They are only
+/// copied to take them from the game's borrowed array, to encode them into
+/// a frame, and to hand them back to the game, which reference-counted
+/// buffers would not avoid.
+pub struct Move<const SIZE: usize> {
+    pub game: u32,
+    /// The sender's state hash after the move, if they registered a
+    /// [`state_hash`](crate::NetcodeInterfaceBuilder::state_hash).
+    pub state_hash: Option<u64>,
+    /// The time the sender has left after the move, if the players agreed on
+    /// a [`TimeControl`].
+    pub clock: Option<Duration>,
+    pub turns: Vec<[u8; SIZE]>,
+}
+
+/// Options from the builder that the protocol needs to know about.
+pub struct ProtocolOptions<const SIZE: usize> {
+    pub first_move: Option<Role>,
+    /// Whether to check that we can reach the internet before connecting.
+    pub check_internet: bool,
+    /// The `host:port` to reach when checking, or `None` for the relay we
+    /// would use.
+    pub internet_check_target: Option<String>,
+    /// The only relay to use, instead of n0's public ones.
+    pub relay_url: Option<RelayUrl>,
+    pub max_spectators: Option<u8>,
+    /// Whether the players submit turns in simultaneous rounds rather than
+    /// alternating.
+    pub simultaneous: bool,
+    pub encoder: Option<Box<dyn DiffEncoder<SIZE>>>,
+    pub compression: Option<Compression>,
+    /// The turn size both players must agree on, at most `SIZE`.
+    pub size: usize,
+    /// How many bytes of each turn we send, then receive.
+    pub turn_sizes: (usize, usize),
+    /// Only used with a connection of our own.
+    pub reconnect: Reconnect,
+    pub time_control: Option<TimeControl>,
+    /// Only used by the host.
+    pub setup: Option<Vec<u8>>,
+    pub metadata: Metadata,
+}
+
+/// The interface's ends of everything connected to a running protocol.
+pub struct ProtocolHandles<const SIZE: usize> {
+    pub recv_from_iroh: UnboundedReceiver<(Instant, Move<SIZE>)>,
+    pub send_to_iroh: Sender<Move<SIZE>>,
+    pub recv_control_from_iroh: UnboundedReceiver<ControlFrame>,
+    pub send_control_to_iroh: UnboundedSender<ControlFrame>,
+    pub recv_activity_from_iroh: UnboundedReceiver<Instant>,
+    pub send_activity_to_iroh: UnboundedSender<()>,
+    pub send_reconnect_to_iroh: UnboundedSender<()>,
+    pub connection_state: watch::Receiver<ConnectionState>,
+    pub spectator_ticket: watch::Receiver<Option<SpectatorTicket>>,
+    pub first_move: watch::Receiver<Option<Role>>,
+    pub bytes_in_flight: watch::Receiver<usize>,
+    pub connected_at: watch::Receiver<Option<Instant>>,
+    pub established_at: watch::Receiver<Option<SystemTime>>,
+    pub setup: watch::Receiver<Option<Vec<u8>>>,
+    pub seed: watch::Receiver<Option<[u8; 32]>>,
+    pub session_id: watch::Receiver<Option<[u8; 16]>>,
+    pub acked: watch::Receiver<u64>,
+    pub written: watch::Receiver<u64>,
+    pub rtt: watch::Receiver<Option<Duration>>,
+    pub turn_bytes: watch::Receiver<(u64, u64)>,
+    pub compressed_bytes: watch::Receiver<(u64, u64)>,
+    pub outgoing_transfer: watch::Receiver<Option<TransferProgress>>,
+    pub incoming_transfer: watch::Receiver<Option<TransferProgress>>,
+    pub node_keys: watch::Receiver<Option<([u8; 32], [u8; 32])>>,
+    pub peer_metadata: watch::Receiver<Option<BTreeMap<String, String>>>,
+    pub endpoint: watch::Receiver<Option<Endpoint>>,
+    pub turn_notify: Arc<Notify>,
+    pub connection_log: Arc<Mutex<Vec<ConnectionEvent>>>,
+    /// A handle to the thread running iroh under the hood.
+    pub iroh_handle: JoinHandle<()>,
+}
+
+/// How the protocol reaches the other player.
+pub enum Transport {
+    /// Over a connection of its own.
+    Config(Config),
+    /// Over a bi-stream of a [`Session`](crate::Session)'s connection.
+    Session(SessionGame),
+}
+
+impl Transport {
+    /// The role of the player using this transport.
+    pub fn role(&self) -> Role {
+        match self {
+            Transport::Config(config) => Role::of(config),
+            Transport::Session(game) => game.role(),
+        }
+    }
+}
+
+/// Spawn the iroh protocol in a new thread, and return the ends of the
+/// channels to it.
+pub fn spawn_iroh_protocol<const SIZE: usize>(
+    transport: Transport,
+    options: ProtocolOptions<SIZE>,
+) -> ProtocolHandles<SIZE> {
+    // hand-coding a bidirectional channel, sorta :p
+    let (send_to_iroh, recv_from_game) = mpsc::channel(1);
+    let (send_to_game, recv_from_iroh) = mpsc::unbounded_channel();
+    let (send_control_to_iroh, recv_control_from_game) = mpsc::unbounded_channel();
+    let (send_control_to_game, recv_control_from_iroh) = mpsc::unbounded_channel();
+    let (send_activity_to_iroh, recv_activity_from_game) = mpsc::unbounded_channel();
+    let (send_activity_to_game, recv_activity_from_iroh) = mpsc::unbounded_channel();
+    let (send_reconnect_to_iroh, recv_reconnect_from_game) = mpsc::unbounded_channel();
+    let (state_sender, connection_state) = watch::channel(ConnectionState::Connecting);
+    let (spectator_ticket_sender, spectator_ticket) = watch::channel(None);
+    let (first_move_sender, first_move) = watch::channel(options.first_move);
+    let (bytes_in_flight_sender, bytes_in_flight) = watch::channel(0);
+    let (connected_at_sender, connected_at) = watch::channel(None);
+    let (established_at_sender, established_at) = watch::channel(None);
+    let (setup_sender, setup) = watch::channel(None);
+    let (seed_sender, seed) = watch::channel(None);
+    let (session_id_sender, session_id) = watch::channel(None);
+    let (acked_sender, acked) = watch::channel(0);
+    let (written_sender, written) = watch::channel(0);
+    let (rtt_sender, rtt) = watch::channel(None);
+    let (turn_bytes_sender, turn_bytes) = watch::channel((0, 0));
+    let (compressed_bytes_sender, compressed_bytes) = watch::channel((0, 0));
+    let (outgoing_transfer_sender, outgoing_transfer) = watch::channel(None);
+    let (incoming_transfer_sender, incoming_transfer) = watch::channel(None);
+    let (node_keys_sender, node_keys) = watch::channel(None);
+    let (peer_metadata_sender, peer_metadata) = watch::channel(None);
+    let (endpoint_sender, endpoint) = watch::channel(None);
+    let turn_notify = Arc::new(Notify::new());
+    let connection_log = Arc::new(Mutex::new(Vec::new()));
+    let iroh_handle = task::spawn(start_iroh_protocol(
+        GameChannels {
+            send_to_game,
+            recv_from_game,
+            send_control_to_game,
+            recv_control_from_game,
+            send_activity_to_game,
+            recv_activity_from_game,
+            recv_reconnect_from_game,
+            turn_notify: turn_notify.clone(),
+        },
+        transport,
+        options,
+        StatusSenders {
+            state: state_sender,
+            spectator_ticket: spectator_ticket_sender,
+            first_move: first_move_sender,
+            bytes_in_flight: bytes_in_flight_sender,
+            connected_at: connected_at_sender,
+            established_at: established_at_sender,
+            setup: setup_sender,
+            seed: seed_sender,
+            session_id: session_id_sender,
+            acked: acked_sender,
+            written: written_sender,
+            rtt: rtt_sender,
+            turn_bytes: turn_bytes_sender,
+            compressed_bytes: compressed_bytes_sender,
+            outgoing_transfer: outgoing_transfer_sender,
+            incoming_transfer: incoming_transfer_sender,
+            node_keys: node_keys_sender,
+            peer_metadata: peer_metadata_sender,
+            endpoint: endpoint_sender,
+            log: connection_log.clone(),
+        },
+    ));
+
+    ProtocolHandles {
+        recv_from_iroh,
+        send_to_iroh,
+        recv_control_from_iroh,
+        send_control_to_iroh,
+        recv_activity_from_iroh,
+        send_activity_to_iroh,
+        send_reconnect_to_iroh,
+        connection_state,
+        spectator_ticket,
+        first_move,
+        bytes_in_flight,
+        connected_at,
+        established_at,
+        setup,
+        seed,
+        session_id,
+        acked,
+        written,
+        rtt,
+        turn_bytes,
+        compressed_bytes,
+        outgoing_transfer,
+        incoming_transfer,
+        node_keys,
+        peer_metadata,
+        endpoint,
+        turn_notify,
+        connection_log,
+        iroh_handle,
+    }
+}
+
+/// Where the protocol reports what it learns back to the interface.
+pub struct StatusSenders {
+    pub state: watch::Sender<ConnectionState>,
+    pub spectator_ticket: watch::Sender<Option<SpectatorTicket>>,
+    /// Set once the coin is flipped, if the first move is random.
+    pub first_move: watch::Sender<Option<Role>>,
+    /// How many bytes the game sent that the stream has yet to accept.
+    pub bytes_in_flight: watch::Sender<usize>,
+    /// Set once the connection is established.
+    pub connected_at: watch::Sender<Option<Instant>>,
+    /// Set during the handshake, by the client's wall clock.
+    pub established_at: watch::Sender<Option<SystemTime>>,
+    /// Set during the handshake, if the host configured a setup.
+    pub setup: watch::Sender<Option<Vec<u8>>>,
+    /// Set during the handshake.
+    pub seed: watch::Sender<Option<[u8; 32]>>,
+    /// Set during the handshake.
+    pub session_id: watch::Sender<Option<[u8; 16]>>,
+    /// The sequence number of the last move the other player acknowledged.
+    pub acked: watch::Sender<u64>,
+    /// The sequence number of the last of our moves written to the stream
+    /// in full.
+    pub written: watch::Sender<u64>,
+    /// Sampled while playing, if the game has a connection of its own.
+    pub rtt: watch::Sender<Option<Duration>>,
+    /// How many bytes of turns we sent, then received, as encoded on the
+    /// stream, but without the frames around them.
+    pub turn_bytes: watch::Sender<(u64, u64)>,
+    /// How many bytes the frames we compressed or decompressed took up
+    /// uncompressed, then compressed, framing included.
+    pub compressed_bytes: watch::Sender<(u64, u64)>,
+    /// How much of the latest move sent in chunks we sent.
+    pub outgoing_transfer: watch::Sender<Option<TransferProgress>>,
+    /// How much of the latest move sent in chunks we received.
+    pub incoming_transfer: watch::Sender<Option<TransferProgress>>,
+    /// Our secret key, then the other player's node ID, set once connected,
+    /// if the game has a connection of its own.
+    pub node_keys: watch::Sender<Option<([u8; 32], [u8; 32])>>,
+    /// Set during the handshake.
+    pub peer_metadata: watch::Sender<Option<BTreeMap<String, String>>>,
+    /// Set once bound, if the game has an endpoint of its own.
+    pub endpoint: watch::Sender<Option<Endpoint>>,
+    /// Every step the connection went through, for debugging.
+    pub log: Arc<Mutex<Vec<ConnectionEvent>>>,
+}
+
+impl StatusSenders {
+    /// Report that the connection is established.
+    fn connected(&self) {
+        self.connected_at.send_replace(Some(Instant::now()));
+        self.state.send_replace(ConnectionState::Connected);
+        self.log(|at| ConnectionEvent::Connected { at });
+    }
+
+    /// Report that the endpoint was bound.
+    fn bound(&self, endpoint: &Endpoint) {
+        self.endpoint.send_replace(Some(endpoint.clone()));
+        self.log(|at| ConnectionEvent::EndpointCreated { at });
+    }
+
+    /// Report that we connected to the other player's node.
+    fn found(&self, connection: &Connection) -> Result<(), BoxError> {
+        let node_id = *connection.remote_node_id()?.as_bytes();
+        self.log(|at| ConnectionEvent::PeerFound { at, node_id });
+        Ok(())
+    }
+
+    /// Add the event made by `event` for now to the connection log.
+    fn log(&self, event: impl FnOnce(Instant) -> ConnectionEvent) {
+        self.log
+            .lock()
+            .expect(STATE_LOCK)
+            .push(event(Instant::now()));
+    }
+
+    /// Report that the protocol stopped because of `e`.
+    fn stopped(&self, e: BoxError) {
+        report_stopped(&self.state, e);
+        if let ConnectionState::Disconnected(reason) = &*self.state.borrow() {
+            self.log(|at| ConnectionEvent::Disconnected {
+                at,
+                reason: reason.clone(),
+            });
+        }
+    }
+}
+
+/// Report that the protocol stopped because of `e`.
+pub fn report_stopped(state: &watch::Sender<ConnectionState>, e: BoxError) {
+    state.send_replace(ConnectionState::Disconnected(netcode_error(e)));
+}
+
+/// Explain `e`, from dialing the other player over `alpn`.
+///
+/// If they do not speak `alpn`, they run a version of sfn-tpn that speaks
+/// differently, which iroh only reports as a failed TLS handshake.
+pub fn connect_error(e: impl std::error::Error + Send + Sync + 'static, alpn: &[u8]) -> BoxError {
+    let rejected_alpn =
+        std::iter::successors(Some(&e as &(dyn std::error::Error + 'static)), |e| {
+            e.source()
+        })
+        .any(|e| {
+            matches!(
+                e.downcast_ref::<ConnectionError>(),
+                Some(ConnectionError::ConnectionClosed(close))
+                    if close.error_code == TransportErrorCode::crypto(NO_APPLICATION_PROTOCOL)
+            )
+        });
+    if rejected_alpn {
+        NetcodeError::ProtocolMismatch {
+            local: String::from_utf8_lossy(alpn).into_owned(),
+            remote: None,
+        }
+        .into()
+    } else {
+        e.into()
+    }
+}
+
+/// Explain `e`, from shaking hands with the host over `conn`, if the host
+/// refused us because it already has a player.
+fn refusal_error(e: BoxError, conn: &Connection) -> BoxError {
+    match conn.close_reason() {
+        Some(ConnectionError::ApplicationClosed(close)) if close.error_code == GAME_FULL => {
+            NetcodeError::GameFull.into()
+        }
+        _ => e,
+    }
+}
+
+/// Fill in which protocol the host speaks, if `e` is a
+/// [`ProtocolMismatch`](NetcodeError::ProtocolMismatch) and they speak one of
+/// the [`EARLIER_ALPNS`], by dialing them again with each.
+async fn identify_mismatch(mut e: BoxError, endpoint: &Endpoint, host: &NodeAddr) -> BoxError {
+    if let Some(NetcodeError::ProtocolMismatch { remote, .. }) = e.downcast_mut::<NetcodeError>() {
+        for &alpn in EARLIER_ALPNS {
+            if let Ok(conn) = endpoint.connect(host.clone(), alpn).await {
+                // we only wanted to know that they speak it.
+                conn.close(VarInt::from_u32(0), b"protocol mismatch");
+                *remote = Some(String::from_utf8_lossy(alpn).into_owned());
+                break;
+            }
+        }
+    }
+    e
+}
+
+/// The error to report to the game for `e`.
+fn netcode_error(e: BoxError) -> NetcodeError {
+    match e.downcast::<NetcodeError>() {
+        Ok(e) => *e,
+        Err(e) => NetcodeError::Disconnected(e.to_string()),
+    }
+}
 
 /// Starts the pieceboard iroh protocol.
+///
+/// Reports the connection's progress through `status`.
 pub async fn start_iroh_protocol<const SIZE: usize>(
-    send_to_game: Sender<[u8; SIZE]>,
-    mut recv_from_game: Receiver<[u8; SIZE]>,
-    config: Config,
+    channels: GameChannels<SIZE>,
+    transport: Transport,
+    options: ProtocolOptions<SIZE>,
+    status: StatusSenders,
 ) {
-    println!("started iroh protocol in new thread");
-    match config {
-        Config::Ticket(t) => {
-            // we are the client, aka sender, aka player with first move.
+    if let Err(e) = run_iroh_protocol(channels, transport, options, &status).await {
+        status.stopped(e);
+    }
+}
+
+/// Runs the iroh protocol until the game hangs up or something goes wrong.
+async fn run_iroh_protocol<const SIZE: usize>(
+    mut channels: GameChannels<SIZE>,
+    transport: Transport,
+    options: ProtocolOptions<SIZE>,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    if options.check_internet && matches!(transport, Transport::Config(_)) {
+        let target = options
+            .internet_check_target
+            .clone()
+            .unwrap_or_else(|| relay_check_target(options.relay_url.as_ref()));
+        check_internet(&target).await?;
+    }
+    let restored = match &transport {
+        Transport::Config(Config::Restore(snapshot)) => Some(snapshot.as_ref().clone()),
+        _ => None,
+    };
+    let hello = Hello {
+        size: u32::try_from(options.size)?,
+        first_move: options.first_move,
+        simultaneous: options.simultaneous,
+        encoder: options.encoder.as_ref().map(|e| e.name()),
+        turn_sizes: (
+            u32::try_from(options.turn_sizes.0)?,
+            u32::try_from(options.turn_sizes.1)?,
+        ),
+        time_control: options.time_control,
+        session_half: rand::random(),
+        restore: restored.as_ref().map(SessionSnapshot::digest),
+    };
+
+    match transport {
+        Transport::Session(game) => {
+            // the session already connected, and only hands us our stream.
+            let role = game.role();
+            let (mut send, mut recv) = game.open().await?;
+            status.log(|at| ConnectionEvent::StreamOpened { at });
+            handshake(
+                &mut send,
+                &mut recv,
+                &hello,
+                &options.metadata,
+                role,
+                None,
+                status,
+            )
+            .await?;
+            exchange_setup(&mut send, &mut recv, role, options.setup, status).await?;
+            exchange_established_at(&mut send, &mut recv, role, status).await?;
+            status.connected();
+
+            // datagrams cannot tell the session's games apart, and the
+            // session owns the connection, so it cannot be reconnected.
+            play(
+                None,
+                (send, recv),
+                (&mut channels, &Mutex::new(PlayState::new())),
+                role,
+                None,
+                &(options.encoder, options.compression, options.turn_sizes),
+                status,
+            )
+            .await
+        }
+        Transport::Config(Config::Ticket(t)) => {
+            // we are the client.
             // create a client endpoint and connect to a server based on our ticket.
-            let client_endpoint = Endpoint::builder().discovery_n0().bind().await.unwrap();
-            let host_addr = NodeAddr::from(
-                NodeTicket::from_str(&t).expect("The nodeticket could not be parsed"),
+            let client_endpoint = endpoint_builder(&options).discovery_n0().bind().await?;
+            let host_addr = NodeAddr::from(NodeTicket::from_str(&t)?);
+            join_host(
+                channels,
+                client_endpoint,
+                host_addr,
+                &hello,
+                options,
+                None,
+                status,
+            )
+            .await
+        }
+        Transport::Config(Config::Uri(uri)) => {
+            // we are the client, like with a ticket.
+            let host_addr = url::node_addr_from_uri(&uri)?;
+            let client_endpoint = endpoint_builder(&options).discovery_n0().bind().await?;
+            join_host(
+                channels,
+                client_endpoint,
+                host_addr,
+                &hello,
+                options,
+                None,
+                status,
+            )
+            .await
+        }
+        Transport::Config(Config::TicketSender(sender)) => {
+            // we are the host.
+            host(
+                channels,
+                endpoint_builder(&options),
+                Some(sender),
+                &hello,
+                options,
+                None,
+                status,
+            )
+            .await
+        }
+        Transport::Config(Config::Restore(snapshot)) => {
+            if options.simultaneous {
+                return Err(NetcodeError::HandshakeFailed(
+                    "simultaneous rounds cannot be restored".to_string(),
+                )
+                .into());
+            }
+            // binding with the same secret key as before gives us the same
+            // node ID, so the host can be found again, and the client
+            // recognized.
+            let builder = endpoint_builder(&options).secret_key(snapshot.checked_secret_key()?);
+            match snapshot.role {
+                Role::Client => {
+                    let host_addr = NodeAddr::new(NodeId::from_bytes(&snapshot.peer)?);
+                    let client_endpoint = builder.discovery_n0().bind().await?;
+                    join_host(
+                        channels,
+                        client_endpoint,
+                        host_addr,
+                        &hello,
+                        options,
+                        restored.as_ref(),
+                        status,
+                    )
+                    .await
+                }
+                Role::Host => {
+                    host(
+                        channels,
+                        builder,
+                        None,
+                        &hello,
+                        options,
+                        restored.as_ref(),
+                        status,
+                    )
+                    .await
+                }
+            }
+        }
+    }
+}
+
+/// Start building an endpoint that uses the relay in `options`, if any.
+fn endpoint_builder<const SIZE: usize>(options: &ProtocolOptions<SIZE>) -> iroh::endpoint::Builder {
+    let builder = Endpoint::builder();
+    match &options.relay_url {
+        Some(url) => builder.relay_mode(RelayMode::Custom(RelayMap::from(url.clone()))),
+        None => builder,
+    }
+}
+
+/// Return the `host:port` of the relay we would use, which iroh contacts
+/// anyway, so checking it does not reach out to anyone else.
+///
+/// Without a relay of our own, that is n0's North American relay, the first
+/// of its public ones.
+fn relay_check_target(relay_url: Option<&RelayUrl>) -> String {
+    relay_url
+        .and_then(|url| {
+            Some(format!(
+                "{}:{}",
+                url.host_str()?,
+                url.port_or_known_default()?
+            ))
+        })
+        .unwrap_or_else(|| {
+            format!(
+                "{}:{}",
+                iroh::defaults::prod::NA_RELAY_HOSTNAME,
+                iroh::defaults::DEFAULT_HTTPS_PORT
+            )
+        })
+}
+
+/// Make sure we can reach the internet, so that looking for the other player
+/// does not hang forever.
+async fn check_internet(target: &str) -> Result<(), BoxError> {
+    match time::timeout(INTERNET_CHECK_TIMEOUT, TcpStream::connect(target)).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(_)) | Err(_) => Err(NetcodeError::NoInternet.into()),
+    }
+}
+
+/// Client side: connect to the host, then play, dialing the host again if
+/// the connection drops.
+async fn join_host<const SIZE: usize>(
+    mut channels: GameChannels<SIZE>,
+    client_endpoint: Endpoint,
+    host_addr: NodeAddr,
+    hello: &Hello,
+    options: ProtocolOptions<SIZE>,
+    restored: Option<&SessionSnapshot>,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    status.bound(&client_endpoint);
+    status.log(|at| ConnectionEvent::DiscoveryStarted { at });
+    let conn = match client_endpoint.connect(host_addr.clone(), ALPN).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            let e = connect_error(e, ALPN);
+            return Err(identify_mismatch(e, &client_endpoint, &host_addr).await);
+        }
+    };
+    let shaken = async {
+        status.found(&conn)?;
+        let (mut send, mut recv) = conn.open_bi().await?;
+        status.log(|at| ConnectionEvent::StreamOpened { at });
+
+        handshake(
+            &mut send,
+            &mut recv,
+            hello,
+            &options.metadata,
+            Role::Client,
+            restored,
+            status,
+        )
+        .await?;
+        exchange_setup(&mut send, &mut recv, Role::Client, None, status).await?;
+        exchange_established_at(&mut send, &mut recv, Role::Client, status).await?;
+        Ok::<_, BoxError>((send, recv))
+    };
+    let (send, recv) = shaken.await.map_err(|e| refusal_error(e, &conn))?;
+    status.node_keys.send_replace(Some((
+        client_endpoint.secret_key().to_bytes(),
+        *conn.remote_node_id()?.as_bytes(),
+    )));
+    status.connected();
+
+    let encoding = (options.encoder, options.compression, options.turn_sizes);
+    let state = Mutex::new(PlayState::new());
+    let (mut connection, mut streams) = (conn, (send, recv));
+    loop {
+        let e = match play(
+            Some(&connection),
+            streams,
+            (&mut channels, &state),
+            Role::Client,
+            None,
+            &encoding,
+            status,
+        )
+        .await
+        {
+            Err(e) if dropped(&e, &connection) => e,
+            result => return result,
+        };
+        (connection, streams) = reconnect(
+            options.reconnect,
+            Rejoin::Dial {
+                endpoint: &client_endpoint,
+                host: &host_addr,
+            },
+            e,
+            (&mut channels.recv_reconnect_from_game, &state),
+            status,
+        )
+        .await?;
+    }
+}
+
+/// Host side: wait for the client, then play, letting them back in if the
+/// connection drops.
+///
+/// Sends a new ticket through `ticket_sender`, if any. When `restored`, only
+/// the player from the snapshot may connect.
+async fn host<const SIZE: usize>(
+    mut channels: GameChannels<SIZE>,
+    builder: iroh::endpoint::Builder,
+    ticket_sender: Option<oneshot::Sender<String>>,
+    hello: &Hello,
+    options: ProtocolOptions<SIZE>,
+    restored: Option<&SessionSnapshot>,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    let mut alpns = vec![ALPN.to_vec()];
+    if options.max_spectators.is_some() {
+        alpns.push(SPECTATOR_ALPN.to_vec());
+    }
+    let host_endpoint = builder.discovery_n0().alpns(alpns).bind().await?;
+    status.bound(&host_endpoint);
+
+    // send our user the ticket string
+    let ticket = NodeTicket::new(host_endpoint.node_addr().initialized().await?);
+    if options.max_spectators.is_some() {
+        status
+            .spectator_ticket
+            .send_replace(Some(SpectatorTicket::new(&ticket)));
+    }
+    if let Some(sender) = ticket_sender {
+        sender
+            .send(ticket.to_string())
+            .map_err(|_| "the ticket receiver was dropped")?;
+    }
+    status.log(|at| ConnectionEvent::DiscoveryStarted { at });
+
+    let spectators = options.max_spectators.map(|max| Spectators {
+        permits: Arc::new(Semaphore::new(max.into())),
+        turns: broadcast::channel(SPECTATOR_BACKLOG).0,
+        hello: hello.clone(),
+    });
+    let broadcast = spectators.as_ref().map(|s| s.turns.clone());
+    let peer = restored
+        .map(|snapshot| NodeId::from_bytes(&snapshot.peer))
+        .transpose()?;
+    let (player_sender, mut players) = mpsc::unbounded_channel();
+    let accept_task = task::spawn(accept_connections(
+        host_endpoint.clone(),
+        player_sender,
+        (peer, options.reconnect != Reconnect::Disabled),
+        spectators,
+    ));
+
+    let result = async {
+        let connection = players
+            .recv()
+            .await
+            .ok_or("the endpoint closed before anyone connected")?;
+        let node_id = connection.remote_node_id()?;
+        status.found(&connection)?;
+        let (mut send, mut recv) = connection.accept_bi().await?;
+        status.log(|at| ConnectionEvent::StreamOpened { at });
+        handshake(
+            &mut send,
+            &mut recv,
+            hello,
+            &options.metadata,
+            Role::Host,
+            restored,
+            status,
+        )
+        .await?;
+        exchange_setup(&mut send, &mut recv, Role::Host, options.setup, status).await?;
+        exchange_established_at(&mut send, &mut recv, Role::Host, status).await?;
+        status.node_keys.send_replace(Some((
+            host_endpoint.secret_key().to_bytes(),
+            *node_id.as_bytes(),
+        )));
+        status.connected();
+
+        let encoding = (options.encoder, options.compression, options.turn_sizes);
+        let state = Mutex::new(PlayState::new());
+        let (mut connection, mut streams) = (connection, (send, recv));
+        loop {
+            let (e, came_back) = tokio::select! {
+                result = play(
+                    Some(&connection),
+                    streams,
+                    (&mut channels, &state),
+                    Role::Host,
+                    broadcast.clone(),
+                    &encoding,
+                    status,
+                ) => match result {
+                    Err(e) if dropped(&e, &connection) => (e, None),
+                    result => return result,
+                },
+                // the connection we have is gone, even if we have yet to
+                // notice.
+                Some(connection) = players.recv() => {
+                    ("the client reconnected".into(), Some(connection))
+                }
+            };
+            (connection, streams) = reconnect(
+                options.reconnect,
+                Rejoin::Accept {
+                    players: &mut players,
+                    came_back,
+                },
+                e,
+                (&mut channels.recv_reconnect_from_game, &state),
+                status,
+            )
+            .await?;
+        }
+    }
+    .await;
+    accept_task.abort();
+    result
+}
+
+/// Everything the host needs to serve spectators.
+pub struct Spectators<const SIZE: usize> {
+    /// One permit per spectator that may be connected at once.
+    permits: Arc<Semaphore>,
+    /// Where the host publishes every turn exchanged.
+    turns: broadcast::Sender<(Role, [u8; SIZE])>,
+    hello: Hello,
+}
+
+/// Host side: accept incoming connections until the endpoint closes.
+///
+/// The first player to connect is handed to `player`, and only they are ever
+/// given the game's channels, so a second player cannot interleave turns with
+/// the first. If `peer` is given, only that node may be the player. If
+/// `reconnect`, later connections from the player's node are handed over
+/// too, since they are the player coming back. Any other player is closed
+/// with [`GAME_FULL`], which they report as [`NetcodeError::GameFull`].
+/// Spectators, if allowed, are served in their own tasks.
+pub async fn accept_connections<const SIZE: usize>(
+    endpoint: Endpoint,
+    player: UnboundedSender<Connection>,
+    (peer, reconnect): (Option<NodeId>, bool),
+    spectators: Option<Spectators<SIZE>>,
+) {
+    let mut player_id: Option<NodeId> = None;
+    while let Some(incoming) = endpoint.accept().await {
+        let connection = async {
+            let mut accepting = incoming.accept()?;
+            let alpn = accepting.alpn().await?;
+            let connection = accepting.await?;
+            Ok::<_, BoxError>((alpn, connection))
+        }
+        .await;
+        let (alpn, connection) = match connection {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::debug!("failed to accept a connection: {e}");
+                continue;
+            }
+        };
+
+        if alpn == SPECTATOR_ALPN {
+            let Some(spectators) = &spectators else {
+                continue;
+            };
+            let Ok(permit) = spectators.permits.clone().try_acquire_owned() else {
+                tracing::debug!("refused a spectator because we are full");
+                continue;
+            };
+            task::spawn(spectator::serve_spectator(
+                connection,
+                spectators.hello.clone(),
+                spectators.turns.subscribe(),
+                permit,
+            ));
+        } else if peer.is_some_and(|peer| connection.remote_node_id().ok() != Some(peer)) {
+            tracing::debug!("refused a player who is not in the restored session");
+            connection.close(
+                NOT_THE_PLAYER,
+                b"the game is being restored with someone else",
             );
+        } else if player_id.is_none() {
+            player_id = connection.remote_node_id().ok();
+            let _ = player.send(connection);
+        } else if reconnect && connection.remote_node_id().ok() == player_id {
+            let _ = player.send(connection);
+        } else {
+            // only one player can ever connect, so tell anyone else why
+            // rather than leaving them to guess.
+            tracing::debug!("refused a player because the game is full");
+            connection.close(GAME_FULL, b"the game already has two players");
+        }
+    }
+}
 
-            println!("trying to connect to host...");
-            let conn = client_endpoint.connect(host_addr, ALPN).await.unwrap();
-            let (mut send, mut recv) = conn.open_bi().await.unwrap();
+/// Shake hands with the other player over a freshly opened bi-stream.
+///
+/// Reports the session ID, who moves first, the shared seed, and the other
+/// player's metadata through `status`. If `restored`, the session ID and seed
+/// are kept from the snapshot instead.
+async fn handshake(
+    send: &mut SendStream,
+    recv: &mut RecvStream,
+    hello: &Hello,
+    metadata: &Metadata,
+    role: Role,
+    restored: Option<&SessionSnapshot>,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    let theirs = match role {
+        Role::Client => {
+            // the host can only accept the stream once we write to it, so we say hello first.
+            Frame::Hello(hello.clone()).write(send).await?;
+            Frame::read(recv).await?
+        }
+        Role::Host => {
+            // reply with our own hello before checking theirs, so that the
+            // client can also tell what went wrong.
+            let theirs = Frame::read(recv).await?;
+            Frame::Hello(hello.clone()).write(send).await?;
+            theirs
+        }
+    };
+    let theirs = match check_hello(hello, theirs) {
+        Ok(theirs) => theirs,
+        Err(e) => return Err(refuse(send, e).await),
+    };
+    // XOR is symmetric, so both players get the same ID.
+    let session_id = match restored {
+        Some(snapshot) => snapshot.session_id,
+        None => std::array::from_fn(|i| hello.session_half[i] ^ theirs.session_half[i]),
+    };
+    status.session_id.send_replace(Some(session_id));
+
+    Frame::Metadata(metadata.clone()).write(send).await?;
+    let Frame::Metadata(their_metadata) = Frame::read(recv).await? else {
+        return Err("the other player did not send their metadata".into());
+    };
+    if let Err(e) = metadata.check(&their_metadata) {
+        return Err(refuse(send, e.into()).await);
+    }
+    status
+        .peer_metadata
+        .send_replace(Some(their_metadata.all()));
 
-            println!("client opened bi-stream");
+    // the players still agree on a new seed, so the handshake goes the same
+    // way, but keep the one the snapshot's turns were played with.
+    let seed = agree_on_seed(send, recv, role).await?;
+    let seed = restored.map_or(seed, |snapshot| snapshot.seed);
+    let first_move = match hello.first_move {
+        Some(first_move) => first_move,
+        // flip a coin with the seed.
+        None if seed[0] & 1 == 0 => Role::Host,
+        None => Role::Client,
+    };
+    status.first_move.send_replace(Some(first_move));
+    status.seed.send_replace(Some(seed));
+    Ok(())
+}
 
+/// Send the host's setup to the client, before any turns are exchanged.
+///
+/// Only the host's `setup` is used. Reports the setup through `status`.
+async fn exchange_setup(
+    send: &mut SendStream,
+    recv: &mut RecvStream,
+    role: Role,
+    setup: Option<Vec<u8>>,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    let setup = match role {
+        Role::Host => {
+            Frame::Setup(setup.clone()).write(send).await?;
+            setup
+        }
+        Role::Client => {
+            let Frame::Setup(setup) = Frame::read(recv).await? else {
+                return Err("the host did not send their setup".into());
+            };
+            setup
+        }
+    };
+    status.setup.send_replace(setup);
+    Ok(())
+}
+
+/// Agree on when the connection was established, by the client's wall clock,
+/// so both players log the same time. Reports it through `status`.
+async fn exchange_established_at(
+    send: &mut SendStream,
+    recv: &mut RecvStream,
+    role: Role,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    let millis = match role {
+        Role::Client => {
+            let millis = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let millis = u64::try_from(millis)?;
+            Frame::EstablishedAt(millis).write(send).await?;
+            millis
+        }
+        Role::Host => {
+            let Frame::EstablishedAt(millis) = Frame::read(recv).await? else {
+                return Err("the client did not send when the connection was established".into());
+            };
+            millis
+        }
+    };
+    status
+        .established_at
+        .send_replace(Some(SystemTime::UNIX_EPOCH + Duration::from_millis(millis)));
+    Ok(())
+}
+
+/// Give what we wrote during the handshake a moment to reach the other
+/// player before the connection is closed because of `e`, so they can tell
+/// what went wrong too, rather than only seeing the connection drop.
+async fn refuse(send: &mut SendStream, e: BoxError) -> BoxError {
+    if send.finish().is_ok() {
+        let _ = time::timeout(GOODBYE_TIMEOUT, send.stopped()).await;
+    }
+    e
+}
+
+/// Make sure the other player's hello agrees with ours, and return it.
+fn check_hello(ours: &Hello, theirs: Frame) -> Result<Hello, BoxError> {
+    let Frame::Hello(theirs) = theirs else {
+        return Err("the other player did not say hello".into());
+    };
+
+    if ours.size != theirs.size {
+        return Err(NetcodeError::HandshakeFailed(format!(
+            "our turns are {} bytes, but the other player's are {} bytes",
+            ours.size, theirs.size
+        ))
+        .into());
+    }
+    if ours.turn_sizes != (theirs.turn_sizes.1, theirs.turn_sizes.0) {
+        return Err(NetcodeError::HandshakeFailed(format!(
+            "we send {}-byte turns and receive {}-byte turns, but the other player sends {}-byte turns and receives {}-byte turns",
+            ours.turn_sizes.0, ours.turn_sizes.1, theirs.turn_sizes.0, theirs.turn_sizes.1
+        ))
+        .into());
+    }
+    if ours.restore != theirs.restore {
+        return Err(NetcodeError::SnapshotMismatch.into());
+    }
+    if ours.simultaneous != theirs.simultaneous {
+        let describe = |simultaneous| match simultaneous {
+            true => "submit turns in simultaneous rounds",
+            false => "alternate turns",
+        };
+        return Err(NetcodeError::HandshakeFailed(format!(
+            "we {}, but the other player expects to {}",
+            describe(ours.simultaneous),
+            describe(theirs.simultaneous)
+        ))
+        .into());
+    }
+    if ours.encoder != theirs.encoder {
+        let describe = |encoder: &Option<String>| match encoder {
+            Some(name) => format!("encode turns with {name}"),
+            None => "send turns as-is".to_string(),
+        };
+        return Err(NetcodeError::HandshakeFailed(format!(
+            "we {}, but the other player expects to {}",
+            describe(&ours.encoder),
+            describe(&theirs.encoder)
+        ))
+        .into());
+    }
+    if ours.first_move != theirs.first_move {
+        let describe = |first_move| match first_move {
+            Some(role) => format!("the {role} moves first"),
+            None => "a coin flip decides who moves first".to_string(),
+        };
+        return Err(NetcodeError::HandshakeFailed(format!(
+            "we expect that {}, but the other player expects that {}",
+            describe(ours.first_move),
+            describe(theirs.first_move)
+        ))
+        .into());
+    }
+    if ours.time_control != theirs.time_control {
+        let describe = |time_control| match time_control {
+            Some(TimeControl::Increment { initial, increment }) => {
+                format!("play with {initial:?} plus {increment:?} a move")
+            }
+            Some(TimeControl::Delay { initial, delay }) => {
+                format!("play with {initial:?} and a {delay:?} delay")
+            }
+            None => "play without clocks".to_string(),
+        };
+        return Err(NetcodeError::HandshakeFailed(format!(
+            "we {}, but the other player expects to {}",
+            describe(ours.time_control),
+            describe(theirs.time_control)
+        ))
+        .into());
+    }
+    Ok(theirs)
+}
+
+/// Jointly pick a random seed with the other player.
+///
+/// Each player commits to a random nonce by sending its hash, and only reveals
+/// the nonce after receiving the other player's commitment. So, neither player
+/// can pick their nonce after seeing the other's.
+async fn agree_on_seed(
+    send: &mut SendStream,
+    recv: &mut RecvStream,
+    role: Role,
+) -> Result<[u8; 32], BoxError> {
+    let nonce: [u8; 32] = rand::random();
+    Frame::Commit(Sha256::digest(nonce).into())
+        .write(send)
+        .await?;
+    let Frame::Commit(their_commitment) = Frame::read(recv).await? else {
+        return Err("the other player did not commit to a seed".into());
+    };
+
+    Frame::Reveal(nonce).write(send).await?;
+    let Frame::Reveal(their_nonce) = Frame::read(recv).await? else {
+        return Err("the other player did not reveal their seed".into());
+    };
+    if <[u8; 32]>::from(Sha256::digest(their_nonce)) != their_commitment {
+        return Err(NetcodeError::HandshakeFailed(
+            "the other player's seed did not match their commitment".to_string(),
+        )
+        .into());
+    }
+
+    // hash the nonces in the same order on both sides, so both players get
+    // the same seed.
+    let (host_nonce, client_nonce) = match role {
+        Role::Host => (nonce, their_nonce),
+        Role::Client => (their_nonce, nonce),
+    };
+    Ok(Sha256::new()
+        .chain_update(host_nonce)
+        .chain_update(client_nonce)
+        .finalize()
+        .into())
+}
+
+/// How turns are encoded: the diff encoder, the compression, and how many
+/// bytes of each turn we send, then receive.
+type TurnEncoding<const SIZE: usize> = (
+    Option<Box<dyn DiffEncoder<SIZE>>>,
+    Option<Compression>,
+    (usize, usize),
+);
+
+/// What a game keeps across reconnections, so it picks up where the
+/// connection dropped.
+struct PlayState<const SIZE: usize> {
+    /// The sequence number of the last move we sent.
+    sent: u64,
+    /// The sequence number of the last move we received.
+    received: u64,
+    /// Our moves the other player has yet to acknowledge, with their
+    /// sequence numbers, as sent, to be sent again after reconnecting.
+    unacked: VecDeque<(u64, Vec<u8>)>,
+    /// Turns alternate, so both players see the same sequence of turns, and
+    /// agree on which one came before.
+    prev: [u8; SIZE],
+}
+
+impl<const SIZE: usize> PlayState<SIZE> {
+    fn new() -> Self {
+        Self {
+            sent: 0,
+            received: 0,
+            unacked: VecDeque::new(),
+            prev: [0; SIZE],
+        }
+    }
+}
+
+/// Exchange frames with the other player until the game hangs up.
+///
+/// Turns and control frames are read and written concurrently. The interface
+/// makes sure turns alternate. Activity pings go over `connection`, if any,
+/// as datagrams alongside the stream. Every turn exchanged is also published to
+/// `spectators`, if any.
+///
+/// Turns are encoded with `encoder`, if any, relative to the previous turn
+/// exchanged. Only the first `send_size` bytes of our turns are sent, and the
+/// other player's are zero-padded from `recv_size` bytes. Moves are
+/// compressed with `compression`, if any. Reports how many bytes are waiting
+/// on the stream through `status`.
+async fn play<const SIZE: usize>(
+    connection: Option<&Connection>,
+    (mut send, mut recv): (SendStream, RecvStream),
+    (channels, state): (&mut GameChannels<SIZE>, &Mutex<PlayState<SIZE>>),
+    role: Role,
+    spectators: Option<broadcast::Sender<(Role, [u8; SIZE])>>,
+    &(ref encoder, compression, (send_size, recv_size)): &TurnEncoding<SIZE>,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    let GameChannels {
+        send_to_game,
+        recv_from_game,
+        send_control_to_game,
+        recv_control_from_game,
+        send_activity_to_game,
+        recv_activity_from_game,
+        recv_reconnect_from_game: _,
+        turn_notify,
+    } = channels;
+    // acks are read and written by different halves of this function.
+    let (send_ack, mut recv_ack) = mpsc::unbounded_channel::<Frame>();
+    let publish = |exchanged| {
+        if let Some(spectators) = &spectators {
+            // nobody may be watching, which is fine.
+            let _ = spectators.send(exchanged);
+        }
+    };
+
+    // the rest of each turn is never sent, so it is zeroed for everyone.
+    let truncate = |mut turn: [u8; SIZE]| {
+        turn[send_size..].fill(0);
+        turn
+    };
+    let encode = |turn: &[u8; SIZE]| {
+        let mut state = state.lock().expect(STATE_LOCK);
+        let encoded = match encoder {
+            Some(encoder) => encoder.encode(&state.prev, turn),
+            None => turn[..send_size].to_vec(),
+        };
+        state.prev = *turn;
+        encoded
+    };
+    let decode = |encoded: Vec<u8>| {
+        let mut state = state.lock().expect(STATE_LOCK);
+        let turn = match encoder {
+            Some(encoder) => encoder
+                .decode(&state.prev, &encoded)
+                .ok_or("the other player sent a malformed diff")?,
+            None if encoded.len() == recv_size => {
+                let mut turn = [0; SIZE];
+                turn[..recv_size].copy_from_slice(&encoded);
+                turn
+            }
+            None => return Err("the other player sent a turn of the wrong size".into()),
+        };
+        state.prev = turn;
+        Ok::<_, BoxError>(turn)
+    };
+
+    // the stream only accepts bytes as fast as the connection allows, so
+    // anything it has yet to accept is stuck behind congestion.
+    let mut write_bytes = async |bytes: &[u8]| {
+        status.bytes_in_flight.send_replace(bytes.len());
+        send.write_all(bytes).await?;
+        status.bytes_in_flight.send_replace(0);
+        Ok::<_, BoxError>(())
+    };
+    // each player numbers their own moves, so a move that is dropped or
+    // repeated is caught before it is delivered into the wrong ply.
+    let write = async {
+        // the rest of a move too large to send at once, sent a chunk at a
+        // time, so everything else can be sent in between.
+        let mut chunks = VecDeque::new();
+        // the sequence number of the move being sent in chunks.
+        let mut chunked = 0;
+        // set once the game says goodbye, which is only written after the
+        // moves it sent before.
+        let mut leaving = false;
+        loop {
+            tokio::select! {
+                // the interface says goodbye as it is dropped, so we must
+                // see it before noticing that the game hung up.
+                biased;
+                Some(control) = recv_control_from_game.recv(), if !leaving => {
+                    if control == ControlFrame::Disconnect {
+                        leaving = true;
+                    } else {
+                        write_bytes(&Frame::Control(control).encode()?).await?;
+                    }
+                }
+                Some(ack) = recv_ack.recv() => {
+                    write_bytes(&ack.encode()?).await?;
+                }
+                Some(()) = recv_activity_from_game.recv() => {
+                    // pings are unreliable anyway, so one that cannot be
+                    // sent is dropped.
+                    if let Some(connection) = connection {
+                        let _ = connection.send_datagram(ACTIVITY_DATAGRAM.to_vec().into());
+                    }
+                }
+                () = std::future::ready(()), if !chunks.is_empty() => {
+                    let bytes: Vec<u8> = chunks.pop_front().expect("there are chunks left");
+                    let len = bytes.len() as u64;
+                    let chunk = Frame::Chunk {
+                        last: chunks.is_empty(),
+                        bytes,
+                    };
+                    write_bytes(&chunk.encode()?).await?;
+                    status.outgoing_transfer.send_modify(|progress| {
+                        if let Some(progress) = progress {
+                            progress.done += len;
+                        }
+                    });
+                    if chunks.is_empty() {
+                        status.written.send_replace(chunked);
+                    }
+                }
+                () = std::future::ready(()), if leaving && chunks.is_empty() && recv_from_game.is_empty() => {
+                    write_bytes(&Frame::Control(ControlFrame::Disconnect).encode()?).await?;
+                    return Ok(());
+                }
+                // moves are sent in order, so the next waits for the chunks.
+                turns = recv_from_game.recv(), if chunks.is_empty() => {
+                    // Send the data the game wants to send
+                    let Some(Move {
+                        game,
+                        state_hash,
+                        clock,
+                        turns,
+                    }) = turns
+                    else {
+                        // the game hung up
+                        return Ok(());
+                    };
+                    let turns: Vec<_> = turns.into_iter().map(truncate).collect();
+                    let encoded: Vec<_> = turns.iter().map(encode).collect();
+                    let bytes = encoded.iter().map(Vec::len).sum::<usize>() as u64;
+                    let seq = {
+                        let mut state = state.lock().expect(STATE_LOCK);
+                        state.sent += 1;
+                        state.sent
+                    };
+                    let frame = Frame::Turns {
+                        seq,
+                        game,
+                        clock: clock.map(|clock| u64::try_from(clock.as_millis())).transpose()?,
+                        state_hash,
+                        turns: encoded,
+                    }
+                    .encode()?;
+                    let compressed = match &compression {
+                        Some(compression) => compression.compress(&frame)?,
+                        None => None,
+                    };
+                    let frame = match compressed {
+                        Some(compressed) => {
+                            status.compressed_bytes.send_modify(|(before, after)| {
+                                *before += frame.len() as u64;
+                                *after += compressed.len() as u64;
+                            });
+                            compressed
+                        }
+                        None => frame,
+                    };
+                    // kept until acknowledged, to be sent again if the
+                    // connection drops first.
+                    state
+                        .lock()
+                        .expect(STATE_LOCK)
+                        .unacked
+                        .push_back((seq, frame.clone()));
+                    if frame.len() <= CHUNK_LEN {
+                        write_bytes(&frame).await?;
+                        status.written.send_replace(seq);
+                    } else {
+                        chunked = seq;
+                        status.outgoing_transfer.send_replace(Some(TransferProgress {
+                            done: 0,
+                            total: frame.len() as u64,
+                        }));
+                        chunks = transfer::split(&frame);
+                    }
+                    status.turn_bytes.send_modify(|(sent, _)| *sent += bytes);
+                    for turn in turns {
+                        publish((role, turn));
+                    }
+                }
+            }
+        }
+    };
+    let read = async {
+        let mut reassembly = Reassembly::default();
+        loop {
+            let frame = match Frame::read(&mut recv).await? {
+                Frame::Chunk { last, bytes } => {
+                    let frame = reassembly.push(last, &bytes)?;
+                    status.incoming_transfer.send_replace(reassembly.progress());
+                    match frame {
+                        Some(frame) => frame,
+                        None => continue,
+                    }
+                }
+                frame => frame,
+            };
+            let frame = match frame {
+                // only sent if both players agreed on a compression.
+                Frame::Compressed(bytes) if compression.is_some() => {
+                    let frame = compression::decompress(&bytes)?;
+                    let before = frame.encode()?.len() as u64;
+                    status
+                        .compressed_bytes
+                        .send_modify(|(uncompressed, compressed)| {
+                            *uncompressed += before;
+                            *compressed += (HEADER_LEN + bytes.len()) as u64;
+                        });
+                    frame
+                }
+                frame => frame,
+            };
+            match frame {
+                Frame::Turns {
+                    seq,
+                    game,
+                    clock,
+                    state_hash,
+                    turns,
+                } => {
+                    let expected = state.lock().expect(STATE_LOCK).received + 1;
+                    if seq != expected {
+                        return Err(NetcodeError::Desync { expected, got: seq }.into());
+                    }
+                    state.lock().expect(STATE_LOCK).received = seq;
+                    if turns.is_empty() {
+                        return Err("the other player sent a move without turns".into());
+                    }
+                    let bytes = turns.iter().map(Vec::len).sum::<usize>() as u64;
+                    status
+                        .turn_bytes
+                        .send_modify(|(_, received)| *received += bytes);
+                    let turns = turns
+                        .into_iter()
+                        .map(decode)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    for &turn in &turns {
+                        publish((role.other(), turn));
+                    }
+                    // the game may have stopped listening, which is fine.
+                    let _ = send_to_game.send((
+                        Instant::now(),
+                        Move {
+                            game,
+                            state_hash,
+                            clock: clock.map(Duration::from_millis),
+                            turns,
+                        },
+                    ));
+                    turn_notify.notify_one();
+                    // acks are written alongside everything else we send.
+                    let _ = send_ack.send(Frame::Ack(seq));
+                }
+                Frame::Ack(seq) => {
+                    status.acked.send_replace(seq);
+                    state
+                        .lock()
+                        .expect(STATE_LOCK)
+                        .unacked
+                        .retain(|&(sent, _)| sent > seq);
+                }
+                Frame::Control(ControlFrame::Disconnect) => {
+                    return Err(NetcodeError::PeerClosed.into());
+                }
+                Frame::Control(ControlFrame::Finish(result)) => {
+                    // acknowledged right away, so the other player can close
+                    // the connection even if our game stopped listening.
+                    let _ = send_ack.send(Frame::Control(ControlFrame::FinishAck));
+                    let _ = send_control_to_game.send(ControlFrame::Finish(result));
+                    turn_notify.notify_one();
+                }
+                Frame::Control(control) => {
+                    // the game may have stopped listening, which is fine.
+                    let _ = send_control_to_game.send(control);
+                    // a pass or takeback may have handed the turn over.
+                    turn_notify.notify_one();
+                }
+                _ => return Err("the other player sent an unexpected frame".into()),
+            }
+        }
+    };
+
+    let datagrams = async {
+        // once the connection is lost, the stream reports it.
+        while let Some(connection) = connection
+            && let Ok(datagram) = connection.read_datagram().await
+        {
+            if *datagram == *ACTIVITY_DATAGRAM {
+                // the game may have stopped listening, which is fine.
+                let _ = send_activity_to_game.send(Instant::now());
+            }
+        }
+        std::future::pending().await
+    };
+    let sample_rtt = async {
+        while let Some(connection) = connection {
+            status.rtt.send_replace(Some(connection.rtt()));
+            time::sleep(RTT_SAMPLE_INTERVAL).await;
+        }
+        std::future::pending().await
+    };
+
+    let result = tokio::select! {
+        result = write => result,
+        result = read => result,
+        result = datagrams => result,
+        result = sample_rtt => result,
+    };
+    // give everything we wrote, like a goodbye, a moment to reach the other
+    // player before the connection is closed.
+    if send.finish().is_ok() {
+        let _ = time::timeout(GOODBYE_TIMEOUT, send.stopped()).await;
+    }
+    result
+}
+
+/// Whether `e` stopped the game because the connection dropped, like when
+/// either player's network went away, rather than because either player
+/// closed it, so it is worth reconnecting.
+fn dropped(e: &BoxError, connection: &Connection) -> bool {
+    !e.is::<NetcodeError>()
+        && matches!(
+            connection.close_reason(),
+            Some(ConnectionError::TimedOut | ConnectionError::Reset)
+        )
+}
+
+/// A connection to the other player, and the bi-stream the game is played
+/// over.
+type Reconnected = (Connection, (SendStream, RecvStream));
+
+/// How to find the other player again after the connection dropped.
+enum Rejoin<'a> {
+    /// Dial the host again, as the client.
+    Dial {
+        endpoint: &'a Endpoint,
+        host: &'a NodeAddr,
+    },
+    /// Wait for the client to dial us again, as the host, unless they
+    /// already `came_back`.
+    Accept {
+        players: &'a mut UnboundedReceiver<Connection>,
+        came_back: Option<Connection>,
+    },
+}
+
+impl Rejoin<'_> {
+    /// Find the other player once, waiting at most `wait` for them, or as
+    /// long as it takes if `None`, then resume the game.
+    async fn attempt<const SIZE: usize>(
+        &mut self,
+        wait: Option<Duration>,
+        state: &Mutex<PlayState<SIZE>>,
+        status: &StatusSenders,
+    ) -> Result<Reconnected, BoxError> {
+        let (connection, (mut send, mut recv)) = match self {
+            Rejoin::Dial { endpoint, host } => {
+                if let Some(wait) = wait {
+                    time::sleep(wait).await;
+                }
+                let connection =
+                    time::timeout(RECONNECT_TIMEOUT, endpoint.connect((*host).clone(), ALPN))
+                        .await??;
+                status.found(&connection)?;
+                let streams = time::timeout(RECONNECT_TIMEOUT, connection.open_bi()).await??;
+                (connection, streams)
+            }
+            Rejoin::Accept { players, came_back } => {
+                let connection = match came_back.take() {
+                    Some(connection) => Some(connection),
+                    None => match wait {
+                        Some(wait) => time::timeout(wait, players.recv()).await?,
+                        None => players.recv().await,
+                    },
+                }
+                .ok_or("the endpoint closed")?;
+                status.found(&connection)?;
+                let streams = time::timeout(RECONNECT_TIMEOUT, connection.accept_bi()).await??;
+                (connection, streams)
+            }
+        };
+        status.log(|at| ConnectionEvent::StreamOpened { at });
+        time::timeout(
+            RECONNECT_TIMEOUT,
+            resume(&mut send, &mut recv, state, status),
+        )
+        .await??;
+        status.connected();
+        Ok((connection, (send, recv)))
+    }
+}
+
+/// Reconnect to the other player after the connection dropped with
+/// `dropped`, following `strategy`, then resume the game.
+///
+/// Only the client dials, so the host lets them back in without waiting for
+/// the game to ask.
+async fn reconnect<const SIZE: usize>(
+    strategy: Reconnect,
+    mut rejoin: Rejoin<'_>,
+    dropped: BoxError,
+    (requests, state): (&mut UnboundedReceiver<()>, &Mutex<PlayState<SIZE>>),
+    status: &StatusSenders,
+) -> Result<Reconnected, BoxError> {
+    if strategy != Reconnect::Disabled {
+        status.log(|at| ConnectionEvent::Disconnected {
+            at,
+            reason: NetcodeError::Disconnected(dropped.to_string()),
+        });
+    }
+    match strategy {
+        Reconnect::Disabled => Err(dropped),
+        Reconnect::Automatic {
+            max_attempts,
+            backoff,
+        } => {
+            status.state.send_replace(ConnectionState::Connecting);
+            let mut last = dropped;
+            let mut wait = backoff;
+            for _ in 0..max_attempts {
+                match rejoin.attempt(Some(wait), state, status).await {
+                    Ok(reconnected) => return Ok(reconnected),
+                    Err(e) => last = e,
+                }
+                wait = wait.saturating_mul(2);
+            }
+            Err(last)
+        }
+        Reconnect::Manual => {
+            let mut last = dropped;
             loop {
-                // Send the data the game wants to send
-                send.write_all(&recv_from_game.recv().await.unwrap())
-                    .await
-                    .unwrap();
+                let e = netcode_error(last);
+                status
+                    .state
+                    .send_replace(ConnectionState::Disconnected(e.clone()));
+                // asking before the connection dropped does not count.
+                while requests.try_recv().is_ok() {}
+                let reconnected = if let Rejoin::Dial { .. } = rejoin {
+                    if requests.recv().await.is_none() {
+                        return Err(e.into());
+                    }
+                    status.state.send_replace(ConnectionState::Connecting);
+                    rejoin.attempt(None, state, status).await
+                } else {
+                    // the game may ask all it wants, but only the client
+                    // can come back.
+                    let attempt = rejoin.attempt(None, state, status);
+                    tokio::pin!(attempt);
+                    loop {
+                        tokio::select! {
+                            reconnected = &mut attempt => break reconnected,
+                            request = requests.recv() => if request.is_none() {
+                                return Err(e.into());
+                            },
+                        }
+                    }
+                };
+                match reconnected {
+                    Ok(reconnected) => return Ok(reconnected),
+                    Err(e) => last = e,
+                }
+            }
+        }
+    }
+}
 
-                let mut buf = [0; SIZE];
-                recv.read_exact(&mut buf).await.unwrap();
-                send_to_game
-                    .try_send(buf)
-                    .expect("we should never have a full buffer");
+/// Pick the game back up over a new bi-stream, after reconnecting.
+///
+/// Each player tells the other the sequence number of the last move they
+/// received, then sends again their own moves that were lost when the
+/// connection dropped. Control frames that were lost are not sent again.
+async fn resume<const SIZE: usize>(
+    send: &mut SendStream,
+    recv: &mut RecvStream,
+    state: &Mutex<PlayState<SIZE>>,
+    status: &StatusSenders,
+) -> Result<(), BoxError> {
+    let received = state.lock().expect(STATE_LOCK).received;
+    // the host can only accept the stream once the client writes to it, so
+    // both players write first.
+    Frame::Resume(received).write(send).await?;
+    let Frame::Resume(theirs) = Frame::read(recv).await? else {
+        return Err("the other player did not say where to resume".into());
+    };
+    let (sent, lost): (u64, Vec<Vec<u8>>) = {
+        let mut state = state.lock().expect(STATE_LOCK);
+        if theirs > state.sent {
+            return Err(NetcodeError::Desync {
+                expected: state.sent,
+                got: theirs,
             }
+            .into());
         }
-        Config::TicketSender(sender) => {
-            // we are the host, aka receiver, aka player with second move.
-            let host_endpoint = Endpoint::builder()
-                .discovery_n0()
-                .alpns(vec![ALPN.to_vec()])
+        state.unacked.retain(|&(sent, _)| sent > theirs);
+        let lost = state
+            .unacked
+            .iter()
+            .map(|(_, frame)| frame.clone())
+            .collect();
+        (state.sent, lost)
+    };
+    status
+        .acked
+        .send_modify(|acked| *acked = (*acked).max(theirs));
+    for frame in lost {
+        transfer::write_bytes(send, &frame).await?;
+    }
+    // a move cut off mid-chunk was just sent again in full.
+    status.written.send_replace(sent);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use iroh::endpoint::ConnectionClose;
+
+    use super::*;
+    use crate::{NetcodeInterface, tests::eventually};
+
+    fn closed(code: u8) -> ConnectionError {
+        ConnectionError::ConnectionClosed(ConnectionClose {
+            error_code: TransportErrorCode::crypto(code),
+            frame_type: None,
+            reason: Default::default(),
+        })
+    }
+
+    #[test]
+    fn refused_alpn_is_a_protocol_mismatch() {
+        let e = netcode_error(connect_error(closed(NO_APPLICATION_PROTOCOL), ALPN));
+        assert_eq!(
+            e,
+            NetcodeError::ProtocolMismatch {
+                local: "saffron/sfn-tpn/1".to_string(),
+                remote: None,
+            }
+        );
+    }
+
+    #[test]
+    fn other_handshake_failures_are_not() {
+        // a bad certificate.
+        let e = netcode_error(connect_error(closed(42), ALPN));
+        assert!(matches!(e, NetcodeError::Disconnected(_)));
+        let e = netcode_error(connect_error(ConnectionError::TimedOut, ALPN));
+        assert!(matches!(e, NetcodeError::Disconnected(_)));
+    }
+
+    /// A client that speaks the protocol by hand, so it can send frames the
+    /// interface never would.
+    struct RawClient {
+        _endpoint: Endpoint,
+        connection: Connection,
+        send: SendStream,
+        /// Never read, but kept open so the host can keep writing.
+        _recv: RecvStream,
+    }
+
+    impl RawClient {
+        /// Connect to the host with `ticket` and shake hands like a client
+        /// with the default options and one-byte turns.
+        async fn connect(ticket: String) -> Self {
+            let endpoint = Endpoint::builder()
+                .relay_mode(RelayMode::Disabled)
                 .bind()
                 .await
                 .unwrap();
+            let host = NodeAddr::from(NodeTicket::from_str(&ticket).unwrap());
+            let connection = endpoint.connect(host, ALPN).await.unwrap();
+            let (mut send, mut recv) = connection.open_bi().await.unwrap();
+            let hello = Hello {
+                size: 1,
+                first_move: Some(Role::Client),
+                simultaneous: false,
+                encoder: None,
+                turn_sizes: (1, 1),
+                time_control: None,
+                session_half: [0; 16],
+                restore: None,
+            };
+            let nonce = [0; 32];
+            for frame in [
+                Frame::Hello(hello),
+                Frame::Metadata(Metadata::default()),
+                Frame::Commit(Sha256::digest(nonce).into()),
+            ] {
+                frame.write(&mut send).await.unwrap();
+                Frame::read(&mut recv).await.unwrap();
+            }
+            Frame::Reveal(nonce).write(&mut send).await.unwrap();
+            Frame::read(&mut recv).await.unwrap();
+            assert_eq!(Frame::read(&mut recv).await.unwrap(), Frame::Setup(None));
+            Frame::EstablishedAt(0).write(&mut send).await.unwrap();
+            Self {
+                _endpoint: endpoint,
+                connection,
+                send,
+                _recv: recv,
+            }
+        }
 
-            // send our user the ticket string
-            sender
-                .send(
-                    NodeTicket::new(host_endpoint.node_addr().initialized().await.unwrap())
-                        .to_string(),
-                )
-                .unwrap();
+        /// Send a move of one turn, numbered `seq`.
+        async fn send_move(&mut self, seq: u64, turn: u8) {
+            Frame::Turns {
+                seq,
+                game: 0,
+                clock: None,
+                state_hash: None,
+                turns: vec![vec![turn]],
+            }
+            .write(&mut self.send)
+            .await
+            .unwrap();
+        }
+    }
 
-            match host_endpoint.accept().await {
-                Some(incoming) => {
-                    let connection = incoming.await.unwrap();
-                    let node_id = connection.remote_node_id().unwrap();
-                    println!("accepted connection from {node_id}");
-                    let (mut send, mut recv) = connection.accept_bi().await.unwrap();
+    /// Start a host, and connect a raw client to it.
+    async fn raw_game() -> (NetcodeInterface<1>, RawClient) {
+        let (ticket_sender, ticket) = oneshot::channel();
+        let mut host = NetcodeInterface::new(Config::TicketSender(ticket_sender));
+        let client = RawClient::connect(ticket.await.unwrap()).await;
+        host.wait_for_connection().await.unwrap();
+        (host, client)
+    }
 
-                    loop {
-                        let mut buf = [0; SIZE];
-                        recv.read_exact(&mut buf).await.unwrap();
-                        send_to_game
-                            .try_send(buf)
-                            .expect("we should never have a full buffer");
-
-                        // Send the data the game wants to send
-                        send.write_all(&recv_from_game.recv().await.unwrap())
-                            .await
-                            .unwrap();
-                    }
-                }
-                None => todo!(),
+    /// Poll the host like a game loop until the protocol stops, returning
+    /// the turns it received first, and why it stopped.
+    async fn turns_until_stopped(host: &mut NetcodeInterface<1>) -> (Vec<u8>, NetcodeError) {
+        let mut turns = Vec::new();
+        let e = eventually(|| {
+            if !host.my_turn()
+                && let crate::TurnPoll::Ready([turn]) = host.try_recv_turn()
+            {
+                turns.push(turn);
+                // hand the turn back without telling the raw client.
+                host.reset_is_my_turn(false);
+            }
+            match host.connection_state() {
+                ConnectionState::Disconnected(e) => Some(e),
+                _ => None,
+            }
+        })
+        .await;
+        (turns, e)
+    }
+
+    #[tokio::test]
+    async fn a_duplicated_move_is_a_desync() {
+        let (mut host, mut client) = raw_game().await;
+        client.send_move(1, 10).await;
+        client.send_move(1, 10).await;
+        let (turns, e) = turns_until_stopped(&mut host).await;
+        assert_eq!(turns, [10]);
+        assert_eq!(
+            e,
+            NetcodeError::Desync {
+                expected: 2,
+                got: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn a_skipped_move_is_a_desync() {
+        let (mut host, mut client) = raw_game().await;
+        client.send_move(1, 10).await;
+        client.send_move(3, 30).await;
+        let (turns, e) = turns_until_stopped(&mut host).await;
+        assert_eq!(turns, [10]);
+        assert_eq!(
+            e,
+            NetcodeError::Desync {
+                expected: 2,
+                got: 3
             }
+        );
+    }
+
+    #[tokio::test]
+    async fn an_unacked_turn_times_out_until_the_ack_arrives() {
+        let (mut host, mut client) = raw_game().await;
+        client.send_move(1, 10).await;
+        eventually(|| host.try_recv_turn_into(&mut [0]).ok()).await;
+        assert_eq!(
+            host.send_turn_with_ack_timeout(&[20], Duration::from_millis(200))
+                .await,
+            Err(NetcodeError::AckTimeout)
+        );
+        assert!(!host.last_turn_acked());
+        Frame::Ack(1).write(&mut client.send).await.unwrap();
+        eventually(|| host.last_turn_acked().then_some(())).await;
+    }
+
+    #[tokio::test]
+    async fn a_move_cut_off_mid_transfer_is_never_delivered() {
+        let (mut host, mut client) = raw_game().await;
+        let frame = Frame::Turns {
+            seq: 1,
+            game: 0,
+            clock: None,
+            state_hash: None,
+            turns: vec![vec![7; 3 * CHUNK_LEN]],
         }
+        .encode()
+        .unwrap();
+        let mut chunks = transfer::split(&frame);
+        for _ in 0..2 {
+            let bytes = chunks.pop_front().unwrap();
+            Frame::Chunk { last: false, bytes }
+                .write(&mut client.send)
+                .await
+                .unwrap();
+        }
+        let progress = TransferProgress {
+            done: 2 * CHUNK_LEN as u64,
+            total: frame.len() as u64,
+        };
+        eventually(|| (host.incoming_transfer() == Some(progress)).then_some(())).await;
+
+        client.connection.close(VarInt::from_u32(0), b"gone");
+        let (turns, e) = turns_until_stopped(&mut host).await;
+        assert!(turns.is_empty());
+        assert!(matches!(e, NetcodeError::Disconnected(_)));
+        assert_eq!(host.ply(), 0);
     }
 }
@@ -0,0 +1,38 @@
+//! Picking a game back up when the connection drops mid-game, like when a
+//! laptop goes to sleep.
+//!
+//! The client dials the host again from the same node, and the host only
+//! lets that node back in. Then each player tells the other the last move
+//! they received, and sends again any of theirs that were lost.
+
+use std::time::Duration;
+
+/// What to do when the connection to the other player drops after it was
+/// established. Set with
+/// [`reconnect`](crate::NetcodeInterfaceBuilder::reconnect).
+///
+/// Only the client can dial, so the host's strategy decides how long it
+/// waits for the client to come back. A player who closes the game on
+/// purpose is never reconnected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reconnect {
+    /// Stay disconnected.
+    #[default]
+    Disabled,
+    /// Reconnect in the background, up to `max_attempts` times.
+    ///
+    /// The client waits `backoff` before its first attempt, and twice as
+    /// long before each one after. The host waits that long for the client
+    /// on each attempt. Meanwhile, the connection state is
+    /// [`Connecting`](crate::ConnectionState::Connecting), and it only
+    /// becomes [`Disconnected`](crate::ConnectionState::Disconnected) once
+    /// every attempt failed.
+    Automatic {
+        max_attempts: u32,
+        backoff: Duration,
+    },
+    /// Become [`Disconnected`](crate::ConnectionState::Disconnected), and
+    /// only reconnect once the game calls
+    /// [`reconnect`](crate::NetcodeInterface::reconnect).
+    Manual,
+}
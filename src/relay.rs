@@ -0,0 +1,49 @@
+//! Reaching the other player through a relay server of our own, instead of
+//! n0's public ones, like on a private network.
+
+use iroh::{NodeId, RelayUrl, Watcher, endpoint::ConnectionType};
+
+use crate::{NetcodeInterface, NetcodeInterfaceBuilder};
+
+impl<const SIZE: usize> NetcodeInterfaceBuilder<SIZE> {
+    /// Use only the relay server at `url`, instead of n0's public relays,
+    /// like for games on a private network, or with a relay close to their
+    /// players.
+    ///
+    /// Relays are only used until the players find a direct path to each
+    /// other, if they ever do. Both players should use the same relay, so
+    /// each can be reached through it. Games opened from a
+    /// [`Session`](crate::Session) use the session's connection instead.
+    ///
+    /// Requires the `iroh-relay` feature.
+    pub fn relay_url(mut self, url: RelayUrl) -> Self {
+        self.relay_url = Some(url);
+        self
+    }
+}
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Return whether the connection to the other player is currently
+    /// relayed, rather than direct, including while iroh is still trying a
+    /// direct path alongside the relay.
+    ///
+    /// Returns `false` until the connection is established, and for games
+    /// opened from a [`Session`](crate::Session).
+    ///
+    /// Requires the `iroh-relay` feature.
+    pub fn iroh_uses_relay(&self) -> bool {
+        let (Some(endpoint), Some((_, theirs))) = (self.iroh_endpoint(), *self.node_keys.borrow())
+        else {
+            return false;
+        };
+        let Ok(node_id) = NodeId::from_bytes(&theirs) else {
+            return false;
+        };
+        endpoint.conn_type(node_id).is_some_and(|conn_type| {
+            matches!(
+                conn_type.get(),
+                Ok(ConnectionType::Relay(_) | ConnectionType::Mixed(..))
+            )
+        })
+    }
+}
@@ -0,0 +1,181 @@
+//! Offering, accepting, and declining rematches over the same connection.
+//!
+//! Every turn is sent with the number of the game it belongs to, so turns
+//! still in flight from a finished game are discarded once a rematch starts.
+
+use std::collections::VecDeque;
+
+use crate::frame::ControlFrame;
+
+/// Something that happened to a rematch offer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RematchEvent {
+    /// The other player offered a rematch. Answer with
+    /// [`respond_rematch`](crate::NetcodeInterface::respond_rematch).
+    Offered {
+        /// Whether the rematch swaps who moves first.
+        swap_first_move: bool,
+    },
+    /// The other player declined the user's offer.
+    Declined,
+    /// A rematch started. The game should reset its own state.
+    Started {
+        /// Whether the user moves first in the rematch.
+        you_move_first: bool,
+    },
+}
+
+/// Who made the rematch offer that has yet to be answered, and whether it
+/// swaps who moves first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Offer {
+    Ours(bool),
+    Theirs(bool),
+}
+
+/// Both players' view of rematch offers.
+#[derive(Debug, Default)]
+pub(crate) struct Rematch {
+    outstanding: Option<Offer>,
+    /// Events the game has yet to receive.
+    events: VecDeque<RematchEvent>,
+}
+
+impl Rematch {
+    /// Offer a rematch, returning the frame to send, if any, and whether a
+    /// rematch starts now, and if so whether it swaps who moves first.
+    ///
+    /// Offering again while our offer is outstanding does nothing. Offering
+    /// while theirs is outstanding accepts it, on their terms.
+    pub fn offer(&mut self, swap_first_move: bool) -> (Option<ControlFrame>, Option<bool>) {
+        match self.outstanding {
+            Some(Offer::Ours(_)) => (None, None),
+            Some(Offer::Theirs(swap)) => {
+                self.outstanding = None;
+                (Some(ControlFrame::RematchAccept), Some(swap))
+            }
+            None => {
+                self.outstanding = Some(Offer::Ours(swap_first_move));
+                (Some(ControlFrame::RematchOffer(swap_first_move)), None)
+            }
+        }
+    }
+
+    /// Answer the other player's offer, returning the frame to send, and
+    /// whether a rematch starts now, and if so whether it swaps who moves
+    /// first.
+    pub fn respond(&mut self, accept: bool) -> (ControlFrame, Option<bool>) {
+        let Some(Offer::Theirs(swap)) = self.outstanding.take() else {
+            panic!("the other player has no outstanding rematch offer");
+        };
+        if accept {
+            (ControlFrame::RematchAccept, Some(swap))
+        } else {
+            (ControlFrame::RematchDecline, None)
+        }
+    }
+
+    /// Handle a rematch frame from the other player.
+    ///
+    /// Returns whether a rematch starts now, and if so whether it swaps who
+    /// moves first.
+    pub fn handle(&mut self, control: ControlFrame) -> Option<bool> {
+        match (control, self.outstanding) {
+            // our offers crossed in flight, so both of us see the other's
+            // offer while our own is outstanding. Swapping if either of us
+            // asked to gives both of us the same answer.
+            (ControlFrame::RematchOffer(theirs), Some(Offer::Ours(ours))) => {
+                self.outstanding = None;
+                Some(ours || theirs)
+            }
+            (ControlFrame::RematchOffer(swap), _) => {
+                self.outstanding = Some(Offer::Theirs(swap));
+                self.events.push_back(RematchEvent::Offered {
+                    swap_first_move: swap,
+                });
+                None
+            }
+            (ControlFrame::RematchAccept, Some(Offer::Ours(swap))) => {
+                self.outstanding = None;
+                Some(swap)
+            }
+            (ControlFrame::RematchDecline, Some(Offer::Ours(_))) => {
+                self.outstanding = None;
+                self.events.push_back(RematchEvent::Declined);
+                None
+            }
+            // anything else answers an offer that does not exist, which a
+            // well-behaved player never sends.
+            _ => None,
+        }
+    }
+
+    /// Tell the game a rematch started.
+    pub fn started(&mut self, you_move_first: bool) {
+        self.events
+            .push_back(RematchEvent::Started { you_move_first });
+    }
+
+    /// Return the next event the game has yet to receive.
+    pub fn next_event(&mut self) -> Option<RematchEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offer_then_accept_on_the_offer_terms() {
+        let (mut ours, mut theirs) = (Rematch::default(), Rematch::default());
+        let (offer, started) = ours.offer(true);
+        assert_eq!(started, None);
+        assert_eq!(theirs.handle(offer.unwrap()), None);
+        assert_eq!(
+            theirs.next_event(),
+            Some(RematchEvent::Offered {
+                swap_first_move: true
+            })
+        );
+        let (accept, swap) = theirs.respond(true);
+        assert_eq!(swap, Some(true));
+        assert_eq!(ours.handle(accept), Some(true));
+    }
+
+    #[test]
+    fn offer_then_decline() {
+        let (mut ours, mut theirs) = (Rematch::default(), Rematch::default());
+        theirs.handle(ours.offer(false).0.unwrap());
+        let (decline, swap) = theirs.respond(false);
+        assert_eq!(swap, None);
+        assert_eq!(ours.handle(decline), None);
+        assert_eq!(ours.next_event(), Some(RematchEvent::Declined));
+    }
+
+    #[test]
+    fn crossed_offers_agree_on_swapping() {
+        let (mut ours, mut theirs) = (Rematch::default(), Rematch::default());
+        let (a, b) = (ours.offer(false).0.unwrap(), theirs.offer(true).0.unwrap());
+        assert_eq!(ours.handle(b), Some(true));
+        assert_eq!(theirs.handle(a), Some(true));
+    }
+
+    #[test]
+    fn offering_again_does_nothing_and_accepting_uses_their_terms() {
+        let (mut ours, mut theirs) = (Rematch::default(), Rematch::default());
+        let offer = theirs.offer(true).0.unwrap();
+        assert_eq!(theirs.offer(true), (None, None));
+        ours.handle(offer);
+        assert_eq!(
+            ours.offer(false),
+            (Some(ControlFrame::RematchAccept), Some(true))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn responding_without_an_offer_panics() {
+        Rematch::default().respond(true);
+    }
+}
@@ -0,0 +1,312 @@
+//! Recording every turn to a replay file, and reading it back.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use crate::{Direction, metadata::Metadata};
+
+const MAGIC: &[u8; 4] = b"SFNR";
+const VERSION: u8 = 1;
+
+/// A replay written by
+/// [`NetcodeInterfaceBuilder::replay`](crate::NetcodeInterfaceBuilder::replay),
+/// and read back with [`Replay::read`].
+///
+/// # Format
+///
+/// All integers are big-endian. A replay starts with a header:
+///
+/// - the magic bytes `SFNR`
+/// - the format version, a `u8`, currently 1
+/// - the size of a turn in bytes, a `u32`
+/// - the [`shared_seed`](crate::NetcodeInterface::shared_seed), 32 bytes
+/// - the length of the metadata, a `u32`, then the recording player's
+///   [`metadata`](crate::NetcodeInterfaceBuilder::metadata). Each entry is 1
+///   if it is hard or 0 if it is soft, then the key and value, each prefixed
+///   with its length as a `u16`
+///
+/// Then, one record per turn, each prefixed with its length as a `u32`:
+///
+/// - the number of the game, a `u32`
+/// - the [`ply`](crate::NetcodeInterface::ply) after the turn's move, a `u64`
+/// - 0 if the turn was sent, or 1 if it was received
+/// - when the turn was sent or received, in milliseconds since the Unix
+///   epoch, a `u64`
+/// - the turn itself
+///
+/// Turns that were taken back stay in the replay. The file is flushed after
+/// every record, so a crash leaves at most the last record truncated, which
+/// [`Replay::read`] ignores.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    /// The size of a turn in bytes.
+    pub size: u32,
+    pub seed: [u8; 32],
+    /// The recording player's metadata, both hard and soft.
+    pub metadata: BTreeMap<String, String>,
+    pub records: Vec<ReplayRecord>,
+}
+
+/// A single turn in a [`Replay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayRecord {
+    /// The number of the game the turn was taken in, counting from zero.
+    pub game: u32,
+    /// The [`ply`](crate::NetcodeInterface::ply) after the move the turn
+    /// belongs to.
+    pub ply: u64,
+    pub direction: Direction,
+    /// Rounded down to the millisecond.
+    pub timestamp: SystemTime,
+    pub turn: Vec<u8>,
+}
+
+impl Replay {
+    /// Read the replay at `path`.
+    ///
+    /// A truncated last record is ignored. Returns
+    /// [`io::ErrorKind::InvalidData`] if the file is not a replay, or one of
+    /// a version we cannot read.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Replay> {
+        Replay::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a replay from `reader`, like [`read`](Replay::read).
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Replay> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+
+        let mut header = [0; MAGIC.len() + 1 + 4 + 32 + 4];
+        reader.read_exact(&mut header)?;
+        let (magic, rest) = header.split_at(MAGIC.len());
+        if magic != MAGIC {
+            return Err(invalid("not a replay".into()));
+        }
+        let [version, rest @ ..] = rest else {
+            unreachable!("the header is longer than that")
+        };
+        if *version != VERSION {
+            return Err(invalid(format!("unknown replay version {version}").into()));
+        }
+        let (size, rest) = rest.split_first_chunk::<4>().expect("the header fits");
+        let (seed, rest) = rest.split_first_chunk::<32>().expect("the header fits");
+        let metadata_len = u32::from_be_bytes(rest.try_into().expect("the header fits"));
+        let mut metadata = vec![0; metadata_len as usize];
+        reader.read_exact(&mut metadata)?;
+        let metadata = Metadata::decode(&metadata).map_err(invalid)?;
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest)?;
+        let mut rest = rest.as_slice();
+        let mut records = Vec::new();
+        while let Some((len, after)) = rest.split_first_chunk::<4>()
+            && let Some((record, after)) = after.split_at_checked(u32::from_be_bytes(*len) as usize)
+        {
+            records.push(decode_record(record).map_err(invalid)?);
+            rest = after;
+        }
+
+        Ok(Replay {
+            size: u32::from_be_bytes(*size),
+            seed: *seed,
+            metadata: metadata.all(),
+            records,
+        })
+    }
+}
+
+fn decode_record(record: &[u8]) -> Result<ReplayRecord, Box<dyn std::error::Error + Send + Sync>> {
+    let (game, rest) = record.split_first_chunk::<4>().ok_or("truncated record")?;
+    let (ply, rest) = rest.split_first_chunk::<8>().ok_or("truncated record")?;
+    let (direction, rest) = rest.split_first().ok_or("truncated record")?;
+    let (millis, turn) = rest.split_first_chunk::<8>().ok_or("truncated record")?;
+    Ok(ReplayRecord {
+        game: u32::from_be_bytes(*game),
+        ply: u64::from_be_bytes(*ply),
+        direction: match direction {
+            0 => Direction::Sent,
+            1 => Direction::Received,
+            _ => return Err(format!("unknown direction {direction}").into()),
+        },
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(u64::from_be_bytes(*millis)),
+        turn: turn.to_vec(),
+    })
+}
+
+/// Writes a replay as turns are sent and received.
+pub(crate) struct ReplayWriter {
+    writer: Box<dyn Write + Send>,
+    /// The header, minus the seed, until the seed is known and it is written.
+    header: Option<(u32, Vec<u8>)>,
+    /// Records taken before the header could be written.
+    pending: Vec<u8>,
+}
+
+impl ReplayWriter {
+    pub fn new(writer: Box<dyn Write + Send>, size: u32, metadata: &Metadata) -> Self {
+        // metadata too long to encode fails the handshake, so nothing is
+        // ever recorded with it anyway.
+        let metadata = metadata.encode().unwrap_or_default();
+        Self {
+            writer,
+            header: Some((size, metadata)),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Write a record, or keep it for later if the `seed` is not known yet.
+    pub fn record(
+        &mut self,
+        seed: Option<[u8; 32]>,
+        game: u32,
+        ply: u32,
+        direction: Direction,
+        turn: &[u8],
+    ) -> io::Result<()> {
+        let millis = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let len = 4 + 8 + 1 + 8 + turn.len();
+        self.pending
+            .extend_from_slice(&u32::try_from(len).expect("turns are small").to_be_bytes());
+        self.pending.extend_from_slice(&game.to_be_bytes());
+        self.pending
+            .extend_from_slice(&u64::from(ply).to_be_bytes());
+        self.pending.push(match direction {
+            Direction::Sent => 0,
+            Direction::Received => 1,
+        });
+        self.pending
+            .extend_from_slice(&u64::try_from(millis).unwrap_or(u64::MAX).to_be_bytes());
+        self.pending.extend_from_slice(turn);
+
+        if let Some((size, metadata)) = &self.header {
+            // turns can be sent before the connection is established, but
+            // the seed is only known once it is.
+            let Some(seed) = seed else {
+                return Ok(());
+            };
+            self.writer.write_all(MAGIC)?;
+            self.writer.write_all(&[VERSION])?;
+            self.writer.write_all(&size.to_be_bytes())?;
+            self.writer.write_all(&seed)?;
+            let metadata_len = u32::try_from(metadata.len()).expect("metadata is small");
+            self.writer.write_all(&metadata_len.to_be_bytes())?;
+            self.writer.write_all(metadata)?;
+            self.header = None;
+        }
+        self.writer.write_all(&self.pending)?;
+        self.pending.clear();
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// A writer whose bytes can still be read after it is boxed.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The seed known when the turn was taken, then the turn.
+    type Turn<'a> = (Option<[u8; 32]>, Direction, &'a [u8]);
+
+    fn record(turns: &[Turn]) -> Vec<u8> {
+        let shared = Shared::default();
+        let mut metadata = Metadata::default();
+        metadata.hard.insert("rules".into(), "2".into());
+        let mut writer = ReplayWriter::new(Box::new(shared.clone()), 2, &metadata);
+        for (ply, (seed, direction, turn)) in turns.iter().enumerate() {
+            let ply = u32::try_from(ply).unwrap() + 1;
+            writer.record(*seed, 0, ply, *direction, turn).unwrap();
+        }
+        shared.0.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let seed = Some([3; 32]);
+        let bytes = record(&[
+            (seed, Direction::Sent, &[1, 2]),
+            (seed, Direction::Received, &[3, 4]),
+        ]);
+        let replay = Replay::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(replay.size, 2);
+        assert_eq!(replay.seed, [3; 32]);
+        assert_eq!(replay.metadata.get("rules").map(String::as_str), Some("2"));
+        let records: Vec<_> = replay
+            .records
+            .iter()
+            .map(|record| {
+                (
+                    record.game,
+                    record.ply,
+                    record.direction,
+                    record.turn.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (0, 1, Direction::Sent, vec![1, 2]),
+                (0, 2, Direction::Received, vec![3, 4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_wait_for_the_seed() {
+        let bytes = record(&[(None, Direction::Sent, &[1, 2])]);
+        assert!(bytes.is_empty());
+
+        let bytes = record(&[
+            (None, Direction::Sent, &[1, 2]),
+            (Some([5; 32]), Direction::Received, &[3, 4]),
+        ]);
+        let replay = Replay::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(replay.seed, [5; 32]);
+        assert_eq!(replay.records.len(), 2);
+    }
+
+    #[test]
+    fn a_truncated_last_record_is_ignored() {
+        let seed = Some([3; 32]);
+        let bytes = record(&[
+            (seed, Direction::Sent, &[1, 2]),
+            (seed, Direction::Received, &[3, 4]),
+        ]);
+        let replay = Replay::from_reader(&bytes[..bytes.len() - 1]).unwrap();
+        assert_eq!(replay.records.len(), 1);
+        assert_eq!(replay.records[0].turn, [1, 2]);
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let mut bytes = record(&[(Some([3; 32]), Direction::Sent, &[1, 2])]);
+        bytes[MAGIC.len()] = VERSION + 1;
+        let e = Replay::from_reader(bytes.as_slice()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let e = Replay::from_reader(&[0; 64][..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,384 @@
+//! Laying out turns that are one of several kinds of action, like an enum,
+//! as a tag byte followed by that action's fields.
+
+use std::fmt;
+
+/// A turn type with several variants, laid out as a tag byte followed by the
+/// variant's fields, zero-padded up to `SIZE` bytes.
+///
+/// Implement [`tag`](TurnSchema::tag), [`write_fields`](TurnSchema::write_fields),
+/// and [`read_fields`](TurnSchema::read_fields), then convert turns with
+/// [`to_turn`](TurnSchema::to_turn) and [`from_turn`](TurnSchema::from_turn).
+/// Fields are written big-endian, so both players lay them out the same way.
+///
+/// ```
+/// # use sfn_tpn::{SchemaError, TurnReader, TurnSchema, TurnWriter};
+/// #[derive(Debug, PartialEq)]
+/// enum Action {
+///     Move { from: u8, to: u8 },
+///     Promote { at: u8, piece: u8 },
+///     Resign,
+/// }
+///
+/// impl TurnSchema<4> for Action {
+///     fn tag(&self) -> u8 {
+///         match self {
+///             Action::Move { .. } => 0,
+///             Action::Promote { .. } => 1,
+///             Action::Resign => 2,
+///         }
+///     }
+///
+///     fn write_fields(&self, fields: &mut TurnWriter<'_>) -> Result<(), SchemaError> {
+///         match *self {
+///             Action::Move { from, to } => {
+///                 fields.u8(from)?;
+///                 fields.u8(to)
+///             }
+///             Action::Promote { at, piece } => {
+///                 fields.u8(at)?;
+///                 fields.u8(piece)
+///             }
+///             Action::Resign => Ok(()),
+///         }
+///     }
+///
+///     fn read_fields(tag: u8, fields: &mut TurnReader<'_>) -> Result<Self, SchemaError> {
+///         match tag {
+///             0 => Ok(Action::Move { from: fields.u8()?, to: fields.u8()? }),
+///             1 => Ok(Action::Promote { at: fields.u8()?, piece: fields.u8()? }),
+///             2 => Ok(Action::Resign),
+///             // like a variant added in a newer version of the game.
+///             _ => Err(SchemaError::UnknownTag(tag)),
+///         }
+///     }
+/// }
+///
+/// for action in [
+///     Action::Move { from: 12, to: 28 },
+///     Action::Promote { at: 60, piece: 4 },
+///     Action::Resign,
+/// ] {
+///     assert_eq!(Action::from_turn(&action.to_turn()?)?, action);
+/// }
+/// assert_eq!(Action::from_turn(&[7, 0, 0, 0]), Err(SchemaError::UnknownTag(7)));
+/// # Ok::<(), SchemaError>(())
+/// ```
+pub trait TurnSchema<const SIZE: usize>: Sized {
+    /// The tag identifying this value's variant.
+    fn tag(&self) -> u8;
+
+    /// Write this value's fields, which come after the tag.
+    fn write_fields(&self, fields: &mut TurnWriter<'_>) -> Result<(), SchemaError>;
+
+    /// Read the fields of the variant with `tag`.
+    ///
+    /// Return [`SchemaError::UnknownTag`] for any tag this version of the game
+    /// does not know about.
+    fn read_fields(tag: u8, fields: &mut TurnReader<'_>) -> Result<Self, SchemaError>;
+
+    /// Lay this value out as a turn.
+    ///
+    /// Returns [`SchemaError::VariantTooLarge`] if its fields do not fit.
+    fn to_turn(&self) -> Result<[u8; SIZE], SchemaError> {
+        let mut turn = [0; SIZE];
+        let tag = self.tag();
+        let (first, rest) = turn
+            .split_first_mut()
+            .ok_or(SchemaError::VariantTooLarge(tag))?;
+        *first = tag;
+        self.write_fields(&mut TurnWriter {
+            tag,
+            bytes: rest,
+            written: 0,
+        })?;
+        Ok(turn)
+    }
+
+    /// Read a value back from a turn made with
+    /// [`to_turn`](TurnSchema::to_turn).
+    fn from_turn(turn: &[u8; SIZE]) -> Result<Self, SchemaError> {
+        let (&tag, rest) = turn.split_first().ok_or(SchemaError::TruncatedVariant(0))?;
+        Self::read_fields(tag, &mut TurnReader { tag, bytes: rest })
+    }
+}
+
+/// Writes a variant's fields into a turn, for
+/// [`TurnSchema::write_fields`].
+///
+/// Each method returns [`SchemaError::VariantTooLarge`] if the field does
+/// not fit in what is left of the turn.
+pub struct TurnWriter<'a> {
+    tag: u8,
+    bytes: &'a mut [u8],
+    written: usize,
+}
+
+impl TurnWriter<'_> {
+    /// Write raw bytes.
+    pub fn bytes(&mut self, bytes: &[u8]) -> Result<(), SchemaError> {
+        let end = self.written + bytes.len();
+        self.bytes
+            .get_mut(self.written..end)
+            .ok_or(SchemaError::VariantTooLarge(self.tag))?
+            .copy_from_slice(bytes);
+        self.written = end;
+        Ok(())
+    }
+
+    pub fn u8(&mut self, value: u8) -> Result<(), SchemaError> {
+        self.bytes(&[value])
+    }
+
+    pub fn u16(&mut self, value: u16) -> Result<(), SchemaError> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn u32(&mut self, value: u32) -> Result<(), SchemaError> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn u64(&mut self, value: u64) -> Result<(), SchemaError> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn i32(&mut self, value: i32) -> Result<(), SchemaError> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn bool(&mut self, value: bool) -> Result<(), SchemaError> {
+        self.u8(u8::from(value))
+    }
+}
+
+/// Reads a variant's fields from a turn, for [`TurnSchema::read_fields`].
+///
+/// Each method returns [`SchemaError::TruncatedVariant`] if the turn ends
+/// before the field does.
+pub struct TurnReader<'a> {
+    tag: u8,
+    bytes: &'a [u8],
+}
+
+impl TurnReader<'_> {
+    /// Read `N` raw bytes.
+    pub fn bytes<const N: usize>(&mut self) -> Result<[u8; N], SchemaError> {
+        let (field, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or(SchemaError::TruncatedVariant(self.tag))?;
+        self.bytes = rest;
+        Ok(*field)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, SchemaError> {
+        self.bytes().map(u8::from_be_bytes)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, SchemaError> {
+        self.bytes().map(u16::from_be_bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, SchemaError> {
+        self.bytes().map(u32::from_be_bytes)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, SchemaError> {
+        self.bytes().map(u64::from_be_bytes)
+    }
+
+    pub fn i32(&mut self) -> Result<i32, SchemaError> {
+        self.bytes().map(i32::from_be_bytes)
+    }
+
+    /// Read a bool, which must be written as 0 or 1.
+    pub fn bool(&mut self) -> Result<bool, SchemaError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SchemaError::InvalidField(self.tag)),
+        }
+    }
+}
+
+/// A turn could not be laid out or read back by a [`TurnSchema`].
+///
+/// Each variant holds the tag of the variant involved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaError {
+    /// The turn's tag is not a variant this version of the game knows about,
+    /// like when the other player is on a newer version.
+    UnknownTag(u8),
+    /// The turn ended before the variant's fields did.
+    TruncatedVariant(u8),
+    /// The variant's fields did not fit in the turn.
+    VariantTooLarge(u8),
+    /// A field held a value its type cannot, like a bool that is neither 0
+    /// nor 1.
+    InvalidField(u8),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::UnknownTag(tag) => write!(f, "unknown turn tag {tag}"),
+            SchemaError::TruncatedVariant(tag) => {
+                write!(f, "the turn ended inside the fields of tag {tag}")
+            }
+            SchemaError::VariantTooLarge(tag) => {
+                write!(f, "the fields of tag {tag} do not fit in the turn")
+            }
+            SchemaError::InvalidField(tag) => {
+                write!(f, "a field of tag {tag} holds an invalid value")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Action {
+        Place { at: u16, piece: [u8; 2] },
+        Score { points: i32, bonus: bool },
+        Clock { left: u32, since: u64 },
+        Resign,
+    }
+
+    impl TurnSchema<16> for Action {
+        fn tag(&self) -> u8 {
+            match self {
+                Action::Place { .. } => 0,
+                Action::Score { .. } => 1,
+                Action::Clock { .. } => 2,
+                Action::Resign => 3,
+            }
+        }
+
+        fn write_fields(&self, fields: &mut TurnWriter<'_>) -> Result<(), SchemaError> {
+            match *self {
+                Action::Place { at, piece } => {
+                    fields.u16(at)?;
+                    fields.bytes(&piece)
+                }
+                Action::Score { points, bonus } => {
+                    fields.i32(points)?;
+                    fields.bool(bonus)
+                }
+                Action::Clock { left, since } => {
+                    fields.u32(left)?;
+                    fields.u64(since)
+                }
+                Action::Resign => Ok(()),
+            }
+        }
+
+        fn read_fields(tag: u8, fields: &mut TurnReader<'_>) -> Result<Self, SchemaError> {
+            match tag {
+                0 => Ok(Action::Place {
+                    at: fields.u16()?,
+                    piece: fields.bytes()?,
+                }),
+                1 => Ok(Action::Score {
+                    points: fields.i32()?,
+                    bonus: fields.bool()?,
+                }),
+                2 => Ok(Action::Clock {
+                    left: fields.u32()?,
+                    since: fields.u64()?,
+                }),
+                3 => Ok(Action::Resign),
+                _ => Err(SchemaError::UnknownTag(tag)),
+            }
+        }
+    }
+
+    #[test]
+    fn every_variant_round_trips() {
+        for action in [
+            Action::Place {
+                at: 0x1234,
+                piece: [7, 9],
+            },
+            Action::Score {
+                points: -40,
+                bonus: true,
+            },
+            Action::Score {
+                points: i32::MAX,
+                bonus: false,
+            },
+            Action::Clock {
+                left: u32::MAX,
+                since: u64::MAX,
+            },
+            Action::Resign,
+        ] {
+            assert_eq!(Action::from_turn(&action.to_turn().unwrap()), Ok(action));
+        }
+    }
+
+    #[test]
+    fn fields_are_big_endian_and_padded() {
+        let turn = Action::Place {
+            at: 0x1234,
+            piece: [7, 9],
+        }
+        .to_turn()
+        .unwrap();
+        assert_eq!(turn[..5], [0, 0x12, 0x34, 7, 9]);
+        assert!(turn[5..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn unknown_tag_is_rejected() {
+        let mut turn = [0; 16];
+        for tag in 4..=u8::MAX {
+            turn[0] = tag;
+            assert_eq!(Action::from_turn(&turn), Err(SchemaError::UnknownTag(tag)));
+        }
+    }
+
+    #[test]
+    fn invalid_bool_is_rejected() {
+        let mut turn = Action::Score {
+            points: 1,
+            bonus: true,
+        }
+        .to_turn()
+        .unwrap();
+        turn[5] = 2;
+        assert_eq!(Action::from_turn(&turn), Err(SchemaError::InvalidField(1)));
+    }
+
+    /// Too wide for a turn of 4 bytes, whose fields only have 3.
+    #[derive(Debug, PartialEq)]
+    struct Wide(u32);
+
+    impl TurnSchema<4> for Wide {
+        fn tag(&self) -> u8 {
+            0
+        }
+
+        fn write_fields(&self, fields: &mut TurnWriter<'_>) -> Result<(), SchemaError> {
+            fields.u32(self.0)
+        }
+
+        fn read_fields(_tag: u8, fields: &mut TurnReader<'_>) -> Result<Self, SchemaError> {
+            fields.u32().map(Wide)
+        }
+    }
+
+    #[test]
+    fn variant_must_fit_the_turn() {
+        assert_eq!(Wide(1).to_turn(), Err(SchemaError::VariantTooLarge(0)));
+        assert_eq!(
+            Wide::from_turn(&[0, 0, 0, 1]),
+            Err(SchemaError::TruncatedVariant(0))
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Several games between the same two players over one connection.
+//!
+//! Each game gets its own bi-stream, which the client opens and names with a
+//! [`Frame::OpenGame`] before anything else. The host hands each stream to
+//! the game with that id once the user opens it, so the players can open
+//! their games in any order.
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use iroh::{
+    Endpoint, NodeAddr, Watcher,
+    endpoint::{Connection, RecvStream, SendStream},
+};
+use iroh_base::ticket::NodeTicket;
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    task::{self, JoinHandle},
+};
+
+use crate::{
+    Config, NetcodeInterface, NetcodeInterfaceBuilder, Role,
+    frame::Frame,
+    protocol::{self, BoxError, Transport},
+    url,
+};
+
+/// ALPN string for sessions of the sfn-tpn protocol.
+///
+/// Sessions speak differently from single games, so a session cannot connect
+/// to a single game by mistake. See [`ALPN`](crate::protocol::ALPN).
+pub const SESSION_ALPN: &[u8] = b"saffron/sfn-tpn/session/1";
+
+/// The bi-stream of a game, or why it could not be opened.
+type StreamReply = oneshot::Sender<Result<(SendStream, RecvStream), String>>;
+
+/// A connection to the other player that several games can share.
+///
+/// Create one on each side with a [`Config`], like a [`NetcodeInterface`],
+/// then open each game with [`open_game`](Session::open_game). Both players
+/// must open a game with the same id for it to connect. Each game is played
+/// like any other, with its own turns, and can end without disturbing the
+/// rest.
+///
+/// Games opened from a session cannot have spectators, and drop
+/// [activity pings](NetcodeInterface::send_activity_ping). Dropping the
+/// session closes the connection once every game has been opened, so keep it
+/// around for as long as its games are played.
+pub struct Session {
+    role: Role,
+    /// Asks the session task for a game's stream.
+    requests: UnboundedSender<(u32, StreamReply)>,
+    _handle: JoinHandle<()>,
+}
+
+impl Session {
+    /// Start connecting to the other player.
+    pub fn new(config: Config) -> Self {
+        let role = Role::of(&config);
+        let (requests, recv_requests) = mpsc::unbounded_channel();
+        let handle = task::spawn(async move {
+            if let Err(e) = run_session(config, recv_requests).await {
+                tracing::warn!("session stopped: {e}");
+            }
+        });
+        Self {
+            role,
+            requests,
+            _handle: handle,
+        }
+    }
+
+    /// Open the game with `game_id`, with the default options.
+    ///
+    /// Each id can only be opened once per session. Opening it again gives
+    /// an interface that fails to connect.
+    pub fn open_game<const SIZE: usize>(&self, game_id: u32) -> NetcodeInterface<SIZE> {
+        self.game_builder(game_id).build()
+    }
+
+    /// Create a builder to configure the game with `game_id`, like
+    /// [`open_game`](Session::open_game).
+    ///
+    /// [`allow_spectators`](NetcodeInterfaceBuilder::allow_spectators) is
+    /// ignored.
+    pub fn game_builder<const SIZE: usize>(&self, game_id: u32) -> NetcodeInterfaceBuilder<SIZE> {
+        NetcodeInterfaceBuilder::with_transport(Transport::Session(SessionGame {
+            role: self.role,
+            game_id,
+            requests: self.requests.clone(),
+        }))
+    }
+}
+
+/// A game of a [`Session`], yet to get its stream.
+pub(crate) struct SessionGame {
+    role: Role,
+    game_id: u32,
+    requests: UnboundedSender<(u32, StreamReply)>,
+}
+
+impl SessionGame {
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Wait for the session to connect, then get this game's stream.
+    pub async fn open(self) -> Result<(SendStream, RecvStream), BoxError> {
+        let (reply, stream) = oneshot::channel();
+        let stopped = "the session's connection was lost";
+        self.requests
+            .send((self.game_id, reply))
+            .map_err(|_| stopped)?;
+        Ok(stream.await.map_err(|_| stopped)??)
+    }
+}
+
+/// Connect to the other player, then hand out games' streams until the
+/// session is dropped or the connection is lost.
+async fn run_session(
+    config: Config,
+    mut requests: UnboundedReceiver<(u32, StreamReply)>,
+) -> Result<(), BoxError> {
+    let mut opened = HashSet::new();
+    match config {
+        Config::Ticket(t) => {
+            let host_addr = NodeAddr::from(NodeTicket::from_str(&t)?);
+            serve_client(host_addr, &mut requests, &mut opened).await
+        }
+        Config::Uri(uri) => {
+            let host_addr = url::node_addr_from_uri(&uri)?;
+            serve_client(host_addr, &mut requests, &mut opened).await
+        }
+        Config::TicketSender(sender) => {
+            let endpoint = Endpoint::builder()
+                .discovery_n0()
+                .alpns(vec![SESSION_ALPN.to_vec()])
+                .bind()
+                .await?;
+            let ticket = NodeTicket::new(endpoint.node_addr().initialized().await?);
+            sender
+                .send(ticket.to_string())
+                .map_err(|_| "the ticket receiver was dropped")?;
+
+            let (player_sender, mut player) = mpsc::unbounded_channel();
+            // without spectators, any turn size will do.
+            let accept_task = task::spawn(protocol::accept_connections::<0>(
+                endpoint.clone(),
+                player_sender,
+                (None, false),
+                None,
+            ));
+            let result = serve_host(
+                player
+                    .recv()
+                    .await
+                    .ok_or("the endpoint closed before anyone connected")?,
+                &mut requests,
+                &mut opened,
+            )
+            .await;
+            accept_task.abort();
+            result
+        }
+        Config::Restore(_) => Err("sessions cannot be restored from a snapshot".into()),
+    }
+}
+
+/// Connect to the host, then open a stream for each game the user opens.
+async fn serve_client(
+    host_addr: NodeAddr,
+    requests: &mut UnboundedReceiver<(u32, StreamReply)>,
+    opened: &mut HashSet<u32>,
+) -> Result<(), BoxError> {
+    let endpoint = Endpoint::builder().discovery_n0().bind().await?;
+    let connection = endpoint
+        .connect(host_addr, SESSION_ALPN)
+        .await
+        .map_err(|e| protocol::connect_error(e, SESSION_ALPN))?;
+
+    while let Some((game_id, reply)) = requests.recv().await {
+        if !opened.insert(game_id) {
+            let _ = reply.send(Err(format!("game {game_id} is already open")));
+            continue;
+        }
+        let (mut send, recv) = connection.open_bi().await?;
+        // the host can only accept the stream once we write to it.
+        Frame::OpenGame(game_id).write(&mut send).await?;
+        let _ = reply.send(Ok((send, recv)));
+    }
+    Ok(())
+}
+
+/// Hand each stream the client opens to the game it names, once the user
+/// opens that game.
+async fn serve_host(
+    connection: Connection,
+    requests: &mut UnboundedReceiver<(u32, StreamReply)>,
+    opened: &mut HashSet<u32>,
+) -> Result<(), BoxError> {
+    // games the user opened, waiting on the client's stream.
+    let mut waiting = HashMap::new();
+    // streams the client opened, waiting on the user to open their game.
+    let mut arrived = HashMap::new();
+    let (send_named, mut named) = mpsc::unbounded_channel();
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                let Some((game_id, reply)) = request else {
+                    // the session was dropped.
+                    return Ok(());
+                };
+                if !opened.insert(game_id) {
+                    let _ = reply.send(Err(format!("game {game_id} is already open")));
+                } else if let Some(stream) = arrived.remove(&game_id) {
+                    let _ = reply.send(Ok(stream));
+                } else {
+                    waiting.insert(game_id, reply);
+                }
+            }
+            stream = connection.accept_bi() => {
+                let (send, mut recv) = stream?;
+                let send_named = send_named.clone();
+                // a slow client must not hold up the other games' streams.
+                task::spawn(async move {
+                    match Frame::read(&mut recv).await {
+                        Ok(Frame::OpenGame(game_id)) => {
+                            let _ = send_named.send((game_id, (send, recv)));
+                        }
+                        _ => tracing::debug!("the other player opened a stream without naming its game"),
+                    }
+                });
+            }
+            Some((game_id, stream)) = named.recv() => {
+                match waiting.remove(&game_id) {
+                    Some(reply) => {
+                        let _ = reply.send(Ok(stream));
+                    }
+                    None => {
+                        arrived.insert(game_id, stream);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Using an interface through a shared reference, for GUI frameworks that only
+//! pass `&` references into event handlers.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{NetcodeInterface, TurnPoll};
+
+/// A [`NetcodeInterface`] that can be used through a shared reference.
+///
+/// Create one with [`NetcodeInterface::into_shared`]. It is [`Sync`], so it
+/// can also be put in an [`Arc`](std::sync::Arc) and shared between threads.
+/// Each call locks the interface for as long as it runs, so handlers on
+/// different threads take turns.
+pub struct SharedNetcodeInterface<const SIZE: usize> {
+    inner: Mutex<NetcodeInterface<SIZE>>,
+}
+
+impl<const SIZE: usize> SharedNetcodeInterface<SIZE> {
+    pub(crate) fn new(netcode: NetcodeInterface<SIZE>) -> Self {
+        Self {
+            inner: Mutex::new(netcode),
+        }
+    }
+
+    /// Check if the other player has sent a turn to the user, like
+    /// [`NetcodeInterface::try_recv_turn`].
+    ///
+    /// Unlike it, returns [`TurnPoll::Pending`] if it is the user's turn,
+    /// rather than panicking, since another handler may have received the
+    /// turn first.
+    #[must_use = "check whether a turn was received before calling game logic"]
+    pub fn try_recv_turn(&self) -> TurnPoll<SIZE> {
+        let mut netcode = self.lock();
+        if netcode.my_turn() {
+            return TurnPoll::Pending;
+        }
+        netcode.try_recv_turn()
+    }
+
+    /// Send a turn to the other player, like [`NetcodeInterface::send_turn`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not the user's turn, like
+    /// [`NetcodeInterface::send_turn`].
+    pub fn send_turn(&self, turn: &[u8; SIZE]) {
+        self.lock().send_turn(turn);
+    }
+
+    /// Return whether it is the user's turn, like
+    /// [`NetcodeInterface::my_turn`].
+    pub fn my_turn(&self) -> bool {
+        self.lock().my_turn()
+    }
+
+    /// Lock the interface, to call anything else on it.
+    ///
+    /// Other handlers wait until the guard is dropped, so do not hold it for
+    /// long.
+    pub fn lock(&self) -> MutexGuard<'_, NetcodeInterface<SIZE>> {
+        // the interface checks its invariants before changing anything, so
+        // it is still usable after a handler panicked by misusing it.
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Return the interface, to use it through `&mut` references again.
+    pub fn into_inner(self) -> NetcodeInterface<SIZE> {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,188 @@
+//! An interface for games where both players choose their turns secretly,
+//! then reveal them at the same time.
+
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+
+use crate::{
+    Config, ConnectionState, NetcodeError, Reconnect, Role,
+    frame::ControlFrame,
+    protocol::{self, Move, ProtocolHandles, ProtocolOptions, Transport},
+};
+
+/// The user's turn and the other player's turn, in that order.
+pub type Round<const SIZE: usize> = ([u8; SIZE], [u8; SIZE]);
+
+/// The interface for netcode in games with simultaneous rounds.
+///
+/// Like [`NetcodeInterface`](crate::NetcodeInterface), runs iroh under the hood
+/// in a separate thread, so it must be created in the context of a Tokio
+/// runtime, and one player must be the host while the other is the client.
+/// Both players must use a `SimultaneousInterface`.
+///
+/// Each round, both players:
+///
+/// - [`submit_turn`][`SimultaneousInterface::submit_turn`] once
+/// - [`try_recv_round`][`SimultaneousInterface::try_recv_round`] repeatedly
+/// - once it returns `Ok(Some(_))`, the next round begins.
+///
+/// Under the hood, each player first sends a hash of their salted turn, and
+/// only reveals the turn once they have received the other player's hash. So,
+/// neither player can choose their turn after seeing the other's.
+///
+/// Deviations from this procedure are undefined behavior.
+pub struct SimultaneousInterface<const SIZE: usize> {
+    /// The salt and turn we submitted this round, if any.
+    submitted: Option<([u8; 32], [u8; SIZE])>,
+    /// Whether we revealed our turn this round.
+    revealed: bool,
+    their_commitment: Option<[u8; 32]>,
+    their_reveal: Option<Vec<u8>>,
+    recv_control_from_iroh: mpsc::UnboundedReceiver<ControlFrame>,
+    send_control_to_iroh: mpsc::UnboundedSender<ControlFrame>,
+    connection_state: watch::Receiver<ConnectionState>,
+    /// Turns are never sent through the turn channel, but closing it would
+    /// stop the protocol.
+    _send_to_iroh: mpsc::Sender<Move<SIZE>>,
+    _recv_from_iroh: mpsc::UnboundedReceiver<(Instant, Move<SIZE>)>,
+    /// A handle to the thread running iroh under the hood.
+    _iroh_handle: JoinHandle<()>,
+}
+
+impl<const SIZE: usize> SimultaneousInterface<SIZE> {
+    /// Create a new interface.
+    ///
+    /// See the struct's [`docs`][`SimultaneousInterface`] for invariants.
+    pub fn new(config: Config) -> Self {
+        let ProtocolHandles {
+            recv_from_iroh,
+            send_to_iroh,
+            recv_control_from_iroh,
+            send_control_to_iroh,
+            connection_state,
+            iroh_handle,
+            ..
+        } = protocol::spawn_iroh_protocol(
+            Transport::Config(config),
+            ProtocolOptions {
+                // nobody moves first, but both players must still agree.
+                first_move: Some(Role::Client),
+                check_internet: false,
+                internet_check_target: None,
+                relay_url: None,
+                reconnect: Reconnect::Disabled,
+                max_spectators: None,
+                simultaneous: true,
+                encoder: None,
+                compression: None,
+                size: SIZE,
+                turn_sizes: (SIZE, SIZE),
+                time_control: None,
+                setup: None,
+                metadata: Default::default(),
+            },
+        );
+
+        Self {
+            submitted: None,
+            revealed: false,
+            their_commitment: None,
+            their_reveal: None,
+            recv_control_from_iroh,
+            send_control_to_iroh,
+            connection_state,
+            _send_to_iroh: send_to_iroh,
+            _recv_from_iroh: recv_from_iroh,
+            _iroh_handle: iroh_handle,
+        }
+    }
+
+    /// Submit the user's turn for this round.
+    ///
+    /// Only a hash of the turn is sent until the other player submits theirs.
+    ///
+    /// See the struct's [`docs`][`SimultaneousInterface`] for invariants.
+    pub fn submit_turn(&mut self, turn: &[u8; SIZE]) {
+        assert!(self.submitted.is_none());
+        let salt: [u8; 32] = rand::random();
+        self.send_control(ControlFrame::RoundCommit(commitment(&salt, turn)));
+        self.submitted = Some((salt, *turn));
+    }
+
+    /// Check if the round is over.
+    ///
+    /// Returns the [`Round`] once both players have submitted and revealed their turns. Returns
+    /// [`NetcodeError::RevealMismatch`] if the other player revealed a turn
+    /// other than the one they committed to.
+    ///
+    /// See the struct's [`docs`][`SimultaneousInterface`] for invariants.
+    pub fn try_recv_round(&mut self) -> Result<Option<Round<SIZE>>, NetcodeError> {
+        // anything past the other player's reveal belongs to the next round,
+        // so we leave it in the channel.
+        while self.their_reveal.is_none()
+            && let Ok(control) = self.recv_control_from_iroh.try_recv()
+        {
+            match control {
+                ControlFrame::RoundCommit(hash) => self.their_commitment = Some(hash),
+                ControlFrame::RoundReveal(reveal) => self.their_reveal = Some(reveal),
+                // only sent when alternating turns, which the handshake rules out.
+                _ => {}
+            }
+        }
+
+        let Some((salt, ours)) = self.submitted else {
+            return Ok(None);
+        };
+        let Some(their_commitment) = self.their_commitment else {
+            return Ok(None);
+        };
+        if !self.revealed {
+            self.send_control(ControlFrame::RoundReveal([&salt[..], &ours].concat()));
+            self.revealed = true;
+        }
+        let Some(their_reveal) = self.their_reveal.take() else {
+            return Ok(None);
+        };
+
+        self.submitted = None;
+        self.revealed = false;
+        self.their_commitment = None;
+        let (their_salt, theirs) = their_reveal.split_at_checked(32).unwrap_or_default();
+        match (
+            <[u8; 32]>::try_from(their_salt),
+            <[u8; SIZE]>::try_from(theirs),
+        ) {
+            (Ok(their_salt), Ok(theirs))
+                if commitment(&their_salt, &theirs) == their_commitment =>
+            {
+                Ok(Some((ours, theirs)))
+            }
+            _ => Err(NetcodeError::RevealMismatch),
+        }
+    }
+
+    /// Return the current state of the connection.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection_state.borrow().clone()
+    }
+
+    fn send_control(&self, control: ControlFrame) {
+        self.send_control_to_iroh
+            .send(control)
+            .expect("the iroh protocol should still be running");
+    }
+}
+
+/// The hash a player commits to before revealing their salted turn.
+fn commitment(salt: &[u8; 32], turn: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(salt)
+        .chain_update(turn)
+        .finalize()
+        .into()
+}
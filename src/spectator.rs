@@ -0,0 +1,185 @@
+//! Read-only spectators that watch a game live.
+//!
+//! Spectators connect to the host with their own ALPN, so the host can tell them
+//! apart from the other player. The host then forwards every turn exchanged
+//! after the spectator connected.
+
+use std::{fmt, str::FromStr};
+
+use iroh::{Endpoint, NodeAddr, endpoint::Connection};
+use iroh_base::ticket::NodeTicket;
+use tokio::{
+    sync::{
+        OwnedSemaphorePermit, broadcast,
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        watch,
+    },
+    task::{self, JoinHandle},
+};
+
+use crate::{
+    ConnectionState, NetcodeError, Role,
+    frame::{Frame, Hello},
+    protocol::{self, BoxError},
+    transfer::{self, Reassembly},
+};
+
+/// ALPN string for spectators of the sfn-tpn protocol.
+///
+/// See [`ALPN`](crate::protocol::ALPN).
+pub const SPECTATOR_ALPN: &[u8] = b"saffron/sfn-tpn/spectator/1";
+
+/// A ticket that lets a [`SpectatorInterface`] watch a game.
+///
+/// The host's [`NetcodeInterface`](crate::NetcodeInterface) generates one if
+/// spectators are [allowed](crate::NetcodeInterfaceBuilder::allow_spectators).
+/// It converts to and from a string so it can be shared like a player's ticket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpectatorTicket(String);
+
+impl SpectatorTicket {
+    pub(crate) fn new(ticket: &NodeTicket) -> Self {
+        Self(ticket.to_string())
+    }
+}
+
+impl fmt::Display for SpectatorTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for SpectatorTicket {
+    type Err = NetcodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NodeTicket::from_str(s)
+            .map_err(|e| NetcodeError::Disconnected(format!("invalid spectator ticket: {e}")))?;
+        Ok(Self(s.to_string()))
+    }
+}
+
+/// The interface for spectating a game.
+///
+/// Like [`NetcodeInterface`](crate::NetcodeInterface), runs iroh under the hood
+/// in a separate thread, so it must be created in the context of a Tokio
+/// runtime. Spectators can only watch; they never send turns.
+///
+/// Only turns exchanged after the spectator connected are received.
+pub struct SpectatorInterface<const SIZE: usize> {
+    recv_from_iroh: UnboundedReceiver<(Role, [u8; SIZE])>,
+    connection_state: watch::Receiver<ConnectionState>,
+    /// A handle to the thread running iroh under the hood.
+    _iroh_handle: JoinHandle<()>,
+}
+
+impl<const SIZE: usize> SpectatorInterface<SIZE> {
+    /// Start spectating the game the ticket was generated for.
+    pub fn new(ticket: SpectatorTicket) -> Self {
+        let (send_to_game, recv_from_iroh) = mpsc::unbounded_channel();
+        let (state_sender, connection_state) = watch::channel(ConnectionState::Connecting);
+        let _iroh_handle = task::spawn(async move {
+            if let Err(e) = spectate(ticket, send_to_game, &state_sender).await {
+                protocol::report_stopped(&state_sender, e);
+            }
+        });
+
+        Self {
+            recv_from_iroh,
+            connection_state,
+            _iroh_handle,
+        }
+    }
+
+    /// Check if either player has taken a turn.
+    ///
+    /// Returns which player took the turn, and the turn itself. Turns are
+    /// returned in the order they were taken.
+    pub fn try_recv_turn(&mut self) -> Option<(Role, [u8; SIZE])> {
+        self.recv_from_iroh.try_recv().ok()
+    }
+
+    /// Return the current state of the connection to the host.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection_state.borrow().clone()
+    }
+}
+
+/// Connect to the host and forward every turn we are shown to the game.
+async fn spectate<const SIZE: usize>(
+    ticket: SpectatorTicket,
+    send_to_game: UnboundedSender<(Role, [u8; SIZE])>,
+    state: &watch::Sender<ConnectionState>,
+) -> Result<(), BoxError> {
+    let endpoint = Endpoint::builder().discovery_n0().bind().await?;
+    let host_addr = NodeAddr::from(NodeTicket::from_str(&ticket.0)?);
+    let conn = endpoint
+        .connect(host_addr, SPECTATOR_ALPN)
+        .await
+        .map_err(|e| protocol::connect_error(e, SPECTATOR_ALPN))?;
+    let mut recv = conn.accept_uni().await?;
+
+    let Frame::Hello(hello) = Frame::read(&mut recv).await? else {
+        return Err("the host did not say hello".into());
+    };
+    if hello.size != u32::try_from(SIZE)? {
+        return Err(NetcodeError::HandshakeFailed(format!(
+            "our turns are {SIZE} bytes, but the host's are {} bytes",
+            hello.size
+        ))
+        .into());
+    }
+    state.send_replace(ConnectionState::Connected);
+
+    let mut reassembly = Reassembly::default();
+    loop {
+        let frame = match Frame::read(&mut recv).await? {
+            Frame::Chunk { last, bytes } => match reassembly.push(last, &bytes)? {
+                Some(frame) => frame,
+                None => continue,
+            },
+            frame => frame,
+        };
+        let Frame::Spectated { side, turn } = frame else {
+            return Err("the host sent something other than a turn".into());
+        };
+        let turn: [u8; SIZE] = turn
+            .try_into()
+            .map_err(|_| "the host sent a turn of the wrong size")?;
+        if send_to_game.send((side, turn)).is_err() {
+            // the game stopped spectating
+            return Ok(());
+        }
+    }
+}
+
+/// Host side: forward turns to a spectator until either side hangs up.
+///
+/// Holds onto `_permit` so the spectator counts against the maximum.
+pub(crate) async fn serve_spectator<const SIZE: usize>(
+    connection: Connection,
+    hello: Hello,
+    mut turns: broadcast::Receiver<(Role, [u8; SIZE])>,
+    _permit: OwnedSemaphorePermit,
+) -> Result<(), BoxError> {
+    let mut send = connection.open_uni().await?;
+    // the rest of each turn is never sent to the other player either.
+    let size = usize::try_from(hello.size)?;
+    Frame::Hello(hello).write(&mut send).await?;
+
+    loop {
+        match turns.recv().await {
+            Ok((side, turn)) => {
+                let frame = Frame::Spectated {
+                    side,
+                    turn: turn[..size].to_vec(),
+                };
+                transfer::write(&mut send, &frame).await?;
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            Err(broadcast::error::RecvError::Lagged(_)) => {
+                return Err("the spectator fell too far behind".into());
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Splitting an interface into a half that sends turns and a half that
+//! receives them, for games that own each in a different task.
+//!
+//! Whose turn it is and whether we are connected are shared between the
+//! halves through watches, so checking them never locks. Sending and
+//! receiving a turn still lock the interface for as long as the call runs:
+//! both update the same plies, clocks, history, and takeback, pause, and
+//! suspend requests, and receiving drains control frames that decide whose
+//! turn it is. Giving each half its own copy of that state would let the
+//! copies disagree, so the halves take turns with it instead, and never
+//! hold the lock while waiting.
+
+use std::sync::{Arc, MutexGuard};
+
+use tokio::sync::watch;
+
+use crate::{ConnectionState, NetcodeError, NetcodeInterface, SharedNetcodeInterface, TurnPoll};
+
+/// The half of a [split](NetcodeInterface::split) interface that sends the
+/// user's turns.
+pub struct TurnSender<const SIZE: usize> {
+    shared: Arc<SharedNetcodeInterface<SIZE>>,
+    my_turn: watch::Receiver<bool>,
+}
+
+/// The half of a [split](NetcodeInterface::split) interface that receives
+/// the other player's turns.
+pub struct TurnReceiver<const SIZE: usize> {
+    shared: Arc<SharedNetcodeInterface<SIZE>>,
+    my_turn: watch::Receiver<bool>,
+    connection_state: watch::Receiver<ConnectionState>,
+}
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Split the interface into a half that sends the user's turns and a
+    /// half that receives the other player's, so each can be owned by a
+    /// different task, like game logic and networking.
+    ///
+    /// The halves share the interface, and each locks it only for as long
+    /// as a call runs, never while waiting. Put them back together with
+    /// [`TurnSender::reunite`].
+    ///
+    /// The same split is available as a conversion, with
+    /// `let (sender, receiver) = netcode.into();`.
+    pub fn split(mut self) -> (TurnSender<SIZE>, TurnReceiver<SIZE>) {
+        // the watch only starts mirroring the turn once who moves first is
+        // known.
+        self.resolve_first_move();
+        let my_turn = self.my_turn_watch();
+        let connection_state = self.connection_state.clone();
+        let shared = Arc::new(self.into_shared());
+        (
+            TurnSender {
+                shared: shared.clone(),
+                my_turn: my_turn.clone(),
+            },
+            TurnReceiver {
+                shared,
+                my_turn,
+                connection_state,
+            },
+        )
+    }
+}
+
+impl<const SIZE: usize> From<NetcodeInterface<SIZE>> for (TurnSender<SIZE>, TurnReceiver<SIZE>) {
+    /// Split the interface, like [`NetcodeInterface::split`].
+    fn from(netcode: NetcodeInterface<SIZE>) -> Self {
+        netcode.split()
+    }
+}
+
+impl<const SIZE: usize> From<(TurnSender<SIZE>, TurnReceiver<SIZE>)> for NetcodeInterface<SIZE> {
+    /// Put the halves back together, like [`TurnSender::reunite`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the halves were split from different interfaces.
+    fn from((sender, receiver): (TurnSender<SIZE>, TurnReceiver<SIZE>)) -> Self {
+        sender.reunite(receiver)
+    }
+}
+
+impl<const SIZE: usize> TurnSender<SIZE> {
+    /// Send a turn to the other player, like
+    /// [`NetcodeInterface::send_turn`].
+    ///
+    /// Returns [`NetcodeError::NotYourTurn`] instead of panicking if it is
+    /// not the user's turn, like when the [`TurnReceiver`] has yet to
+    /// receive the other player's turn. Also returns
+    /// [`NetcodeError::Paused`] if the game is
+    /// [paused](NetcodeInterface::request_pause), or
+    /// [`NetcodeError::GameOver`] if either player
+    /// [finished](NetcodeInterface::finish_game) the game.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the user asked to [suspend](NetcodeInterface::suspend) and
+    /// has yet to hear back.
+    pub fn send_turn(&mut self, turn: &[u8; SIZE]) -> Result<(), NetcodeError> {
+        let mut netcode = self.shared.lock();
+        netcode.drain_controls();
+        if netcode.finished {
+            return Err(NetcodeError::GameOver);
+        }
+        netcode.resolve_first_move();
+        if !netcode.my_turn() {
+            return Err(NetcodeError::NotYourTurn);
+        }
+        if netcode.pause.paused() {
+            return Err(NetcodeError::Paused);
+        }
+        netcode.send_turn(turn);
+        Ok(())
+    }
+
+    /// Return whether it is the user's turn, like
+    /// [`NetcodeInterface::my_turn`], without locking the interface.
+    pub fn my_turn(&self) -> bool {
+        *self.my_turn.borrow()
+    }
+
+    /// Lock the interface, to call anything else on it, like
+    /// [`SharedNetcodeInterface::lock`].
+    ///
+    /// The [`TurnReceiver`] waits until the guard is dropped, so do not hold
+    /// it for long.
+    pub fn lock(&self) -> MutexGuard<'_, NetcodeInterface<SIZE>> {
+        self.shared.lock()
+    }
+
+    /// Put the halves of a split interface back together.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `receiver` was split from a different interface.
+    pub fn reunite(self, receiver: TurnReceiver<SIZE>) -> NetcodeInterface<SIZE> {
+        assert!(
+            Arc::ptr_eq(&self.shared, &receiver.shared),
+            "the halves were split from different interfaces"
+        );
+        drop(receiver);
+        Arc::into_inner(self.shared)
+            .expect("only the two halves hold the interface")
+            .into_inner()
+    }
+}
+
+impl<const SIZE: usize> TurnReceiver<SIZE> {
+    /// Wait for the other player's turn, like
+    /// [`NetcodeInterface::recv_turn`].
+    ///
+    /// While it is the user's turn, this waits for the [`TurnSender`] to send
+    /// one first, rather than returning. Returns `Ok(None)` if it became the
+    /// user's turn without a turn from the other player, because they passed,
+    /// or a takeback was accepted. Returns the error that disconnected us if
+    /// the connection is lost first.
+    ///
+    /// This is cancellation safe, like `recv_turn`.
+    pub async fn recv_turn(&mut self) -> Result<Option<[u8; SIZE]>, NetcodeError> {
+        loop {
+            let notified = {
+                let mut netcode = self.shared.lock();
+                if !netcode.my_turn() {
+                    match netcode.try_recv_turn() {
+                        TurnPoll::Ready(turn) => return Ok(Some(turn)),
+                        TurnPoll::OpponentPassed => return Ok(None),
+                        // a takeback was accepted.
+                        _ if netcode.my_turn() => return Ok(None),
+                        TurnPoll::Pending | TurnPoll::WaitingTooLong => {}
+                    }
+                }
+                if let ConnectionState::Disconnected(e) = netcode.connection_state() {
+                    return Err(e);
+                }
+                if netcode.connection_state.has_changed().is_err() {
+                    return Err(NetcodeError::Disconnected(
+                        "the iroh protocol stopped".to_string(),
+                    ));
+                }
+                if netcode.my_turn() {
+                    netcode.publish_my_turn();
+                    None
+                } else {
+                    Some(netcode.turn_notified())
+                }
+            };
+            match notified {
+                Some(notified) => notified.await,
+                // the sender flips the turn once it sends, which the watch
+                // shows without the lock.
+                None => {
+                    tokio::select! {
+                        _ = self.my_turn.wait_for(|&mine| !mine) => {}
+                        _ = self.connection_state.wait_for(|s| matches!(s, ConnectionState::Disconnected(_))) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return whether it is the user's turn, like
+    /// [`NetcodeInterface::my_turn`], without locking the interface.
+    pub fn my_turn(&self) -> bool {
+        *self.my_turn.borrow()
+    }
+
+    /// Lock the interface, to call anything else on it, like
+    /// [`SharedNetcodeInterface::lock`].
+    ///
+    /// The [`TurnSender`] waits until the guard is dropped, so do not hold it
+    /// for long.
+    pub fn lock(&self) -> MutexGuard<'_, NetcodeInterface<SIZE>> {
+        self.shared.lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::{mpsc, oneshot};
+
+    use super::*;
+    use crate::Config;
+
+    /// The number of the last turn played, counting from zero.
+    const LAST: u8 = 9;
+
+    /// Play a player's half of the game: each turn is one more than the
+    /// last, with the receiving half on a task of its own, handing the other
+    /// player's turns to the sending half.
+    async fn play(netcode: NetcodeInterface<1>) -> NetcodeInterface<1> {
+        let (mut sender, mut receiver) = netcode.into();
+        let moves_first = sender.my_turn();
+        let received = (LAST / 2 + 1) as usize;
+        let (send_turn, mut turns) = mpsc::unbounded_channel();
+        let receiving = tokio::spawn(async move {
+            for _ in 0..received {
+                let turn = receiver.recv_turn().await.unwrap().unwrap();
+                send_turn.send(turn).unwrap();
+            }
+            receiver
+        });
+
+        if moves_first {
+            sender.send_turn(&[0]).unwrap();
+            assert!(matches!(
+                sender.send_turn(&[0]),
+                Err(NetcodeError::NotYourTurn)
+            ));
+        }
+        let mut expected = u8::from(moves_first);
+        while let Some([turn]) = turns.recv().await {
+            assert_eq!(turn, expected);
+            if turn < LAST {
+                sender.send_turn(&[turn + 1]).unwrap();
+            }
+            expected += 2;
+        }
+        (sender, receiving.await.unwrap()).into()
+    }
+
+    #[tokio::test]
+    async fn halves_play_a_loopback_game() {
+        let (ticket_sender, ticket) = oneshot::channel();
+        let host = NetcodeInterface::new(Config::TicketSender(ticket_sender));
+        let client = NetcodeInterface::new(Config::Ticket(ticket.await.unwrap()));
+        let (host, client) = tokio::join!(play(host), play(client));
+        assert_eq!(host.ply(), u64::from(LAST) + 1);
+        assert_eq!(client.ply(), u64::from(LAST) + 1);
+        // an even number of turns were played, so the client, who moves
+        // first by default, is up again.
+        assert!(client.my_turn() && !host.my_turn());
+    }
+}
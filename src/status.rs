@@ -0,0 +1,123 @@
+//! A read-only view of a session, for the parts of a game that do not own
+//! the interface, like a HUD or a debug overlay.
+
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::{ConnectionState, NetcodeError, NetcodeInterface};
+
+/// A read-only view of an interface's connection and turn, for the parts of
+/// a game that do not own it, like a HUD, a pause menu, or a debug overlay.
+///
+/// Created with [`NetcodeInterface::status`]. It is cheap to clone, can be
+/// sent to other threads, and can never change the session. Whose turn it
+/// is and the ply are updated by whichever call on the interface changes
+/// them, like [`my_turn_watch`](NetcodeInterface::my_turn_watch). Once the
+/// interface is dropped, it reports the connection as
+/// [`Disconnected`](ConnectionState::Disconnected).
+#[derive(Debug, Clone)]
+pub struct NetcodeStatus {
+    state: watch::Receiver<ConnectionState>,
+    rtt: watch::Receiver<Option<Duration>>,
+    my_turn: watch::Receiver<bool>,
+    ply: watch::Receiver<u64>,
+}
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Return a read-only view of the connection and turn, to hand to the
+    /// parts of a game that do not own the interface.
+    pub fn status(&self) -> NetcodeStatus {
+        NetcodeStatus {
+            state: self.connection_state.clone(),
+            rtt: self.rtt.clone(),
+            my_turn: self.my_turn_watch.subscribe(),
+            ply: self.ply_watch.subscribe(),
+        }
+    }
+}
+
+impl NetcodeStatus {
+    /// Return the current state of the connection, like
+    /// [`NetcodeInterface::connection_state`].
+    pub fn state(&self) -> ConnectionState {
+        if self.dropped() {
+            return ConnectionState::Disconnected(NetcodeError::Disconnected(
+                "the interface was dropped".to_string(),
+            ));
+        }
+        self.state.borrow().clone()
+    }
+
+    /// Return whether the user is connected to the other player.
+    pub fn is_connected(&self) -> bool {
+        self.state() == ConnectionState::Connected
+    }
+
+    /// Return whether it is the user's turn, like
+    /// [`NetcodeInterface::my_turn`]. Always `false` once the interface is
+    /// dropped.
+    pub fn my_turn(&self) -> bool {
+        !self.dropped() && *self.my_turn.borrow()
+    }
+
+    /// Return how many moves and passes have been made in the current game,
+    /// like [`NetcodeInterface::ply`].
+    pub fn ply(&self) -> u64 {
+        *self.ply.borrow()
+    }
+
+    /// Return the connection's latest round-trip time, like
+    /// [`NetcodeInterface::rtt`], or `None` if it is not connected.
+    pub fn latency(&self) -> Option<Duration> {
+        if !self.is_connected() {
+            return None;
+        }
+        *self.rtt.borrow()
+    }
+
+    /// Return whether the interface was dropped.
+    fn dropped(&self) -> bool {
+        // only the interface holds the sender.
+        self.my_turn.has_changed().is_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{connect, recv};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn status_follows_the_interface() {
+        let (mut host, mut client) = connect::<1>().await;
+        let (host_status, client_status) = (host.status(), client.status());
+        assert!(host_status.is_connected() && client_status.is_connected());
+        assert!(client_status.my_turn() && !host_status.my_turn());
+        assert!(client_status.latency().is_some());
+
+        client.send_turn(&[1]);
+        assert!(!client_status.my_turn());
+        assert_eq!(client_status.ply(), 1);
+        // the host's status flips once its game receives the move.
+        recv(&mut host).await;
+        let clone = host_status.clone();
+        let seen = std::thread::spawn(move || (clone.my_turn(), clone.ply()));
+        assert_eq!(seen.join().unwrap(), (true, 1));
+    }
+
+    #[tokio::test]
+    async fn status_reports_a_dropped_interface_as_disconnected() {
+        let (_host, client) = connect::<1>().await;
+        let status = client.status();
+        assert!(status.my_turn());
+        drop(client);
+        assert!(matches!(
+            status.state(),
+            ConnectionState::Disconnected(NetcodeError::Disconnected(_))
+        ));
+        assert!(!status.is_connected() && !status.my_turn());
+        assert_eq!(status.latency(), None);
+    }
+}
@@ -0,0 +1,326 @@
+//! Receiving the other player's turns as a [`Stream`], to merge them with
+//! other streams, like UI events and timers, and sending the user's as a
+//! [`Sink`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker, ready},
+};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use crate::{ConnectionState, NetcodeError, NetcodeInterface, TurnPoll};
+
+/// Waiting for the next turn, holding the interface until it is done.
+type NextTurn<'a, const SIZE: usize> = Pin<
+    Box<
+        dyn Future<
+                Output = (
+                    Result<TurnPoll<SIZE>, NetcodeError>,
+                    &'a mut NetcodeInterface<SIZE>,
+                ),
+            > + 'a,
+    >,
+>;
+
+/// Sending the user's turn, holding the interface until it is written.
+type SendTurn<'a, const SIZE: usize> =
+    Pin<Box<dyn Future<Output = (Result<(), NetcodeError>, &'a mut NetcodeInterface<SIZE>)> + 'a>>;
+
+/// The other player's turns, as a [`Stream`].
+///
+/// Created with [`NetcodeInterface::incoming`].
+pub struct IncomingTurns<'a, const SIZE: usize> {
+    state: State<'a, SIZE>,
+}
+
+enum State<'a, const SIZE: usize> {
+    Idle(&'a mut NetcodeInterface<SIZE>),
+    Waiting(NextTurn<'a, SIZE>),
+    Done,
+}
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Return the other player's turns as a [`Stream`], to merge them with
+    /// other streams, like UI events.
+    ///
+    /// Each item is what [`recv_turn`](NetcodeInterface::recv_turn) would
+    /// have returned. The stream ends once it is the user's turn, since the
+    /// user cannot move while it borrows the interface, so drop it, move,
+    /// then call `incoming` again. It also ends after the error that
+    /// disconnected us.
+    ///
+    /// Like `recv_turn`, it is cancellation safe: a turn that arrives as the
+    /// stream is polled, or dropped, is never lost.
+    ///
+    /// Requires the `stream` feature.
+    pub fn incoming(&mut self) -> IncomingTurns<'_, SIZE> {
+        IncomingTurns {
+            state: State::Idle(self),
+        }
+    }
+
+    /// Return both players' turns as a [`Stream`] of the other player's,
+    /// and a [`Sink`] for the user's, to build the game out of combinators
+    /// like `forward` and `send_all`.
+    ///
+    /// The stream yields what [`incoming`](NetcodeInterface::incoming)
+    /// would, but waits while it is the user's turn instead of ending, until
+    /// they send one through the sink. The sink is only ready on the user's
+    /// turn, so it waits for the stream to receive the other player's turn
+    /// first, and the stream must be polled for that to happen. Sending a
+    /// turn is like [`send_turn_checked`](NetcodeInterface::send_turn_checked),
+    /// and flushing waits until it was written to the connection in full.
+    ///
+    /// Requires the `stream` feature.
+    pub fn turns(&mut self) -> Turns<'_, SIZE> {
+        Turns {
+            state: TurnsState::Idle(self),
+            failed: None,
+            sent: None,
+            my_turn: None,
+        }
+    }
+
+    /// Wait until every move the user sent was written to the connection in
+    /// full.
+    async fn written(&self) -> Result<(), NetcodeError> {
+        let sent = self.sent_moves;
+        let mut written = self.written.clone();
+        let mut state = self.connection_state.clone();
+        tokio::select! {
+            biased;
+            Ok(_) = written.wait_for(|&written| written >= sent) => Ok(()),
+            state = state.wait_for(|s| matches!(s, ConnectionState::Disconnected(_))) => {
+                Err(match state.as_deref() {
+                    Ok(ConnectionState::Disconnected(e)) => e.clone(),
+                    _ => NetcodeError::Disconnected("the iroh protocol stopped".to_string()),
+                })
+            }
+        }
+    }
+}
+
+/// What a stream of turns yields for what
+/// [`wait_for_turn`](NetcodeInterface::wait_for_turn) returned.
+fn item<const SIZE: usize>(
+    result: Result<TurnPoll<SIZE>, NetcodeError>,
+) -> Result<Option<[u8; SIZE]>, NetcodeError> {
+    match result {
+        Ok(TurnPoll::Ready(turn)) => Ok(Some(turn)),
+        // the other player passed, or a takeback was accepted.
+        Ok(_) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl<const SIZE: usize> Stream for IncomingTurns<'_, SIZE> {
+    type Item = Result<Option<[u8; SIZE]>, NetcodeError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match std::mem::replace(&mut self.state, State::Done) {
+                State::Idle(netcode) => {
+                    if netcode.my_turn() {
+                        return Poll::Ready(None);
+                    }
+                    self.state = State::Waiting(Box::pin(async move {
+                        (netcode.wait_for_turn().await, netcode)
+                    }));
+                }
+                State::Waiting(mut next) => {
+                    let (result, netcode) = match next.as_mut().poll(cx) {
+                        Poll::Ready(done) => done,
+                        Poll::Pending => {
+                            self.state = State::Waiting(next);
+                            return Poll::Pending;
+                        }
+                    };
+                    if result.is_ok() {
+                        self.state = State::Idle(netcode);
+                    }
+                    return Poll::Ready(Some(item(result)));
+                }
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Both players' turns, as a [`Stream`] of the other player's and a
+/// [`Sink`] for the user's.
+///
+/// Created with [`NetcodeInterface::turns`].
+pub struct Turns<'a, const SIZE: usize> {
+    state: TurnsState<'a, SIZE>,
+    /// The error of a send the stream finished, for the sink to return.
+    failed: Option<NetcodeError>,
+    /// The stream, waiting for the user to send a turn.
+    sent: Option<Waker>,
+    /// The sink, waiting for it to be the user's turn.
+    my_turn: Option<Waker>,
+}
+
+enum TurnsState<'a, const SIZE: usize> {
+    Idle(&'a mut NetcodeInterface<SIZE>),
+    Waiting(NextTurn<'a, SIZE>),
+    Sending(SendTurn<'a, SIZE>),
+    Done,
+}
+
+impl<const SIZE: usize> Turns<'_, SIZE> {
+    /// Finish sending the user's turn, if they are.
+    fn poll_sent(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let TurnsState::Sending(send) = &mut self.state {
+            let (result, netcode) = ready!(send.as_mut().poll(cx));
+            self.state = TurnsState::Idle(netcode);
+            self.failed = result.err();
+            if let Some(waker) = self.sent.take() {
+                waker.wake();
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+impl<const SIZE: usize> Stream for Turns<'_, SIZE> {
+    type Item = Result<Option<[u8; SIZE]>, NetcodeError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        ready!(self.poll_sent(cx));
+        loop {
+            match std::mem::replace(&mut self.state, TurnsState::Done) {
+                TurnsState::Idle(netcode) => {
+                    if netcode.my_turn() {
+                        self.state = TurnsState::Idle(netcode);
+                        self.sent = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                    self.state = TurnsState::Waiting(Box::pin(async move {
+                        (netcode.wait_for_turn().await, netcode)
+                    }));
+                }
+                TurnsState::Waiting(mut next) => {
+                    let (result, netcode) = match next.as_mut().poll(cx) {
+                        Poll::Ready(done) => done,
+                        Poll::Pending => {
+                            self.state = TurnsState::Waiting(next);
+                            return Poll::Pending;
+                        }
+                    };
+                    if result.is_ok() {
+                        self.state = TurnsState::Idle(netcode);
+                    }
+                    if let Some(waker) = self.my_turn.take() {
+                        waker.wake();
+                    }
+                    return Poll::Ready(Some(item(result)));
+                }
+                TurnsState::Sending(_) => unreachable!("the send was finished above"),
+                TurnsState::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<const SIZE: usize> Sink<[u8; SIZE]> for Turns<'_, SIZE> {
+    type Error = NetcodeError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.poll_sent(cx));
+        if let Some(e) = self.failed.take() {
+            return Poll::Ready(Err(e));
+        }
+        match &self.state {
+            TurnsState::Idle(netcode) if netcode.my_turn() => Poll::Ready(Ok(())),
+            TurnsState::Done => Poll::Ready(Err(NetcodeError::SendFailed)),
+            _ => {
+                self.my_turn = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, turn: [u8; SIZE]) -> Result<(), Self::Error> {
+        let TurnsState::Idle(netcode) = std::mem::replace(&mut self.state, TurnsState::Done) else {
+            panic!("start_send was called before poll_ready was ready");
+        };
+        self.state = TurnsState::Sending(Box::pin(async move {
+            let result = match netcode.send_turn_checked(&turn).await {
+                Ok(()) => netcode.written().await,
+                Err(e) => Err(e),
+            };
+            (result, netcode)
+        }));
+        // the stream can drive the send, and then wait for the reply.
+        if let Some(waker) = self.sent.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.poll_sent(cx));
+        Poll::Ready(match self.failed.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        })
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+
+    use super::*;
+    use crate::tests::{connect, recv};
+
+    #[tokio::test]
+    async fn incoming_yields_each_turn_then_the_disconnect() {
+        let (mut host, mut client) = connect::<1>().await;
+        for turn in 0..3 {
+            client.send_turn(&[turn]);
+            let mut incoming = host.incoming();
+            assert_eq!(incoming.next().await, Some(Ok(Some([turn]))));
+            // it is the host's turn now.
+            assert_eq!(incoming.next().await, None);
+            drop(incoming);
+            host.send_turn(&[turn]);
+            assert_eq!(recv(&mut client).await, [turn]);
+        }
+        drop(client);
+        let mut incoming = host.incoming();
+        assert_eq!(incoming.next().await, Some(Err(NetcodeError::PeerClosed)));
+        assert_eq!(incoming.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn turns_echo_through_forward() {
+        let (mut host, mut client) = connect::<1>().await;
+        // the host plays back every turn it receives.
+        let echo = async {
+            let (sink, stream) = host.turns().split();
+            stream
+                .take(5)
+                .map_ok(|turn| turn.expect("the client never passes"))
+                .forward(sink)
+                .await
+        };
+        let play = async {
+            for turn in 0..5 {
+                client.send_turn(&[turn]);
+                assert_eq!(recv(&mut client).await, [turn]);
+            }
+        };
+        let (echoed, ()) = tokio::join!(echo, play);
+        assert_eq!(echoed, Ok(()));
+        assert_eq!((host.ply(), client.ply()), (10, 10));
+        assert!(client.my_turn());
+    }
+}
@@ -0,0 +1,386 @@
+//! Suspending a session when both players agree, to restore it later over a
+//! new connection.
+//!
+//! Every request names the game and how many plies had been played when it
+//! was made, like a takeback request, so both players suspend at the same
+//! ply. If the other player moves past it before accepting, it is declined.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use iroh::SecretKey;
+use sha2::{Digest, Sha256};
+
+use crate::{NetcodeError, Role, frame::ControlFrame};
+
+/// Something that happened to a request to suspend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspendEvent {
+    /// The other player asked to suspend. Answer with
+    /// [`respond_suspend`](crate::NetcodeInterface::respond_suspend).
+    Requested,
+    /// The other player declined the user's request, or moved before they
+    /// saw it.
+    Declined,
+    /// Both players agreed to suspend. The game should save the
+    /// [`snapshot`](crate::NetcodeInterface::snapshot), then drop the
+    /// interface.
+    Suspended,
+}
+
+/// Everything needed to pick a suspended session back up, from
+/// [`snapshot`](crate::NetcodeInterface::snapshot).
+///
+/// Pass it to [`Config::restore`](crate::Config::restore) to reconnect. With
+/// the `serde` feature, it can be serialized, so games can save it to disk.
+///
+/// It holds the secret key the user connected with, so that the host can be
+/// found again at the same node ID. The key is never serialized: save
+/// [`secret_key`](SessionSnapshot::secret_key) somewhere as private as the
+/// user's ticket, like the platform's keychain, and hand it back with
+/// [`with_secret_key`](SessionSnapshot::with_secret_key) before restoring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionSnapshot {
+    pub(crate) role: Role,
+    pub(crate) game: u32,
+    pub(crate) plies: u32,
+    pub(crate) my_turn: bool,
+    /// Who moved first in the current game.
+    pub(crate) first_move: Role,
+    pub(crate) seed: [u8; 32],
+    pub(crate) session_id: [u8; 16],
+    pub(crate) metadata: BTreeMap<String, String>,
+    pub(crate) peer_metadata: BTreeMap<String, String>,
+    /// The user's node ID.
+    pub(crate) node_id: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) secret_key: Option<[u8; 32]>,
+    /// The other player's node ID.
+    pub(crate) peer: [u8; 32],
+}
+
+impl SessionSnapshot {
+    /// Return which side of the connection the user was on. They are on the
+    /// same side once restored.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Return how many moves and passes had been made in the current game.
+    pub fn ply(&self) -> u64 {
+        u64::from(self.plies)
+    }
+
+    /// Return whether it was the user's turn.
+    pub fn my_turn(&self) -> bool {
+        self.my_turn
+    }
+
+    /// Return the session's [`shared_seed`](crate::NetcodeInterface::shared_seed),
+    /// which is kept once restored.
+    pub fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    /// Return the session's [`session_id`](crate::NetcodeInterface::session_id),
+    /// which is kept once restored.
+    pub fn session_id(&self) -> [u8; 16] {
+        self.session_id
+    }
+
+    /// Return the other player's
+    /// [`peer_metadata`](crate::NetcodeInterface::peer_metadata).
+    pub fn peer_metadata(&self) -> &BTreeMap<String, String> {
+        &self.peer_metadata
+    }
+
+    /// Return the other player's node ID.
+    pub fn peer(&self) -> [u8; 32] {
+        self.peer
+    }
+
+    /// Return the secret key the user connected with, unless the snapshot was
+    /// deserialized, which leaves it out.
+    pub fn secret_key(&self) -> Option<[u8; 32]> {
+        self.secret_key
+    }
+
+    /// Hand back the [`secret_key`](SessionSnapshot::secret_key) of a
+    /// deserialized snapshot, so it can be restored.
+    ///
+    /// Restoring fails with [`NetcodeError::MissingSecretKey`] if the key is
+    /// missing, or is not the one the snapshot was taken with.
+    pub fn with_secret_key(mut self, secret_key: [u8; 32]) -> Self {
+        self.secret_key = Some(secret_key);
+        self
+    }
+
+    /// Return the secret key to restore with, if it is the one the snapshot
+    /// was taken with.
+    pub(crate) fn checked_secret_key(&self) -> Result<SecretKey, NetcodeError> {
+        self.secret_key
+            .map(|secret_key| SecretKey::from_bytes(&secret_key))
+            .filter(|secret_key| *secret_key.public().as_bytes() == self.node_id)
+            .ok_or(NetcodeError::MissingSecretKey)
+    }
+
+    /// A hash of everything both players' snapshots must agree on, so the
+    /// players can compare them during the handshake.
+    pub(crate) fn digest(&self) -> [u8; 32] {
+        let node_id = self.node_id;
+        let to_move = match self.my_turn {
+            true => self.role,
+            false => self.role.other(),
+        };
+        // hash everything from the host's point of view, so both players get
+        // the same digest.
+        let ((host, host_metadata), (client, client_metadata)) = match self.role {
+            Role::Host => ((node_id, &self.metadata), (self.peer, &self.peer_metadata)),
+            Role::Client => ((self.peer, &self.peer_metadata), (node_id, &self.metadata)),
+        };
+        let mut hasher = Sha256::new()
+            .chain_update(self.game.to_be_bytes())
+            .chain_update(self.plies.to_be_bytes())
+            .chain_update([u8::from(to_move == Role::Host)])
+            .chain_update([u8::from(self.first_move == Role::Host)])
+            .chain_update(self.seed)
+            .chain_update(self.session_id)
+            .chain_update(host)
+            .chain_update(client);
+        for metadata in [host_metadata, client_metadata] {
+            hasher.update((metadata.len() as u64).to_be_bytes());
+            for s in metadata.iter().flat_map(|(key, value)| [key, value]) {
+                hasher.update((s.len() as u64).to_be_bytes());
+                hasher.update(s);
+            }
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Both players' view of requests to suspend. A request is made at a game
+/// and a number of plies.
+#[derive(Debug, Default)]
+pub(crate) struct Suspend {
+    /// Where we made our outstanding request.
+    ours: Option<(u32, u32)>,
+    /// Where the other player made their outstanding request.
+    theirs: Option<(u32, u32)>,
+    /// Whether the game was told about the other player's request.
+    theirs_seen: bool,
+    suspended: bool,
+    /// Events the game has yet to receive.
+    events: VecDeque<SuspendEvent>,
+}
+
+impl Suspend {
+    /// Whether both players agreed to suspend.
+    pub fn suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Whether we asked to suspend, and have yet to hear back.
+    pub fn requested(&self) -> bool {
+        self.ours.is_some()
+    }
+
+    /// Ask to suspend at `at`, returning the frame to send, if any.
+    ///
+    /// Asking again while our request is outstanding, or once suspended, does
+    /// nothing. Asking at the same point as their outstanding request
+    /// accepts it.
+    pub fn request(&mut self, at: (u32, u32)) -> Option<ControlFrame> {
+        if self.ours.is_some() || self.suspended {
+            return None;
+        }
+        if self.theirs == Some(at) {
+            self.theirs = None;
+            self.theirs_seen = false;
+            self.suspend();
+            return Some(ControlFrame::SuspendAccept);
+        }
+        self.ours = Some(at);
+        Some(ControlFrame::SuspendRequest(at.0, at.1))
+    }
+
+    /// Answer the other player's request, returning the frame to send.
+    pub fn respond(&mut self, at: (u32, u32), accept: bool) -> ControlFrame {
+        assert!(self.theirs_seen && self.theirs == Some(at));
+        self.theirs = None;
+        self.theirs_seen = false;
+        if accept {
+            self.suspend();
+            ControlFrame::SuspendAccept
+        } else {
+            ControlFrame::SuspendDecline
+        }
+    }
+
+    /// Handle a suspend frame from the other player.
+    pub fn handle(&mut self, control: ControlFrame) {
+        match control {
+            // our requests crossed in flight, so both of us suspend.
+            ControlFrame::SuspendRequest(game, plies) if self.ours == Some((game, plies)) => {
+                self.ours = None;
+                self.suspend();
+            }
+            ControlFrame::SuspendRequest(game, plies) => {
+                self.theirs = Some((game, plies));
+                self.theirs_seen = false;
+            }
+            ControlFrame::SuspendAccept if self.ours.take().is_some() => self.suspend(),
+            ControlFrame::SuspendDecline if self.ours.take().is_some() => {
+                self.events.push_back(SuspendEvent::Declined);
+            }
+            // anything else answers a request that does not exist, which a
+            // well-behaved player never sends.
+            _ => {}
+        }
+    }
+
+    /// Catch up with the game and plies played, returning a frame to send,
+    /// if any.
+    ///
+    /// The other player's request is only shown to the game once it has
+    /// received every move they made before it, and is declined once the
+    /// game has moved past it.
+    pub fn update(&mut self, at: (u32, u32)) -> Option<ControlFrame> {
+        let theirs = self.theirs?;
+        if at > theirs {
+            self.theirs = None;
+            self.theirs_seen = false;
+            return Some(ControlFrame::SuspendDecline);
+        }
+        if at == theirs && !self.theirs_seen {
+            self.theirs_seen = true;
+            self.events.push_back(SuspendEvent::Requested);
+        }
+        None
+    }
+
+    /// Return the next event the game has yet to receive.
+    pub fn next_event(&mut self) -> Option<SuspendEvent> {
+        self.events.pop_front()
+    }
+
+    fn suspend(&mut self) {
+        self.suspended = true;
+        self.events.push_back(SuspendEvent::Suspended);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_then_accept() {
+        let (mut ours, mut theirs) = (Suspend::default(), Suspend::default());
+        let request = ours.request((0, 4)).unwrap();
+        assert!(ours.requested());
+        theirs.handle(request);
+        // they have yet to receive our last move.
+        assert_eq!(theirs.update((0, 3)), None);
+        assert_eq!(theirs.next_event(), None);
+        assert_eq!(theirs.update((0, 4)), None);
+        assert_eq!(theirs.next_event(), Some(SuspendEvent::Requested));
+        ours.handle(theirs.respond((0, 4), true));
+        assert!(ours.suspended() && theirs.suspended());
+        assert!(!ours.requested());
+        assert_eq!(ours.next_event(), Some(SuspendEvent::Suspended));
+        assert_eq!(theirs.next_event(), Some(SuspendEvent::Suspended));
+        assert_eq!(ours.request((0, 4)), None);
+    }
+
+    #[test]
+    fn request_then_decline() {
+        let (mut ours, mut theirs) = (Suspend::default(), Suspend::default());
+        theirs.handle(ours.request((1, 0)).unwrap());
+        theirs.update((1, 0));
+        ours.handle(theirs.respond((1, 0), false));
+        assert!(!ours.suspended() && !ours.requested());
+        assert_eq!(ours.next_event(), Some(SuspendEvent::Declined));
+    }
+
+    #[test]
+    fn request_is_declined_once_the_game_moves_past_it() {
+        let mut suspend = Suspend::default();
+        suspend.handle(ControlFrame::SuspendRequest(0, 4));
+        // a new game is past every ply of the old one.
+        assert_eq!(suspend.update((1, 0)), Some(ControlFrame::SuspendDecline));
+        assert_eq!(suspend.next_event(), None);
+    }
+
+    #[test]
+    fn crossed_requests_suspend() {
+        let (mut ours, mut theirs) = (Suspend::default(), Suspend::default());
+        let (a, b) = (
+            ours.request((0, 2)).unwrap(),
+            theirs.request((0, 2)).unwrap(),
+        );
+        ours.handle(b);
+        theirs.handle(a);
+        assert!(ours.suspended() && theirs.suspended());
+    }
+
+    #[test]
+    fn requesting_at_their_request_accepts_it() {
+        let mut suspend = Suspend::default();
+        suspend.handle(ControlFrame::SuspendRequest(0, 2));
+        assert_eq!(suspend.request((0, 2)), Some(ControlFrame::SuspendAccept));
+        assert!(suspend.suspended());
+    }
+
+    fn snapshot() -> SessionSnapshot {
+        let secret_key = [3; 32];
+        SessionSnapshot {
+            role: Role::Host,
+            game: 1,
+            plies: 4,
+            my_turn: true,
+            first_move: Role::Client,
+            seed: [1; 32],
+            session_id: [2; 16],
+            metadata: BTreeMap::new(),
+            peer_metadata: BTreeMap::new(),
+            node_id: *SecretKey::from_bytes(&secret_key).public().as_bytes(),
+            secret_key: Some(secret_key),
+            peer: [4; 32],
+        }
+    }
+
+    #[test]
+    fn restoring_needs_the_right_secret_key() {
+        let snapshot = snapshot();
+        assert!(snapshot.checked_secret_key().is_ok());
+        let missing = SessionSnapshot {
+            secret_key: None,
+            ..snapshot.clone()
+        };
+        assert_eq!(
+            missing.checked_secret_key().unwrap_err(),
+            NetcodeError::MissingSecretKey
+        );
+        assert_eq!(
+            missing
+                .clone()
+                .with_secret_key([5; 32])
+                .checked_secret_key()
+                .unwrap_err(),
+            NetcodeError::MissingSecretKey
+        );
+        assert_eq!(missing.with_secret_key([3; 32]), snapshot);
+    }
+
+    #[cfg(feature = "session-export")]
+    #[test]
+    fn secret_key_is_not_serialized() {
+        let snapshot = snapshot();
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert!(json.get("secret_key").is_none());
+        let restored: SessionSnapshot = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.secret_key(), None);
+        assert_eq!(restored.digest(), snapshot.digest());
+        assert_eq!(restored.with_secret_key([3; 32]), snapshot);
+    }
+}
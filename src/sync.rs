@@ -0,0 +1,175 @@
+//! Using an interface from a synchronous main loop, for game engines that
+//! are not async.
+
+use std::time::Duration;
+
+use tokio::runtime::{self, Handle};
+
+use crate::{Config, NetcodeError, NetcodeInterface, NetcodeInterfaceBuilder, TurnPoll};
+
+impl<const SIZE: usize> NetcodeInterface<SIZE> {
+    /// Block the calling thread until the other player's turn arrives, for
+    /// game loops without any async, like
+    /// [`recv_turn`](NetcodeInterface::recv_turn).
+    ///
+    /// Returns what [`try_recv_turn`](NetcodeInterface::try_recv_turn) would
+    /// have, once it is anything but [`TurnPoll::Pending`]. That is
+    /// [`TurnPoll::WaitingTooLong`] if `timeout` elapses first, or `Pending`
+    /// if it became the user's turn without one, because a takeback was
+    /// accepted. Returns the error that disconnected us if the connection is
+    /// lost first. [`send_turn`](NetcodeInterface::send_turn) never blocks,
+    /// so it needs nothing like this.
+    ///
+    /// The interface must still have been created in the context of a Tokio
+    /// runtime that keeps running, like on another thread.
+    ///
+    /// See the struct's [`docs`][`NetcodeInterface`] for invariants.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a Tokio runtime, whose thread it would
+    /// block, possibly stopping the turn from ever arriving. Use `recv_turn`
+    /// there instead.
+    ///
+    /// ```should_panic
+    /// use sfn_tpn::{Config, NetcodeInterface};
+    /// use tokio::sync::oneshot;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let (send, _recv) = oneshot::channel();
+    ///     let mut netcode: NetcodeInterface<1> =
+    ///         NetcodeInterface::new(Config::TicketSender(send));
+    ///     // the host moves second, so this would wait for the client.
+    ///     let _ = netcode.recv_turn_blocking(None);
+    /// }
+    /// ```
+    pub fn recv_turn_blocking(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<TurnPoll<SIZE>, NetcodeError> {
+        assert!(
+            Handle::try_current().is_err(),
+            "recv_turn_blocking was called from within a Tokio runtime, whose thread it would block"
+        );
+        // the channels work with any runtime, so a small one of our own can
+        // wait on them, and keep time for the timeout.
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("a runtime without IO can always be built");
+        runtime.block_on(async {
+            match timeout {
+                Some(timeout) => self.recv_turn_timeout(timeout).await,
+                None => self.wait_for_turn().await,
+            }
+        })
+    }
+}
+
+/// A [`NetcodeInterface`] that can be used without being in an async
+/// context, like from a game engine's synchronous main loop.
+///
+/// The interface runs on the Tokio runtime of the [`Handle`] it is created
+/// with, and its methods that wait block the calling thread instead. The
+/// runtime must keep running in the background, so it should be a
+/// multi-threaded runtime, kept alive for as long as the adapter. The
+/// blocking methods panic if called from within an async context, like any
+/// [`Handle::block_on`].
+///
+/// ```no_run
+/// use sfn_tpn::{Config, SyncTurnAdapter, TurnPoll};
+/// use tokio::runtime::Handle;
+///
+/// fn run(runtime: Handle, ticket: String) {
+///     let mut netcode: SyncTurnAdapter<4> =
+///         SyncTurnAdapter::new(runtime, Config::Ticket(ticket));
+///     netcode.wait_for_connection().unwrap();
+///     netcode.send_turn(b"ping");
+///     loop {
+///         // draw a frame, then check for the other player's turn.
+///         if let TurnPoll::Ready(turn) = netcode.try_recv_turn() {
+///             println!("received {turn:?}");
+///             break;
+///         }
+///     }
+/// }
+/// ```
+pub struct SyncTurnAdapter<const SIZE: usize> {
+    handle: Handle,
+    inner: NetcodeInterface<SIZE>,
+}
+
+impl<const SIZE: usize> SyncTurnAdapter<SIZE> {
+    /// Create an interface with the default options, running on the
+    /// runtime of `handle`.
+    pub fn new(handle: Handle, config: Config) -> Self {
+        Self::from_builder(handle, NetcodeInterface::builder(config))
+    }
+
+    /// Create an interface configured by `builder`, running on the runtime
+    /// of `handle`.
+    pub fn from_builder(handle: Handle, builder: NetcodeInterfaceBuilder<SIZE>) -> Self {
+        let inner = {
+            let _runtime = handle.enter();
+            builder.build()
+        };
+        Self { handle, inner }
+    }
+
+    /// Send a turn to the other player, like [`NetcodeInterface::send_turn`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not the user's turn, like
+    /// [`NetcodeInterface::send_turn`].
+    pub fn send_turn(&mut self, turn: &[u8; SIZE]) {
+        let _runtime = self.handle.enter();
+        self.inner.send_turn(turn);
+    }
+
+    /// Check if the other player has sent a turn to the user, like
+    /// [`NetcodeInterface::try_recv_turn`]. This never blocks, so it can be
+    /// called every frame.
+    #[must_use = "check whether a turn was received before calling game logic"]
+    pub fn try_recv_turn(&mut self) -> TurnPoll<SIZE> {
+        let _runtime = self.handle.enter();
+        self.inner.try_recv_turn()
+    }
+
+    /// Return whether it is the user's turn, like
+    /// [`NetcodeInterface::my_turn`].
+    pub fn my_turn(&self) -> bool {
+        self.inner.my_turn()
+    }
+
+    /// Block until the other player has connected, like
+    /// [`NetcodeInterface::wait_for_connection`].
+    pub fn wait_for_connection(&mut self) -> Result<(), NetcodeError> {
+        self.handle.block_on(self.inner.wait_for_connection())
+    }
+
+    /// Block until the other player's turn arrives, like
+    /// [`NetcodeInterface::recv_turn`].
+    pub fn recv_turn(&mut self) -> Result<Option<[u8; SIZE]>, NetcodeError> {
+        self.handle.block_on(self.inner.recv_turn())
+    }
+
+    /// Return the interface, to call anything else on it.
+    ///
+    /// Its async methods can be run with [`handle`](SyncTurnAdapter::handle)'s
+    /// [`block_on`](Handle::block_on).
+    pub fn inner_mut(&mut self) -> &mut NetcodeInterface<SIZE> {
+        &mut self.inner
+    }
+
+    /// Return the handle of the runtime the interface runs on.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Return the interface, to use it from an async context again.
+    pub fn into_inner(self) -> NetcodeInterface<SIZE> {
+        self.inner
+    }
+}
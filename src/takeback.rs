@@ -0,0 +1,163 @@
+//! Asking to take back the move the user just made.
+//!
+//! Every request names how many plies had been played when it was made, so
+//! both players can tell which move it is about. The other player may only
+//! accept while that move is still the last one played. If they already
+//! replied with a move of their own, the request is declined, whichever order
+//! the frames arrive in.
+
+use std::collections::VecDeque;
+
+use crate::frame::ControlFrame;
+
+/// Something that happened to a takeback request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TakebackEvent {
+    /// The other player asked to take back their last move. Answer with
+    /// [`respond_takeback`](crate::NetcodeInterface::respond_takeback).
+    Requested,
+    /// Both players agreed to take back the last move. The game should undo
+    /// one ply; it is the turn of whoever made it again.
+    Accepted,
+    /// The other player declined the user's request, or replied to the move
+    /// before they saw it.
+    Declined,
+}
+
+/// Both players' view of takeback requests in the current game.
+#[derive(Debug, Default)]
+pub(crate) struct Takeback {
+    /// The plies played when we made our outstanding request.
+    ours: Option<u32>,
+    /// The plies played when the other player made their outstanding request.
+    theirs: Option<u32>,
+    /// Whether the game was told about the other player's request.
+    theirs_seen: bool,
+    /// Events the game has yet to receive.
+    events: VecDeque<TakebackEvent>,
+}
+
+impl Takeback {
+    /// Ask to take back the move that brought the game to `plies`, returning
+    /// the frame to send, if any.
+    ///
+    /// Asking again while our request is outstanding does nothing.
+    pub fn request(&mut self, plies: u32) -> Option<ControlFrame> {
+        if self.ours.is_some() {
+            return None;
+        }
+        self.ours = Some(plies);
+        Some(ControlFrame::TakebackRequest(plies))
+    }
+
+    /// Answer the other player's request, returning the frame to send.
+    pub fn respond(&mut self, plies: u32, accept: bool) -> ControlFrame {
+        assert!(self.theirs_seen && self.theirs == Some(plies));
+        self.theirs = None;
+        self.theirs_seen = false;
+        if accept {
+            self.events.push_back(TakebackEvent::Accepted);
+            ControlFrame::TakebackAccept
+        } else {
+            ControlFrame::TakebackDecline
+        }
+    }
+
+    /// Handle a takeback frame from the other player.
+    ///
+    /// Returns whether they accepted our request.
+    pub fn handle(&mut self, control: ControlFrame) -> bool {
+        match control {
+            ControlFrame::TakebackRequest(plies) => {
+                self.theirs = Some(plies);
+                self.theirs_seen = false;
+            }
+            ControlFrame::TakebackAccept if self.ours.take().is_some() => {
+                self.events.push_back(TakebackEvent::Accepted);
+                return true;
+            }
+            ControlFrame::TakebackDecline if self.ours.take().is_some() => {
+                self.events.push_back(TakebackEvent::Declined);
+            }
+            // anything else answers a request that does not exist, which a
+            // well-behaved player never sends.
+            _ => {}
+        }
+        false
+    }
+
+    /// Catch up with the plies played, returning a frame to send, if any.
+    ///
+    /// The other player's request is only shown to the game once it has
+    /// received the move it is about, and is declined once the game has
+    /// moved past it.
+    pub fn update(&mut self, plies: u32) -> Option<ControlFrame> {
+        let theirs = self.theirs?;
+        if plies > theirs {
+            self.theirs = None;
+            self.theirs_seen = false;
+            return Some(ControlFrame::TakebackDecline);
+        }
+        if plies == theirs && !self.theirs_seen {
+            self.theirs_seen = true;
+            self.events.push_back(TakebackEvent::Requested);
+        }
+        None
+    }
+
+    /// Return the next event the game has yet to receive.
+    pub fn next_event(&mut self) -> Option<TakebackEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_is_shown_once_the_move_arrives() {
+        let mut takeback = Takeback::default();
+        takeback.handle(ControlFrame::TakebackRequest(3));
+        // the move it is about is still in flight.
+        assert_eq!(takeback.update(2), None);
+        assert_eq!(takeback.next_event(), None);
+        assert_eq!(takeback.update(3), None);
+        assert_eq!(takeback.next_event(), Some(TakebackEvent::Requested));
+        assert_eq!(takeback.respond(3, true), ControlFrame::TakebackAccept);
+        assert_eq!(takeback.next_event(), Some(TakebackEvent::Accepted));
+    }
+
+    #[test]
+    fn request_is_declined_once_the_game_moves_past_it() {
+        let mut takeback = Takeback::default();
+        takeback.handle(ControlFrame::TakebackRequest(3));
+        assert_eq!(takeback.update(4), Some(ControlFrame::TakebackDecline));
+        assert_eq!(takeback.update(4), None);
+        assert_eq!(takeback.next_event(), None);
+    }
+
+    #[test]
+    fn our_request_is_answered_once() {
+        let mut takeback = Takeback::default();
+        assert_eq!(takeback.request(5), Some(ControlFrame::TakebackRequest(5)));
+        assert_eq!(takeback.request(5), None);
+        assert!(takeback.handle(ControlFrame::TakebackAccept));
+        assert_eq!(takeback.next_event(), Some(TakebackEvent::Accepted));
+        // a second answer has no request to answer.
+        assert!(!takeback.handle(ControlFrame::TakebackAccept));
+        assert_eq!(takeback.next_event(), None);
+
+        takeback.request(4);
+        assert!(!takeback.handle(ControlFrame::TakebackDecline));
+        assert_eq!(takeback.next_event(), Some(TakebackEvent::Declined));
+    }
+
+    #[test]
+    #[should_panic]
+    fn responding_before_the_request_is_shown_panics() {
+        let mut takeback = Takeback::default();
+        takeback.handle(ControlFrame::TakebackRequest(3));
+        takeback.respond(3, true);
+    }
+}
@@ -0,0 +1,157 @@
+//! Sending frames too large to send at once, like moves with huge turns, in
+//! chunks.
+//!
+//! A frame over [`CHUNK_LEN`] bytes is encoded whole, then split into
+//! [`Frame::Chunk`]s. Everything else can be sent between the chunks, so a
+//! large move does not hold up acks or control frames. The receiver only
+//! decodes the frame once its last chunk arrives, so a connection lost
+//! partway through never delivers part of a move.
+
+use std::collections::VecDeque;
+
+use iroh::endpoint::SendStream;
+
+use crate::{
+    frame::{Frame, HEADER_LEN},
+    protocol::BoxError,
+};
+
+/// The most bytes of a frame sent in one chunk. Frames up to this size are
+/// sent as-is.
+pub const CHUNK_LEN: usize = 1 << 14;
+
+/// The largest frame we are willing to reassemble from chunks.
+///
+/// Guards against a misbehaving peer making us buffer without end.
+pub const MAX_TRANSFER_LEN: usize = 1 << 24;
+
+/// How much of a frame sent in chunks has been sent or received, from
+/// [`outgoing_transfer`](crate::NetcodeInterface::outgoing_transfer) and
+/// [`incoming_transfer`](crate::NetcodeInterface::incoming_transfer).
+///
+/// Both are in bytes, including a few bytes of framing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransferProgress {
+    pub done: u64,
+    pub total: u64,
+}
+
+/// Split an encoded frame into the bytes of each chunk.
+pub fn split(bytes: &[u8]) -> VecDeque<Vec<u8>> {
+    bytes.chunks(CHUNK_LEN).map(<[u8]>::to_vec).collect()
+}
+
+/// Write a frame to the stream, in chunks if it is too large to send at once.
+pub async fn write(send: &mut SendStream, frame: &Frame) -> Result<(), BoxError> {
+    write_bytes(send, &frame.encode()?).await
+}
+
+/// Write an encoded frame to the stream, like [`write`].
+pub async fn write_bytes(send: &mut SendStream, bytes: &[u8]) -> Result<(), BoxError> {
+    if bytes.len() <= CHUNK_LEN {
+        send.write_all(bytes).await?;
+        return Ok(());
+    }
+    let mut chunks = split(bytes);
+    while let Some(bytes) = chunks.pop_front() {
+        Frame::Chunk {
+            last: chunks.is_empty(),
+            bytes,
+        }
+        .write(send)
+        .await?;
+    }
+    Ok(())
+}
+
+/// A frame being received in chunks.
+#[derive(Debug, Default)]
+pub struct Reassembly {
+    bytes: Vec<u8>,
+    /// Kept once the frame is complete, until the next one starts.
+    progress: Option<TransferProgress>,
+}
+
+impl Reassembly {
+    /// Add the next chunk, returning the frame once it is complete.
+    pub fn push(&mut self, last: bool, chunk: &[u8]) -> Result<Option<Frame>, BoxError> {
+        self.bytes.extend_from_slice(chunk);
+        if let Some((header, _)) = self.bytes.split_first_chunk::<HEADER_LEN>() {
+            let [_, len @ ..] = *header;
+            self.progress = Some(TransferProgress {
+                done: self.bytes.len() as u64,
+                total: HEADER_LEN as u64 + u64::from(u32::from_be_bytes(len)),
+            });
+        }
+        if self.bytes.len() > MAX_TRANSFER_LEN {
+            return Err(format!(
+                "the other player sent a frame of more than {MAX_TRANSFER_LEN} bytes"
+            )
+            .into());
+        }
+        if !last {
+            return Ok(None);
+        }
+        match Frame::decode(&std::mem::take(&mut self.bytes))? {
+            Frame::Chunk { .. } => Err("the other player sent a chunk inside a chunk".into()),
+            frame => Ok(Some(frame)),
+        }
+    }
+
+    /// How much of the latest frame has arrived, once its header has.
+    pub fn progress(&self) -> Option<TransferProgress> {
+        self.progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_reassemble_into_the_frame() {
+        let frame = Frame::Turns {
+            seq: 1,
+            game: 0,
+            clock: None,
+            state_hash: None,
+            turns: vec![vec![7; 3 * CHUNK_LEN]],
+        };
+        let bytes = frame.encode().unwrap();
+        let mut chunks = split(&bytes);
+        assert_eq!(chunks.len(), 4);
+
+        let mut reassembly = Reassembly::default();
+        let mut done = None;
+        while let Some(chunk) = chunks.pop_front() {
+            assert!(done.is_none());
+            done = reassembly.push(chunks.is_empty(), &chunk).unwrap();
+        }
+        assert_eq!(done, Some(frame));
+        let total = bytes.len() as u64;
+        assert_eq!(
+            reassembly.progress(),
+            Some(TransferProgress { done: total, total })
+        );
+    }
+
+    #[test]
+    fn oversized_transfers_are_rejected() {
+        let mut reassembly = Reassembly::default();
+        let chunk = vec![0; CHUNK_LEN];
+        let result = (0..=MAX_TRANSFER_LEN / CHUNK_LEN)
+            .map(|_| reassembly.push(false, &chunk))
+            .find(Result::is_err);
+        assert!(result.is_some());
+    }
+
+    #[test]
+    fn nested_chunks_are_rejected() {
+        let inner = Frame::Chunk {
+            last: true,
+            bytes: vec![1, 2],
+        };
+        let mut reassembly = Reassembly::default();
+        assert!(reassembly.push(true, &inner.encode().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,17 @@
+//! Inspecting or changing turns on their way between the game and the
+//! connection.
+
+/// Transforms each turn after the game sends it, and before the game receives
+/// it, like logging, encrypting, or flipping the board's coordinates.
+///
+/// Set one with
+/// [`turn_transformer`](crate::NetcodeInterfaceBuilder::turn_transformer).
+/// Unlike a [`DiffEncoder`](crate::DiffEncoder), the players do not have to
+/// agree on it, as only the transformed turns are sent.
+pub trait TurnTransformer<const SIZE: usize>: Send + 'static {
+    /// Transform a turn the user sent, before it is sent to the other player.
+    fn transform_out(&self, turn: [u8; SIZE]) -> [u8; SIZE];
+
+    /// Transform a turn from the other player, before the game receives it.
+    fn transform_in(&self, turn: [u8; SIZE]) -> [u8; SIZE];
+}
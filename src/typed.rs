@@ -0,0 +1,201 @@
+//! An interface over the game's own turn type, so games never handle the
+//! bytes at all.
+
+use std::marker::PhantomData;
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{NetcodeError, NetcodeInterface, NetcodeInterfaceBuilder, TurnCodec, TurnPoll};
+
+/// The [`metadata`](NetcodeInterfaceBuilder::metadata) key holding the
+/// codec's name, so that players with different codecs fail the handshake
+/// rather than misreading each other's turns.
+const CODEC_METADATA_KEY: &str = "sfn-tpn/turn-codec";
+
+/// A [`NetcodeInterface`] whose turns are `T`, encoded with the codec `C`,
+/// rather than `[u8; SIZE]`.
+///
+/// Create one with [`NetcodeInterfaceBuilder::build_typed`]. `SIZE` must fit
+/// the largest encoded `T`. Everything besides sending and receiving turns is
+/// done on the underlying interface, with [`get`](TypedInterface::get) and
+/// [`get_mut`](TypedInterface::get_mut).
+pub struct TypedInterface<T, C, const SIZE: usize> {
+    inner: NetcodeInterface<SIZE>,
+    codec: C,
+    _turn: PhantomData<fn(T) -> T>,
+}
+
+impl<const SIZE: usize> NetcodeInterfaceBuilder<SIZE> {
+    /// Create an interface that sends and receives turns as `T`, encoded
+    /// with `codec`.
+    ///
+    /// The codec's name is added to the
+    /// [`metadata`](NetcodeInterfaceBuilder::metadata), so both players must
+    /// use the same codec, or the handshake fails with
+    /// [`NetcodeError::MetadataMismatch`].
+    pub fn build_typed<T: Serialize + DeserializeOwned, C: TurnCodec>(
+        self,
+        codec: C,
+    ) -> TypedInterface<T, C, SIZE> {
+        let name = codec.name();
+        TypedInterface {
+            inner: self.metadata(&[(CODEC_METADATA_KEY, &name)]).build(),
+            codec,
+            _turn: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned, C: TurnCodec, const SIZE: usize> TypedInterface<T, C, SIZE> {
+    /// Encode `turn`, then send it like
+    /// [`send_turn_checked`](NetcodeInterface::send_turn_checked).
+    ///
+    /// The encoded turn is padded with zeros up to `SIZE` bytes. Returns
+    /// [`NetcodeError::TurnTooLarge`] without sending anything if it does not
+    /// fit, [`NetcodeError::MalformedTurn`] if it cannot be encoded, or the
+    /// same errors as `send_turn_checked`.
+    pub async fn send_turn(&mut self, turn: &T) -> Result<(), NetcodeError> {
+        let mut encoded = Vec::with_capacity(SIZE);
+        self.codec.encode(turn, &mut encoded)?;
+        if encoded.len() > SIZE {
+            return Err(NetcodeError::TurnTooLarge);
+        }
+        let mut bytes = [0; SIZE];
+        bytes[..encoded.len()].copy_from_slice(&encoded);
+        self.inner.send_turn_checked(&bytes).await
+    }
+
+    /// Check if the other player has sent a turn, like
+    /// [`try_recv_turn`](NetcodeInterface::try_recv_turn), and decode it.
+    ///
+    /// Returns `Ok(None)` if there is no turn yet, or the other player passed,
+    /// in which case it is the user's turn. Returns
+    /// [`NetcodeError::MalformedTurn`] if their turn is not a `T`, like when
+    /// they play a different version of the game. The turn was still
+    /// received, so the game may want to
+    /// [reject](NetcodeInterface::reject_turn) it.
+    pub fn try_recv_turn(&mut self) -> Result<Option<T>, NetcodeError> {
+        let TurnPoll::Ready(bytes) = self.inner.try_recv_turn() else {
+            return Ok(None);
+        };
+        Ok(Some(self.codec.decode(&bytes)?))
+    }
+
+    /// Return the underlying interface.
+    pub fn get(&self) -> &NetcodeInterface<SIZE> {
+        &self.inner
+    }
+
+    /// Return the underlying interface, to do anything besides sending and
+    /// receiving turns.
+    pub fn get_mut(&mut self) -> &mut NetcodeInterface<SIZE> {
+        &mut self.inner
+    }
+
+    /// Unwrap the underlying interface.
+    pub fn into_inner(self) -> NetcodeInterface<SIZE> {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::{Config, tests::eventually};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Move {
+        Place { x: u8, y: u8 },
+        Pass,
+        Resign(String),
+    }
+
+    /// Start a host and a client with typed interfaces over loopback,
+    /// without waiting for the handshake.
+    async fn start<H: TurnCodec, C: TurnCodec>(
+        host: H,
+        client: C,
+    ) -> (TypedInterface<Move, H, 16>, TypedInterface<Move, C, 16>) {
+        let (ticket_sender, ticket) = oneshot::channel();
+        let host = NetcodeInterface::builder(Config::TicketSender(ticket_sender)).build_typed(host);
+        let ticket = ticket.await.unwrap();
+        let client = NetcodeInterface::builder(Config::Ticket(ticket)).build_typed(client);
+        (host, client)
+    }
+
+    /// Play a few moves of every variant over loopback, with `codec`.
+    async fn round_trip(codec: impl TurnCodec + Copy) {
+        let (mut host, mut client) = start(codec, codec).await;
+        let (host_connected, client_connected) = tokio::join!(
+            host.get_mut().wait_for_connection(),
+            client.get_mut().wait_for_connection()
+        );
+        host_connected.unwrap();
+        client_connected.unwrap();
+
+        let moves = [
+            Move::Place { x: 3, y: 4 },
+            Move::Pass,
+            Move::Place { x: 0, y: 255 },
+            Move::Resign("good game".to_string()),
+        ];
+        for (ply, turn) in moves.into_iter().enumerate() {
+            let received = match ply % 2 {
+                0 => {
+                    client.send_turn(&turn).await.unwrap();
+                    eventually(|| host.try_recv_turn().unwrap()).await
+                }
+                _ => {
+                    host.send_turn(&turn).await.unwrap();
+                    eventually(|| client.try_recv_turn().unwrap()).await
+                }
+            };
+            assert_eq!(received, turn);
+        }
+        // nothing is sent if the turn does not fit.
+        let too_long = Move::Resign("a resignation too long to fit".to_string());
+        assert_eq!(
+            client.send_turn(&too_long).await,
+            Err(NetcodeError::TurnTooLarge)
+        );
+        assert!(client.get().my_turn());
+    }
+
+    #[cfg(feature = "bincode")]
+    #[tokio::test]
+    async fn turns_round_trip_with_bincode() {
+        round_trip(crate::BincodeCodec).await;
+    }
+
+    #[cfg(feature = "postcard")]
+    #[tokio::test]
+    async fn turns_round_trip_with_postcard() {
+        round_trip(crate::PostcardCodec).await;
+    }
+
+    #[cfg(all(feature = "bincode", feature = "postcard"))]
+    #[tokio::test]
+    async fn different_codecs_fail_the_handshake() {
+        let (mut host, mut client) = start(crate::BincodeCodec, crate::PostcardCodec).await;
+        let (host_connected, client_connected) = tokio::join!(
+            host.get_mut().wait_for_connection(),
+            client.get_mut().wait_for_connection()
+        );
+        let mismatch = |ours: &str, theirs: &str| NetcodeError::MetadataMismatch {
+            key: CODEC_METADATA_KEY.to_string(),
+            ours: Some(ours.to_string()),
+            theirs: Some(theirs.to_string()),
+        };
+        assert_eq!(
+            host_connected,
+            Err(mismatch("bincode-standard", "postcard"))
+        );
+        assert_eq!(
+            client_connected,
+            Err(mismatch("postcard", "bincode-standard"))
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Sharing tickets as links, for games with web-based lobbies, and reading
+//! `iroh://` URIs, for desktop deeplinks.
+
+use std::{net::SocketAddr, str::FromStr};
+
+use iroh::{NodeAddr, NodeId, RelayUrl};
+use iroh_base::ticket::NodeTicket;
+
+use crate::NetcodeError;
+
+/// The query parameter that holds the ticket.
+const TICKET_PARAM: &str = "ticket";
+
+/// The scheme of the URIs in [`Config::Uri`](crate::Config::Uri).
+const URI_SCHEME: &str = "iroh://";
+
+/// Add `ticket` to `base_url` as a query parameter, percent-encoded. Any
+/// `#fragment` stays at the end.
+///
+/// ```
+/// # use sfn_tpn::encode_ticket_as_url;
+/// assert_eq!(
+///     encode_ticket_as_url("https://mygame.example.com/join", "abc"),
+///     "https://mygame.example.com/join?ticket=abc",
+/// );
+/// ```
+///
+/// The inverse is [`decode_ticket_from_url`].
+pub fn encode_ticket_as_url(base_url: &str, ticket: &str) -> String {
+    let (base_url, fragment) = match base_url.split_once('#') {
+        Some((base_url, fragment)) => (base_url, Some(fragment)),
+        None => (base_url, None),
+    };
+    let separator = if base_url.contains('?') { '&' } else { '?' };
+    let mut url = format!("{base_url}{separator}{TICKET_PARAM}=");
+    for byte in ticket.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            url.push(char::from(byte));
+        } else {
+            url.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.push_str(fragment);
+    }
+    url
+}
+
+/// Find the ticket in a link made with [`encode_ticket_as_url`].
+///
+/// Returns `None` if the link has no ticket, or it is not percent-encoded
+/// correctly.
+pub fn decode_ticket_from_url(url: &str) -> Option<String> {
+    let (_, query) = url.split_once('?')?;
+    let query = query.split('#').next()?;
+    let encoded = query
+        .split('&')
+        .find_map(|param| param.strip_prefix(TICKET_PARAM)?.strip_prefix('='))?;
+    percent_decode(encoded)
+}
+
+/// Find the host in an `iroh://` URI, for [`Config::Uri`](crate::Config::Uri).
+pub(crate) fn node_addr_from_uri(uri: &str) -> Result<NodeAddr, NetcodeError> {
+    let invalid = |reason: String| NetcodeError::InvalidUri(reason);
+    let rest = uri
+        .strip_prefix(URI_SCHEME)
+        .ok_or_else(|| invalid(format!("it does not start with {URI_SCHEME}")))?;
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (node, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let node = node.trim_end_matches('/');
+    if node.is_empty() {
+        return Err(invalid("it does not name a node".to_string()));
+    }
+
+    let mut addr = match NodeTicket::from_str(node) {
+        Ok(ticket) => NodeAddr::from(ticket),
+        Err(_) => NodeAddr::new(
+            NodeId::from_str(node)
+                .map_err(|_| invalid(format!("{node:?} is neither a node ID nor a ticket")))?,
+        ),
+    };
+    let mut direct_addresses = vec![];
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        let value = percent_decode(value).ok_or_else(|| {
+            invalid(format!(
+                "the {key} parameter is not percent-encoded correctly"
+            ))
+        })?;
+        match key {
+            "relay" => {
+                let relay = RelayUrl::from_str(&value)
+                    .map_err(|_| invalid(format!("{value:?} is not a relay URL")))?;
+                addr = addr.with_relay_url(relay);
+            }
+            "addr" => direct_addresses.push(
+                SocketAddr::from_str(&value)
+                    .map_err(|_| invalid(format!("{value:?} is not a socket address")))?,
+            ),
+            // left for newer versions to use.
+            _ => {}
+        }
+    }
+    if !direct_addresses.is_empty() {
+        addr = addr.with_direct_addresses(direct_addresses);
+    }
+    Ok(addr)
+}
+
+/// Decode a percent-encoded string, or return `None` if it is malformed.
+fn percent_decode(encoded: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut bytes = encoded.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            // from_str_radix would take a sign, like the + in %+1.
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use iroh::SecretKey;
+
+    use super::*;
+
+    fn node_id() -> NodeId {
+        SecretKey::from_bytes(&[7; 32]).public()
+    }
+
+    fn invalid(uri: &str) -> bool {
+        matches!(node_addr_from_uri(uri), Err(NetcodeError::InvalidUri(_)))
+    }
+
+    #[test]
+    fn percent_decode_rejects_malformed_escapes() {
+        assert_eq!(percent_decode("a%2Bb%2f").as_deref(), Some("a+b/"));
+        assert_eq!(percent_decode("%+1"), None);
+        assert_eq!(percent_decode("%-1"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%1"), None);
+        // not UTF-8 once decoded.
+        assert_eq!(percent_decode("%FF"), None);
+    }
+
+    #[test]
+    fn tickets_round_trip_through_urls() {
+        let ticket = "node abc/+=&?#é~._-";
+        for base_url in [
+            "https://mygame.example.com/join",
+            "https://mygame.example.com/join?lobby=3",
+            "https://mygame.example.com/join#play",
+            "https://mygame.example.com/join?lobby=3#play",
+        ] {
+            let url = encode_ticket_as_url(base_url, ticket);
+            assert_eq!(
+                decode_ticket_from_url(&url).as_deref(),
+                Some(ticket),
+                "{url}"
+            );
+        }
+        assert_eq!(
+            encode_ticket_as_url("https://mygame.example.com/join?lobby=3#play", "a b"),
+            "https://mygame.example.com/join?lobby=3&ticket=a%20b#play",
+        );
+    }
+
+    #[test]
+    fn urls_without_a_ticket_decode_to_none() {
+        assert_eq!(
+            decode_ticket_from_url("https://mygame.example.com/join"),
+            None
+        );
+        assert_eq!(
+            decode_ticket_from_url("https://mygame.example.com/join?tickets=abc"),
+            None
+        );
+        assert_eq!(
+            decode_ticket_from_url("https://mygame.example.com/join?ticket=%+1"),
+            None
+        );
+    }
+
+    #[test]
+    fn uris_name_a_node_by_id_or_ticket() {
+        let node_id = node_id();
+        assert_eq!(
+            node_addr_from_uri(&format!("iroh://{node_id}")),
+            Ok(NodeAddr::new(node_id))
+        );
+        let ticket = NodeTicket::new(NodeAddr::new(node_id));
+        assert_eq!(
+            node_addr_from_uri(&format!("iroh://{ticket}/")),
+            Ok(NodeAddr::new(node_id))
+        );
+    }
+
+    #[test]
+    fn uris_take_relay_and_addr_params() {
+        let node_id = node_id();
+        let addr = node_addr_from_uri(&format!(
+            "iroh://{node_id}/?relay=https%3A%2F%2Frelay.example.com&addr=127.0.0.1%3A4433\
+             &addr=[::1]:4433&newer=1#top"
+        ))
+        .unwrap();
+        assert_eq!(addr.node_id, node_id);
+        assert_eq!(
+            addr.relay_url,
+            Some(RelayUrl::from_str("https://relay.example.com").unwrap())
+        );
+        assert_eq!(
+            addr.direct_addresses.into_iter().collect::<Vec<_>>(),
+            [
+                "127.0.0.1:4433".parse().unwrap(),
+                "[::1]:4433".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_uris_are_rejected() {
+        let node_id = node_id();
+        assert!(invalid(&format!("https://{node_id}")));
+        assert!(invalid(&format!("iroh:{node_id}")));
+        assert!(invalid("iroh://"));
+        assert!(invalid("iroh:///?addr=127.0.0.1:4433"));
+        assert!(invalid("iroh://not-a-node"));
+        assert!(invalid(&format!("iroh://{node_id}?relay=not%20a%20url")));
+        assert!(invalid(&format!("iroh://{node_id}?addr=127.0.0.1")));
+        assert!(invalid(&format!("iroh://{node_id}?addr=%+1")));
+    }
+}